@@ -1,5 +1,8 @@
 use std::{env, error::Error, process};
-use zilog_z80::{bus::Bus, cpu::CPU};
+use zilog_z80::{
+    bus::Bus,
+    cpu::{CPU, StopReason},
+};
 
 fn main() {
     if let Err(e) = load_execute() {
@@ -30,13 +33,14 @@ fn load_execute() -> Result<(), Box<dyn Error>> {
     c.reg.sp = 0xFF00;
 
     loop {
-        c.execute(&mut b);
-        if c.reg.pc == 0x0005 {
-            bdos_call(&c, &b)
-        }
-        if c.reg.pc == 0x0000 {
+        let reason = c.run_until(&mut b, u64::MAX, |c, _| {
+            c.reg.pc == 0x0005 || c.reg.pc == 0x0000
+        });
+        //  if CP/M warm boot, HALT or unknown opcode -> we exit
+        if reason != StopReason::Breakpoint || c.reg.pc == 0x0000 {
             break;
-        } //  if CP/M warm boot -> we exit
+        }
+        bdos_call(&c, &b)
     }
     Ok(())
 }
@@ -1,18 +1,127 @@
+use crate::io::{IoDevice, MemoryDevice};
+use crate::memory::Memory;
 use std::{
     fs::File,
     io::{self, prelude::*},
 };
 
 /// The Bus struct is hosting the Z80 memory map.
+///
+/// The physical memory is divided into pages, mapped into the slots of the 64 KB address space by a page table.
+/// A bus created with `Bus::new` has a single 64 KB page : its memory is directly addressed.
 pub struct Bus {
+    // Physical memory
     address_space: Vec<u8>,
-    rom_space: Option<ROMSpace>,
+    pages: PageTable,
+    // Attributes of the address ranges, and index + 1 of the region of each address (0 : RAM)
+    regions: Vec<RegionEntry>,
+    region_map: Box<[u8; 0x10000]>,
+    // Region of the ROM space set by set_romspace
+    romspace: Option<RegionId>,
+    // Value read where no memory answers
+    open_bus: u8,
+    io_device: Option<Box<dyn IoDevice>>,
+    page_mapper: Option<PageMapper>,
 }
 
-/// Start and end addresses of read-only (ROM) area.
-struct ROMSpace {
-    pub start: u16,
-    pub end: u16,
+/// Attributes of an address range of the bus, see `Bus::add_region`
+pub enum Region {
+    /// Memory read and written normally
+    Ram,
+    /// Read-only memory : write operations are ineffective
+    Rom,
+    /// No memory : reads return the open bus value, writes are ignored
+    Unmapped,
+    /// ROM with RAM underneath : reads return the ROM image (from the start of the range), writes go to the RAM
+    RomOverRam(Vec<u8>),
+    /// Memory-mapped device, receiving the reads and writes of the range
+    Device(Box<dyn MemoryDevice>),
+}
+
+struct RegionEntry {
+    start: u16,
+    end: u16,
+    region: Region,
+}
+
+/// Identifies a memory region of the bus
+pub type RegionId = usize;
+
+/// Function called on every OUT cycle with the port address and data, before the I/O device. It can switch the pages mapped by the bus.
+pub type PageMapper = Box<dyn FnMut(u16, u8, &mut PageTable) + Send>;
+
+/// Mapping of the physical memory pages into the slots of the 64 KB address space. Slot n covers the addresses
+/// from n * page size to (n + 1) * page size - 1. Pages are RAM, or ROM : writes to a ROM page are ignored.
+pub struct PageTable {
+    // log2 of the page size
+    shift: u32,
+    // Page mapped into each slot
+    slots: Vec<usize>,
+    // ROM attribute of each page
+    rom: Vec<bool>,
+}
+
+impl PageTable {
+    fn new(page_size: usize, pages: usize) -> PageTable {
+        assert!(
+            page_size.is_power_of_two() && page_size <= 0x10000,
+            "Page size must be a power of two, up to 64 KB"
+        );
+        PageTable {
+            shift: page_size.trailing_zeros(),
+            slots: (0..0x10000 / page_size).collect(),
+            rom: vec![false; pages],
+        }
+    }
+
+    /// Returns the page size in bytes
+    pub fn page_size(&self) -> usize {
+        1 << self.shift
+    }
+
+    /// Returns the number of slots of the address space (64 KB / page size)
+    pub fn slots(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns the number of physical pages
+    pub fn pages(&self) -> usize {
+        self.rom.len()
+    }
+
+    /// Maps a physical page into a slot of the address space
+    pub fn map(&mut self, slot: usize, page: usize) {
+        assert!(page < self.pages(), "Page {page} does not exist !");
+        self.slots[slot] = page;
+    }
+
+    /// Returns the physical page mapped into a slot
+    pub fn mapped(&self, slot: usize) -> usize {
+        self.slots[slot]
+    }
+
+    /// Sets a physical page as ROM (write operations will be ineffective) or RAM
+    pub fn set_rom(&mut self, page: usize, rom: bool) {
+        self.rom[page] = rom;
+    }
+
+    /// Returns true if a physical page is ROM
+    pub fn is_rom(&self, page: usize) -> bool {
+        self.rom[page]
+    }
+
+    // Returns the physical page mapped at an address, and the physical address
+    #[inline]
+    fn translate(&self, address: u16) -> (usize, usize) {
+        let page = self.slots[(address as usize) >> self.shift];
+        let offset = (address as usize) & ((1 << self.shift) - 1);
+        (page, (page << self.shift) | offset)
+    }
+}
+
+// Region index of each address, all RAM
+fn new_region_map() -> Box<[u8; 0x10000]> {
+    vec![0; 0x10000].into_boxed_slice().try_into().unwrap()
 }
 
 impl Bus {
@@ -20,116 +129,355 @@ impl Bus {
     pub fn new(size: u16) -> Bus {
         Bus {
             address_space: vec![0; (size as usize) + 1],
-            rom_space: None,
+            pages: PageTable::new(0x10000, 1),
+            regions: Vec::new(),
+            region_map: new_region_map(),
+            romspace: None,
+            open_bus: 0xFF,
+            io_device: None,
+            page_mapper: None,
+        }
+    }
+
+    /// Creates a new bus instance with `size` bytes of physical memory, divided into pages of `page_size` bytes
+    /// mapped into the address space. Slot n initially maps page n. The size must be a multiple of the page size.
+    /// ```rust
+    /// use zilog_z80::bus::Bus;
+    /// // 128 KB of memory, by 16 KB pages : 8 pages mapped into 4 slots
+    /// let mut b = Bus::new_paged(0x20000, 0x4000);
+    /// b.write_byte(0xC000, 0x01);
+    /// b.pages_mut().map(3, 7);
+    /// b.write_byte(0xC000, 0x07);
+    /// b.pages_mut().map(3, 3);
+    /// assert_eq!(b.read_byte(0xC000), 0x01);
+    /// ```
+    pub fn new_paged(size: usize, page_size: usize) -> Bus {
+        assert!(
+            size > 0 && size.is_multiple_of(page_size),
+            "Memory size must be a multiple of the page size"
+        );
+        Bus {
+            address_space: vec![0; size],
+            pages: PageTable::new(page_size, size / page_size),
+            regions: Vec::new(),
+            region_map: new_region_map(),
+            romspace: None,
+            open_bus: 0xFF,
+            io_device: None,
+            page_mapper: None,
         }
     }
 
-    /// Sets a ROM space. Write operations will be ineffective in this address range.
+    /// Returns the page table
+    pub fn pages(&self) -> &PageTable {
+        &self.pages
+    }
+
+    /// Returns the page table, to switch the pages mapped into the address space
+    pub fn pages_mut(&mut self) -> &mut PageTable {
+        &mut self.pages
+    }
+
+    /// Sets a function switching pages on port writes, like the bank switching hardware of the machine.
+    /// ```rust
+    /// use zilog_z80::bus::Bus;
+    /// let mut b = Bus::new_paged(0x20000, 0x4000);
+    /// // Port 7FFDh, bits 0-2 : page mapped at C000h
+    /// b.set_page_mapper(Box::new(|address, data, pages| {
+    ///     if address == 0x7FFD {
+    ///         pages.map(3, (data & 0x07) as usize)
+    ///     }
+    /// }));
+    /// b.io_write(0x7FFD, 0x05);
+    /// assert_eq!(b.pages().mapped(3), 5);
+    /// ```
+    pub fn set_page_mapper(&mut self, mapper: PageMapper) {
+        self.page_mapper = Some(mapper);
+    }
+
+    /// Sets the ROM space. Write operations will be ineffective in this address range, whatever the pages mapped.
+    /// There is a single ROM space : calling it again moves it. Use `add_region` with `Region::Rom` for several ROM ranges.
     /// ```rust
     /// use zilog_z80::bus::Bus;
     /// let mut b = Bus::new(0xFFFF);
     /// b.set_romspace(0xF000, 0xFFFF);
     /// ```
     pub fn set_romspace(&mut self, start: u16, end: u16) {
-        self.rom_space = Some(ROMSpace { start, end });
+        match self.romspace {
+            Some(id) => {
+                assert!(
+                    start <= end,
+                    "Region start {start:#06X} is above its end {end:#06X} !"
+                );
+                self.regions[id] = RegionEntry {
+                    start,
+                    end,
+                    region: Region::Rom,
+                };
+                self.map_regions();
+            }
+            None => self.romspace = Some(self.add_region(start, end, Region::Rom)),
+        }
+    }
+
+    /// Gives attributes to an address range, whatever the pages mapped. Addresses outside of any region are RAM.
+    /// A region added over another one takes precedence on the overlapping addresses. Returns the region id. Up to 255 regions can be added.
+    /// Memory-mapped devices are plugged into the bus with `Region::Device`.
+    /// ```rust
+    /// use zilog_z80::bus::{Bus, Region};
+    /// let mut b = Bus::new(0xFFFF);
+    /// b.add_region(0x0000, 0x2FFF, Region::Rom);
+    /// b.add_region(0x3000, 0x37FF, Region::Unmapped);
+    /// b.write_byte(0x0000, 0x01);
+    /// assert_eq!(b.read_byte(0x0000), 0x00);
+    /// assert_eq!(b.read_byte(0x3000), 0xFF);
+    /// ```
+    pub fn add_region(&mut self, start: u16, end: u16, region: Region) -> RegionId {
+        assert!(
+            start <= end,
+            "Region start {start:#06X} is above its end {end:#06X} !"
+        );
+        assert!(self.regions.len() < 255, "Too many memory regions !");
+        self.regions.push(RegionEntry { start, end, region });
+        let id = self.regions.len();
+        self.region_map[start as usize..=end as usize].fill(id as u8);
+        id - 1
+    }
+
+    /// Changes the attributes of a region, keeping its address range : a ROM can be switched off by making its region RAM.
+    /// ```rust
+    /// use zilog_z80::bus::{Bus, Region};
+    /// let mut b = Bus::new(0xFFFF);
+    /// let rom = b.add_region(0x0000, 0x3FFF, Region::RomOverRam(vec![0xC3, 0x00, 0x01]));
+    /// b.write_byte(0x0000, 0x01);         // Written to the RAM underneath
+    /// assert_eq!(b.read_byte(0x0000), 0xC3);
+    /// b.set_region(rom, Region::Ram);
+    /// assert_eq!(b.read_byte(0x0000), 0x01);
+    /// ```
+    pub fn set_region(&mut self, id: RegionId, region: Region) {
+        self.regions[id].region = region;
+    }
+
+    // Rebuilds the region of each address, the last added region taking precedence
+    fn map_regions(&mut self) {
+        self.region_map.fill(0);
+        for (i, r) in self.regions.iter().enumerate() {
+            self.region_map[r.start as usize..=r.end as usize].fill(i as u8 + 1);
+        }
+    }
+
+    /// Sets the value read at unmapped addresses, and outside of the physical memory. Defaults to 0xFF.
+    pub fn set_open_bus(&mut self, value: u8) {
+        self.open_bus = value;
+    }
+
+    /// Connects a device to the I/O ports. It will receive every IN and OUT cycle of the CPU.
+    pub fn set_io_device(&mut self, device: Box<dyn IoDevice>) {
+        self.io_device = Some(device);
+    }
+
+    /// Reads a byte from an I/O port. Returns 0xFF (floating data bus) if no device is connected.
+    pub fn io_read(&mut self, address: u16) -> u8 {
+        match self.io_device.as_mut() {
+            Some(d) => d.read(address),
+            None => 0xFF,
+        }
+    }
+
+    /// Writes a byte to an I/O port, passed to the page mapper then to the device. Ignored if no device is connected.
+    pub fn io_write(&mut self, address: u16, data: u8) {
+        if let Some(mapper) = self.page_mapper.as_mut() {
+            mapper(address, data, &mut self.pages)
+        }
+        if let Some(d) = self.io_device.as_mut() {
+            d.write(address, data)
+        }
     }
 
     /// Reads a slice of bytes from memory
     pub fn read_mem_slice(&self, start: usize, end: usize) -> Vec<u8> {
-        if end > self.address_space.len() {
+        if end > 0xFFFF {
             panic!("Read operation after the end of address space !")
         }
-        self.address_space[start..=end].to_vec()
+        (start..=end).map(|m| self.read_byte(m as u16)).collect()
     }
 
     /// Clears a slice of bytes in memory
     pub fn clear_mem_slice(&mut self, start: usize, end: usize) {
-        if end > self.address_space.len() {
+        if end > 0xFFFF {
             panic!("Write operation after the end of address space !")
         }
         for m in start..=end {
-            self.address_space[m] = 0;
+            self.poke(m as u16, 0);
         }
     }
 
-    /// Reads a byte from memory
+    /// Reads a byte from memory. Memory-mapped devices are peeked at, without side effects :
+    /// the CPU reads them with `MemoryDevice::read`, through the `Memory` trait.
+    #[inline]
     pub fn read_byte(&self, address: u16) -> u8 {
-        if address as usize >= self.address_space.len() {
-            return 0;
+        match self.region(address) {
+            None
+            | Some(RegionEntry {
+                region: Region::Ram | Region::Rom,
+                ..
+            }) => {
+                let (_, physical) = self.pages.translate(address);
+                match self.address_space.get(physical) {
+                    Some(data) => *data,
+                    None => self.open_bus,
+                }
+            }
+            Some(RegionEntry {
+                region: Region::Unmapped,
+                ..
+            }) => self.open_bus,
+            Some(RegionEntry {
+                start,
+                region: Region::RomOverRam(image),
+                ..
+            }) => *image
+                .get((address - start) as usize)
+                .unwrap_or(&self.open_bus),
+            Some(RegionEntry {
+                region: Region::Device(device),
+                ..
+            }) => device.peek(address),
         }
-        self.address_space[usize::from(address)]
     }
 
     /// Writes a byte to memory
+    #[inline]
     pub fn write_byte(&mut self, address: u16, data: u8) {
-        if address as usize >= self.address_space.len() {
-            return;
+        // Write operations are ineffective in ROM regions and pages, and where there is no memory
+        match self.region_mut(address) {
+            Some(RegionEntry {
+                region: Region::Rom | Region::Unmapped,
+                ..
+            }) => return,
+            Some(RegionEntry {
+                region: Region::Device(device),
+                ..
+            }) => return device.write(address, data),
+            _ => {}
         }
-        // if rom space is declared, and write operation is requested in rom area : we exit
-        if self.rom_space.is_some()
-            && address >= self.rom_space.as_ref().unwrap().start
-            && address <= self.rom_space.as_ref().unwrap().end
-        {
+        let (page, physical) = self.pages.translate(address);
+        if self.pages.rom[page] {
             return;
         };
-        self.address_space[usize::from(address)] = data;
+        if let Some(m) = self.address_space.get_mut(physical) {
+            *m = data;
+        }
+    }
+
+    // Returns the region of an address, if any
+    #[inline]
+    fn region(&self, address: u16) -> Option<&RegionEntry> {
+        match self.region_map[address as usize] {
+            0 => None,
+            id => Some(&self.regions[id as usize - 1]),
+        }
+    }
+
+    #[inline]
+    fn region_mut(&mut self, address: u16) -> Option<&mut RegionEntry> {
+        match self.region_map[address as usize] {
+            0 => None,
+            id => Some(&mut self.regions[id as usize - 1]),
+        }
+    }
+
+    // Writes a byte to memory, even in ROM
+    fn poke(&mut self, address: u16, data: u8) {
+        let (_, physical) = self.pages.translate(address);
+        if let Some(m) = self.address_space.get_mut(physical) {
+            *m = data;
+        }
     }
 
     /// Reads a word stored in memory in little endian byte order, returns this word in BE byte order
     pub fn read_word(&self, address: u16) -> u16 {
-        if address as usize >= self.address_space.len() {
-            return 0;
-        }
-        u16::from(self.address_space[usize::from(address)])
-            | (u16::from(self.address_space[usize::from(address + 1)]) << 8)
+        u16::from(self.read_byte(address))
+            | (u16::from(self.read_byte(address.wrapping_add(1))) << 8)
     }
 
     /// Reads a word stored in memory in little endian byte order, returns this word in LE byte order
     pub fn read_le_word(&self, address: u16) -> u16 {
-        if address as usize >= self.address_space.len() {
-            return 0;
-        }
-        u16::from(self.address_space[usize::from(address)]) << 8
-            | (u16::from(self.address_space[usize::from(address + 1)]))
+        self.read_word(address).swap_bytes()
     }
 
     /// Reads a dword stored in memory in little endian byte order, returns this dword in LE byte order
     pub fn read_le_dword(&self, address: u16) -> u32 {
-        if address as usize >= self.address_space.len() {
-            return 0;
-        }
-        u32::from(self.address_space[usize::from(address)]) << 24
-            | u32::from(self.address_space[usize::from(address + 1)]) << 16
-            | u32::from(self.address_space[usize::from(address + 2)]) << 8
-            | u32::from(self.address_space[usize::from(address + 3)])
+        (u32::from(self.read_le_word(address)) << 16)
+            | u32::from(self.read_le_word(address.wrapping_add(2)))
     }
 
     /// Writes a word to memory in little endian byte order
     pub fn write_word(&mut self, address: u16, data: u16) {
-        if address as usize >= self.address_space.len() {
-            return;
-        }
-        // if rom space is declared, and write operation is requested in rom area : we exit
-        if self.rom_space.is_some()
-            && address >= self.rom_space.as_ref().unwrap().start
-            && address <= self.rom_space.as_ref().unwrap().end
-        {
-            return;
-        };
-        self.address_space[usize::from(address)] = (data & 0xFF) as u8;
-        self.address_space[usize::from(address + 1)] = (data >> 8) as u8;
+        self.write_byte(address, (data & 0xFF) as u8);
+        self.write_byte(address.wrapping_add(1), (data >> 8) as u8);
     }
 
-    /// Loads binary data from disk into memory at $0000 + offset. Returns size of loaded file.
+    /// Loads binary data from disk into memory at $0000 + offset, even in ROM. Returns size of loaded file.
+    /// Panics if the data does not fit in the address space, or in the physical memory mapped there.
     pub fn load_bin(&mut self, file: &str, org: u16) -> io::Result<usize> {
-        if org as usize >= self.address_space.len() {
+        let mut f = File::open(file)?;
+        let mut buf = Vec::new();
+        f.read_to_end(&mut buf)?;
+        if org as usize + buf.len() > 0x10000 {
             panic!("Write operation after the end of address space !")
         }
+        if (0..buf.len())
+            .any(|i| self.pages.translate(org + i as u16).1 >= self.address_space.len())
+        {
+            panic!("Write operation after the end of physical memory !")
+        }
+        for (i, data) in buf.iter().enumerate() {
+            self.poke(org + i as u16, *data);
+        }
+        Ok(buf.len())
+    }
+
+    /// Loads binary data from disk into physical memory, at the start of a page, whether it is mapped or not.
+    /// Used to load ROM images into their pages. Returns size of loaded file.
+    pub fn load_bin_page(&mut self, file: &str, page: usize) -> io::Result<usize> {
         let mut f = File::open(file)?;
         let mut buf = Vec::new();
-        let s = f.read_to_end(&mut buf)?;
-        self.address_space[org as usize..(buf.len() + org as usize)].clone_from_slice(&buf[..]);
-        Ok(s)
+        f.read_to_end(&mut buf)?;
+        let start = page << self.pages.shift;
+        if start + buf.len() > self.address_space.len() {
+            panic!("Write operation after the end of physical memory !")
+        }
+        self.address_space[start..start + buf.len()].clone_from_slice(&buf);
+        Ok(buf.len())
+    }
+}
+
+impl Memory for Bus {
+    // Memory-mapped devices are read by the CPU, with side effects
+    #[inline]
+    fn read_byte(&mut self, address: u16) -> u8 {
+        if let Some(RegionEntry {
+            region: Region::Device(device),
+            ..
+        }) = self.region_mut(address)
+        {
+            return device.read(address);
+        }
+        Bus::read_byte(self, address)
+    }
+
+    #[inline]
+    fn write_byte(&mut self, address: u16, data: u8) {
+        Bus::write_byte(self, address, data)
+    }
+
+    fn io_read(&mut self, address: u16) -> u8 {
+        Bus::io_read(self, address)
+    }
+
+    fn io_write(&mut self, address: u16, data: u8) {
+        Bus::io_write(self, address, data)
     }
 }
 
@@ -149,8 +497,10 @@ mod tests {
     #[test]
     fn read_invalid() {
         let mut b = Bus::new(0x7FFF);
-        b.write_byte(0x8000, 0xFF);
-        assert_eq!(b.read_byte(0x8000), 0);
+        b.write_byte(0x8000, 0x00);
+        assert_eq!(b.read_byte(0x8000), 0xFF);
+        b.set_open_bus(0x00);
+        assert_eq!(b.read_byte(0x8000), 0x00);
     }
 
     #[test]
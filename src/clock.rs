@@ -0,0 +1,88 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// A source of time for the timed execution of the CPU (`CPU::execute_timed`).
+///
+/// The host clock is used by default. A virtual clock makes throttled runs deterministic, for tests or headless runs.
+pub trait Clock: Send {
+    /// Returns the time elapsed since a fixed origin. It must never go backwards.
+    fn now(&self) -> Duration;
+}
+
+/// Monotonic host clock, unaffected by changes of the system time.
+pub struct HostClock {
+    origin: Instant,
+}
+
+impl Default for HostClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HostClock {
+    /// Creates a host clock, its origin being the time of creation
+    pub fn new() -> HostClock {
+        HostClock {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Clock for HostClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+/// Virtual clock, only moving forward when advanced. Clones share the same time,
+/// so a clone kept by the caller drives the clock given to the CPU.
+/// ```rust
+/// use std::time::Duration;
+/// use zilog_z80::{bus::Bus, clock::VirtualClock, cpu::CPU};
+/// let mut b = Bus::new(0xFFFF);
+/// let mut c = CPU::new();
+/// let clock = VirtualClock::new();
+/// c.set_clock(Box::new(clock.clone()));
+/// c.set_freq(1.0);                    // 1 MHz : 16000 cycles per 16 ms slice
+/// let mut sleep_time = None;
+/// while sleep_time.is_none() {
+///     sleep_time = c.execute_timed(&mut b);
+/// }
+/// // The slice was executed in no time : the whole slice duration is left to sleep
+/// assert_eq!(sleep_time, Some(Duration::from_millis(16)));
+/// // The caller sleeps : the next slice is on time
+/// clock.advance(Duration::from_millis(16));
+/// sleep_time = None;
+/// while sleep_time.is_none() {
+///     sleep_time = c.execute_timed(&mut b);
+/// }
+/// assert_eq!(sleep_time, Some(Duration::from_millis(16)));
+/// ```
+#[derive(Clone, Default)]
+pub struct VirtualClock {
+    nanos: Arc<AtomicU64>,
+}
+
+impl VirtualClock {
+    /// Creates a virtual clock, starting at 0
+    pub fn new() -> VirtualClock {
+        VirtualClock::default()
+    }
+
+    /// Moves the clock forward
+    pub fn advance(&self, d: Duration) {
+        self.nanos.fetch_add(d.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+    }
+}
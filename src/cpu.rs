@@ -1,50 +1,237 @@
 use crate::bit;
 use crate::bus::Bus;
+use crate::clock::{Clock, HostClock};
 use crate::cycles::{CYCLES, CYCLES_CB, CYCLES_DD_FD, CYCLES_ED};
+use crate::daisy_chain::DaisyChainDevice;
+use crate::dasm::disassemble;
+use crate::memory::Memory;
 use crate::registers::Registers;
-use std::time::SystemTime;
+use crate::scheduler::{EventCallback, EventId, Scheduler};
+use std::{collections::BTreeMap, fmt, time::Duration};
 
-pub struct CPU {
+/// The Z80 CPU, executing code from a memory implementing the `Memory` trait (`Bus` by default).
+pub struct CPU<M = Bus> {
     pub reg: Registers,
     pub alt: Registers,
     halt: bool,
     pub debug: Debug,
-    int: Option<u8>,
+    // Interrupt sources holding the INT line, with the data they put on the bus when acknowledged. Lower ids have priority.
+    int_sources: BTreeMap<u8, Vec<u8>>,
+    // Z80 family peripherals, by decreasing priority
+    daisy_chain: Vec<Box<dyn DaisyChainDevice>>,
+    // Instruction being executed from the data bus (interrupt mode 0 and 1)
+    int_data: Option<Vec<u8>>,
     nmi: bool,
     im: u8,
     iff1: bool,
     iff2: bool,
+    // Copy of the flags if the last instruction modified them, 0 otherwise (used by SCF / CCF)
+    q: u8,
+    // Set by the instructions modifying the flags
+    flags_written: bool,
+    // Internal MEMPTR (WZ) register
+    memptr: u16,
+    // Total clock cycles executed
+    cycles: u64,
+    // Set by EI : maskable interrupts are not accepted before the next instruction has been executed
+    int_delay: bool,
+    // Set by a DD / FD prefix not followed by an indexed instruction : no interrupt is accepted before the next instruction
+    prefix: bool,
+    // Opcode read after an ignored DD / FD prefix, and its address : it is not fetched again by the next step
+    next_opcode: Option<(u16, u8)>,
     slice_duration: u32,
-    // Defaults to 35000 cycles per 16ms slice (2.1 Mhz).
+    // CPU frequency in Hz. Defaults to 35000 cycles per 16ms slice (2.1875 Mhz).
+    freq: f64,
     // cycles = clock speed in Hz / required frames-per-second
     slice_max_cycles: u32,
     slice_current_cycles: u32,
-    slice_start_time: SystemTime,
+    // Time source of the timed execution
+    clock: Box<dyn Clock>,
+    // Clock time when the timed execution started, and cycles executed since then
+    timed_start: Option<Duration>,
+    timed_cycles: u64,
+    // Events fired when the cycle counter reaches their due time
+    scheduler: Scheduler<M>,
+    // How unknown instructions are executed
+    unknown_policy: UnknownOpcodePolicy<M>,
 }
 
-impl CPU {
-    /// Creates a new CPU instance. 'Size' will be its top address.
-    pub fn new() -> CPU {
+impl<M: Memory> Default for CPU<M> {
+    /// Creates a new CPU instance, executing code from any memory type : `CPU::<MyMemory>::default()`
+    fn default() -> Self {
         CPU {
             reg: Registers::new(),
             alt: Registers::new(),
             halt: false,
             debug: Debug::new(),
-            int: None,
+            int_sources: BTreeMap::new(),
+            daisy_chain: Vec::new(),
+            int_data: None,
             nmi: false,
             im: 0,
             iff1: false,
             iff2: false,
+            q: 0,
+            flags_written: false,
+            memptr: 0,
+            cycles: 0,
+            int_delay: false,
+            prefix: false,
+            next_opcode: None,
             slice_duration: 16,
+            freq: 2_187_500.0,
             slice_max_cycles: 35000,
             slice_current_cycles: 0,
-            slice_start_time: SystemTime::now(),
+            clock: Box::new(HostClock::new()),
+            timed_start: None,
+            timed_cycles: 0,
+            scheduler: Scheduler::default(),
+            unknown_policy: UnknownOpcodePolicy::Nop,
         }
     }
+}
+
+impl CPU {
+    /// Creates a new CPU instance, executing code from a `Bus`.
+    pub fn new() -> CPU {
+        CPU::default()
+    }
+}
+
+impl<M: Memory> CPU<M> {
+    /// Creates a new CPU instance in the documented power-on state : AF and SP set to FFFFh, PC, I and R cleared,
+    /// interrupts disabled and interrupt mode 0. The other registers are cleared, see `power_on_with` to set them.
+    pub fn power_on() -> CPU<M> {
+        CPU::power_on_with(UndefinedRegisters::Fill(0x0000))
+    }
+
+    /// Creates a new CPU instance in the power-on state, with the contents of the registers undefined at power-on.
+    /// ```rust
+    /// use zilog_z80::cpu::{CPU, UndefinedRegisters};
+    /// let c: CPU = CPU::power_on_with(UndefinedRegisters::Random(42));
+    /// assert_eq!(c.reg.get_af(), 0xFFFF);
+    /// assert_eq!(c.reg.sp, 0xFFFF);
+    /// assert_eq!(c.reg.pc, 0x0000);
+    /// // The same seed always gives the same contents
+    /// let d: CPU = CPU::power_on_with(UndefinedRegisters::Random(42));
+    /// assert_eq!(c.reg.get_hl(), d.reg.get_hl());
+    /// ```
+    pub fn power_on_with(undefined: UndefinedRegisters) -> CPU<M> {
+        let mut c = CPU::<M>::default();
+        let mut next = match undefined {
+            UndefinedRegisters::Fill(value) => Box::new(move || value) as Box<dyn FnMut() -> u16>,
+            UndefinedRegisters::Random(seed) => {
+                let mut state = seed;
+                Box::new(move || splitmix64(&mut state) as u16)
+            }
+        };
+        for reg in [&mut c.reg, &mut c.alt] {
+            reg.set_bc(next());
+            reg.set_de(next());
+            reg.set_hl(next());
+        }
+        c.reg.set_ix(next());
+        c.reg.set_iy(next());
+        c.alt.set_af(next());
+        c.reg.set_af(0xFFFF);
+        c.reg.sp = 0xFFFF;
+        c
+    }
+
+    /// Hardware reset (RESET line) : clears PC, I, R, IFF1 and IFF2, selects interrupt mode 0 and leaves the HALT state.
+    /// The other registers are preserved. Pending requests of the interrupt sources are kept, a pending NMI is cancelled.
+    /// ```rust
+    /// use zilog_z80::{bus::Bus, cpu::CPU};
+    /// let mut b = Bus::new(0xFFFF);
+    /// let mut c = CPU::new();
+    /// b.write_byte(0x0000, 0x3E);         // LD A,$12
+    /// b.write_byte(0x0001, 0x12);
+    /// b.write_byte(0x0002, 0x76);         // HALT
+    /// c.execute(&mut b);
+    /// c.execute(&mut b);
+    /// c.reset();
+    /// assert!(!c.is_halted());
+    /// assert_eq!(c.reg.pc, 0x0000);
+    /// assert_eq!(c.reg.a, 0x12);
+    /// ```
+    pub fn reset(&mut self) {
+        self.reg.pc = 0;
+        self.reg.i = 0;
+        self.reg.r = 0;
+        self.iff1 = false;
+        self.iff2 = false;
+        self.im = 0;
+        self.halt = false;
+        self.nmi = false;
+        self.int_delay = false;
+        self.prefix = false;
+        self.next_opcode = None;
+        self.int_data = None;
+        self.q = 0;
+    }
 
-    /// Creates a maskable interrupt request
+    /// Creates a maskable interrupt request from interrupt source 0 (see `int_assert`). The byte is put on the data bus by the interrupting device:
+    /// the instruction executed in interrupt mode 0 (usually a RST), or the vector low byte in interrupt mode 2.
     pub fn int_request(&mut self, byte: u8) {
-        self.int = Some(byte);
+        self.int_assert(0, &[byte]);
+    }
+
+    /// Creates a maskable interrupt request from interrupt source 0, with a multi-byte instruction put on the data bus, to be executed in interrupt mode 0.
+    /// The address of the interrupted instruction is stacked if it is a CALL.
+    /// ```rust
+    /// use zilog_z80::{bus::Bus, cpu::CPU};
+    /// let mut b = Bus::new(0xFFFF);
+    /// let mut c = CPU::new();
+    /// b.write_byte(0x0000, 0xFB);         // EI
+    /// b.write_byte(0x0001, 0x00);         // NOP
+    /// c.reg.sp = 0xFF00;
+    /// c.execute(&mut b);
+    /// c.execute(&mut b);
+    /// c.int_request_bytes(&[0xCD, 0x00, 0x10]); // CALL $1000
+    /// c.execute(&mut b);
+    /// assert_eq!(c.reg.pc, 0x1000);
+    /// assert_eq!(b.read_word(0xFEFE), 0x0002);
+    /// ```
+    pub fn int_request_bytes(&mut self, bytes: &[u8]) {
+        self.int_assert(0, bytes);
+    }
+
+    /// Asserts the INT line for an interrupt source. The request stays pending, even while interrupts are disabled,
+    /// until the CPU acknowledges it or the source releases the line. When several sources are pending, the lowest id is acknowledged first.
+    /// The bytes are put on the data bus by the source when acknowledged : the instruction executed in interrupt mode 0, or the vector low byte in interrupt mode 2.
+    /// No byte at all is read as 0xFF, the value of the floating data bus.
+    /// ```rust
+    /// use zilog_z80::{bus::Bus, cpu::CPU};
+    /// let mut b = Bus::new(0xFFFF);
+    /// let mut c = CPU::new();
+    /// b.write_byte(0x0000, 0xED);         // IM 2
+    /// b.write_byte(0x0001, 0x5E);
+    /// b.write_byte(0x0002, 0xFB);         // EI
+    /// b.write_byte(0x0003, 0x00);         // NOP
+    /// b.write_word(0x0010, 0x1000);       // Interrupt vectors
+    /// b.write_word(0x0020, 0x2000);
+    /// c.reg.sp = 0xFF00;
+    /// c.int_assert(2, &[0x20]);           // Requests made while interrupts are disabled stay pending
+    /// c.int_assert(1, &[0x10]);
+    /// for _ in 0..3 {
+    ///     c.execute(&mut b);
+    /// }
+    /// c.execute(&mut b);
+    /// assert_eq!(c.reg.pc, 0x1000);       // Source 1 has priority
+    /// ```
+    pub fn int_assert(&mut self, source: u8, bytes: &[u8]) {
+        let bytes = if bytes.is_empty() { &[0xFF] } else { bytes };
+        self.int_sources.insert(source, bytes.to_vec());
+    }
+
+    /// Releases the INT line for an interrupt source, cancelling its pending request
+    pub fn int_release(&mut self, source: u8) {
+        self.int_sources.remove(&source);
+    }
+
+    /// Adds a device at the end of the interrupt daisy chain : it has a lower priority than the devices already added.
+    pub fn daisy_chain_add(&mut self, device: Box<dyn DaisyChainDevice>) {
+        self.daisy_chain.push(device);
     }
 
     /// Creates a non-maskable interrupt request
@@ -52,72 +239,417 @@ impl CPU {
         self.nmi = true;
     }
 
+    /// Returns true if a maskable interrupt is requested, by an interrupt source or by the daisy chain
+    pub fn int_pending(&self) -> bool {
+        !self.int_sources.is_empty() || self.daisy_chain_request().is_some()
+    }
+
+    /// Cancels the pending maskable interrupt requests of all the interrupt sources. Daisy chain devices keep their own request state.
+    pub fn int_cancel(&mut self) {
+        self.int_sources.clear();
+    }
+
+    /// Returns true if a non-maskable interrupt is requested
+    pub fn nmi_pending(&self) -> bool {
+        self.nmi
+    }
+
+    /// Cancels the pending non-maskable interrupt request
+    pub fn nmi_cancel(&mut self) {
+        self.nmi = false;
+    }
+
+    /// Returns the interrupt mode (0, 1 or 2)
+    pub fn im(&self) -> u8 {
+        self.im
+    }
+
+    /// Sets the interrupt mode, like IM 0 / IM 1 / IM 2. Panics if the mode is not 0, 1 or 2.
+    pub fn set_im(&mut self, im: u8) {
+        assert!(im <= 2, "invalid interrupt mode {im}");
+        self.im = im;
+    }
+
+    /// Returns the IFF1 flip-flop : maskable interrupts are accepted when set
+    pub fn iff1(&self) -> bool {
+        self.iff1
+    }
+
+    /// Sets the IFF1 flip-flop
+    pub fn set_iff1(&mut self, iff1: bool) {
+        self.iff1 = iff1;
+    }
+
+    /// Returns the IFF2 flip-flop : the copy of IFF1 saved during a non-maskable interrupt, restored by RETN
+    pub fn iff2(&self) -> bool {
+        self.iff2
+    }
+
+    /// Sets the IFF2 flip-flop
+    pub fn set_iff2(&mut self, iff2: bool) {
+        self.iff2 = iff2;
+    }
+
+    /// Returns true if the CPU is halted : PC points to the HALT instruction, executed again until an interrupt is accepted
+    pub fn is_halted(&self) -> bool {
+        self.halt
+    }
+
+    /// Sets the HALT state, as restored from a snapshot : PC must point to the HALT instruction.
+    /// Use `release_halt` to resume the execution.
+    pub fn set_halted(&mut self, halt: bool) {
+        self.halt = halt;
+    }
+
+    /// Leaves the HALT state, the execution resuming after the HALT instruction. Does nothing if the CPU is not halted.
+    /// ```rust
+    /// use zilog_z80::{bus::Bus, cpu::CPU};
+    /// let mut b = Bus::new(0xFFFF);
+    /// let mut c = CPU::new();
+    /// b.write_byte(0x0000, 0x76);         // HALT
+    /// c.execute(&mut b);
+    /// assert!(c.is_halted());
+    /// c.release_halt();
+    /// assert!(!c.is_halted());
+    /// assert_eq!(c.reg.pc, 0x0001);
+    /// ```
+    pub fn release_halt(&mut self) {
+        if self.halt {
+            self.halt = false;
+            self.reg.pc = self.reg.pc.wrapping_add(1);
+        }
+    }
+
+    /// Returns the internal MEMPTR (WZ) register. It is not accessible by the Z80 instructions, but it shows in the undocumented flags of BIT n,(HL).
+    pub fn memptr(&self) -> u16 {
+        self.memptr
+    }
+
     /// Shortcut to reg.flags.to_byte()
     pub fn flags(&self) -> u8 {
         self.reg.flags.to_byte()
     }
 
-    /// Fetches and executes one instruction from (pc). Returns consumed clock cycles.
-    pub fn execute(&mut self, bus: &mut Bus) -> u32 {
+    /// Returns the total number of clock cycles executed since the CPU was created
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Fetches and executes one instruction from (pc). Returns consumed clock cycles, or 0xFF for a trapped unknown opcode (see `try_execute`) :
+    /// in that case 0xFF is not a cycle count, nothing has been executed nor added to `cycles()`. Use `try_execute` to tell both cases apart.
+    /// When an interrupt is accepted, the step only performs the interrupt acknowledge and returns its cycles
+    /// (NMI : 11, IM 0 / IM 1 : 13 for a RST, IM 2 : 19) : the first instruction of the handler is executed by the next step.
+    pub fn execute(&mut self, bus: &mut M) -> u32 {
+        self.try_execute(bus).unwrap_or(0xFF)
+    }
+
+    /// Fetches and executes one instruction from (pc), like `execute`. Returns consumed clock cycles,
+    /// or an error when an unknown instruction is trapped (see `set_unknown_opcode_policy`) : PC is left on the unknown instruction.
+    /// ```rust
+    /// use zilog_z80::{bus::Bus, cpu::{CPU, ExecError, Prefix, UnknownOpcodePolicy}};
+    /// let mut b = Bus::new(0xFFFF);
+    /// let mut c = CPU::new();
+    /// c.set_unknown_opcode_policy(UnknownOpcodePolicy::Trap);
+    /// b.write_byte(0x0000, 0x00);         // NOP
+    /// b.write_byte(0x0001, 0xED);         // Unknown instruction
+    /// b.write_byte(0x0002, 0x00);
+    /// assert_eq!(c.try_execute(&mut b), Ok(4));
+    /// let ExecError::UnknownOpcode(unknown) = c.try_execute(&mut b).unwrap_err();
+    /// assert_eq!(unknown.address, 0x0001);
+    /// assert_eq!(unknown.prefix, Prefix::Ed);
+    /// assert_eq!(unknown.bytes, vec![0xED, 0x00]);
+    /// assert_eq!(c.reg.pc, 0x0001);
+    /// ```
+    pub fn try_execute(&mut self, bus: &mut M) -> Result<u32, ExecError> {
+        // Wait states inserted by the memory lengthen the instruction
+        let result = self.step(bus).map(|cycles| cycles + bus.wait_states());
+        if let Ok(cycles) = result {
+            self.cycles += cycles as u64;
+        }
+        self.fire_events(bus);
+        result
+    }
+
+    /// Sets how unknown instructions are executed. Defaults to `UnknownOpcodePolicy::Nop`.
+    /// ```rust
+    /// use zilog_z80::{bus::Bus, cpu::{CPU, UnknownOpcodePolicy}};
+    /// let mut b = Bus::new(0xFFFF);
+    /// let mut c = CPU::new();
+    /// b.write_byte(0x0000, 0xED);         // Unknown instruction
+    /// b.write_byte(0x0001, 0x00);
+    /// assert_eq!(c.execute(&mut b), 8);
+    /// assert_eq!(c.reg.pc, 0x0002);
+    /// c.reg.pc = 0x0000;
+    /// c.set_unknown_opcode_policy(UnknownOpcodePolicy::Trap);
+    /// assert_eq!(c.execute(&mut b), 0xFF);
+    /// assert_eq!(c.reg.pc, 0x0000);
+    /// ```
+    pub fn set_unknown_opcode_policy(&mut self, policy: UnknownOpcodePolicy<M>) {
+        self.unknown_policy = policy;
+    }
+
+    /// Schedules a function to be called once, when `delay` more clock cycles have been executed.
+    /// Events are fired between instructions, once the cycle counter has reached their due time.
+    pub fn schedule(&mut self, delay: u64, callback: EventCallback<M>) -> EventId {
+        self.scheduler.add(self.cycles + delay, None, callback)
+    }
+
+    /// Schedules a function to be called every `period` clock cycles, the first time `period` cycles from now.
+    /// The period is counted from the due time of the previous call, so late calls do not accumulate drift.
+    /// ```rust
+    /// use zilog_z80::{bus::Bus, cpu::CPU};
+    /// let mut b = Bus::new(0xFFFF);
+    /// let mut c = CPU::new();
+    /// // ZX Spectrum 48K : a maskable interrupt at each video frame, every 69888 T-states
+    /// c.schedule_periodic(69888, Box::new(|c, _| c.int_request(0xFF)));
+    /// assert_eq!(c.next_event(), Some(69888));
+    /// ```
+    pub fn schedule_periodic(&mut self, period: u64, callback: EventCallback<M>) -> EventId {
+        assert!(period > 0, "The period of an event can not be 0");
+        self.scheduler
+            .add(self.cycles + period, Some(period), callback)
+    }
+
+    /// Cancels a scheduled event. Returns false if the event was not found (single event already fired).
+    pub fn cancel_event(&mut self, id: EventId) -> bool {
+        self.scheduler.cancel(id)
+    }
+
+    /// Returns the cycle counter value at which the next event is due
+    pub fn next_event(&self) -> Option<u64> {
+        self.scheduler.next_due()
+    }
+
+    /// Executes instructions until the next scheduled event is due, and fires it. Returns the cycle counter value the event was due at,
+    /// or None if no event is scheduled. The cycle counter may exceed the due time by the cycles of the last instruction.
+    /// Stops early with an error when an unknown instruction is trapped (see `try_execute`).
+    pub fn run_to_next_event(&mut self, bus: &mut M) -> Result<Option<u64>, ExecError> {
+        let Some(due) = self.scheduler.next_due() else {
+            return Ok(None);
+        };
+        while self.cycles < due {
+            self.try_execute(bus)?;
+        }
+        Ok(Some(due))
+    }
+
+    // Calls the events due at the current cycle counter value, earliest first
+    fn fire_events(&mut self, bus: &mut M) {
+        while let Some(mut event) = self.scheduler.take_due(self.cycles) {
+            (event.callback)(self, bus);
+            self.scheduler.fired(event);
+        }
+    }
+
+    /// Executes instructions until at least `cycles` clock cycles have been consumed. Returns the number of cycles executed beyond the budget.
+    /// Stops early with an error when an unknown instruction is trapped (see `try_execute`).
+    /// ```rust
+    /// use zilog_z80::{bus::Bus, cpu::CPU};
+    /// let mut b = Bus::new(0xFFFF);
+    /// let mut c = CPU::new();
+    /// // Memory is filled with NOPs (4 cycles each)
+    /// assert_eq!(c.run_for_cycles(&mut b, 10), Ok(2));
+    /// assert_eq!(c.cycles(), 12);
+    /// ```
+    pub fn run_for_cycles(&mut self, bus: &mut M, cycles: u64) -> Result<u64, ExecError> {
+        let target = self.cycles.saturating_add(cycles);
+        while self.cycles < target {
+            self.try_execute(bus)?;
+        }
+        Ok(self.cycles - target)
+    }
+
+    /// Executes instructions until `breakpoint` returns true, a HALT instruction is executed, an unknown opcode is trapped,
+    /// or at least `max_cycles` clock cycles have been consumed. The breakpoint is checked after each instruction.
+    /// ```rust
+    /// use zilog_z80::{bus::Bus, cpu::{CPU, StopReason}};
+    /// let mut b = Bus::new(0xFFFF);
+    /// let mut c = CPU::new();
+    /// b.write_byte(0x0000, 0x3C);     // INC A
+    /// b.write_byte(0x0001, 0xC3);     // JP 0x0000
+    /// b.write_word(0x0002, 0x0000);
+    /// let reason = c.run_until(&mut b, 1_000_000, |c, _| c.reg.a == 0x10);
+    /// assert_eq!(reason, StopReason::Breakpoint);
+    /// assert_eq!(c.reg.pc, 0x0001);
+    /// ```
+    pub fn run_until<F>(&mut self, bus: &mut M, max_cycles: u64, mut breakpoint: F) -> StopReason
+    where
+        F: FnMut(&CPU<M>, &M) -> bool,
+    {
+        let target = self.cycles.saturating_add(max_cycles);
+        loop {
+            if self.try_execute(bus).is_err() {
+                return StopReason::UnknownOpcode;
+            }
+            if breakpoint(self, bus) {
+                return StopReason::Breakpoint;
+            }
+            if self.halt {
+                return StopReason::Halt;
+            }
+            if self.cycles >= target {
+                return StopReason::CycleLimit;
+            }
+        }
+    }
+
+    // Executes one instruction, or acknowledges an interrupt
+    fn step(&mut self, bus: &mut M) -> Result<u32, ExecError> {
+        // The instruction following EI or an ignored DD / FD prefix can not be interrupted : the maskable interrupt request stays pending
+        let chain_request = self.daisy_chain_request();
+        let int_accepted = self.iff1
+            && !self.int_delay
+            && !self.prefix
+            && (chain_request.is_some() || !self.int_sources.is_empty());
+        self.int_delay = false;
+        let nmi_blocked = self.prefix;
+        self.prefix = false;
+
+        // While halted, the CPU executes NOPs until an interrupt is accepted
         if self.halt {
-            return 4;
+            if !self.nmi && !int_accepted {
+                self.inc_r();
+                self.q = 0;
+                return Ok(4);
+            }
+            // Leaving HALT : the address following the HALT instruction will be stacked
+            self.halt = false;
+            self.reg.pc = self.reg.pc.wrapping_add(1);
         };
 
-        // Non maskable interrupt requested ?
-        if self.nmi {
+        // Non maskable interrupt requested ? Push PC onto the stack and jump to 0066h.
+        // The interrupt acknowledge is a step of its own (11 T-states) : the handler is executed by the next step.
+        if self.nmi && !nmi_blocked {
             self.iff2 = self.iff1;
             self.iff1 = false;
+            self.inc_r();
             self.interrupt_stack_push(bus);
             self.reg.pc = 0x0066;
+            self.memptr = self.reg.pc;
             self.nmi = false;
+            self.q = 0;
+            return Ok(11);
         }
 
-        // Interrupt requested in interrupt mode 1 ? Restart at address 0038h (opcode 0xFF)
-        if self.iff1 && self.int.is_some() && self.im == 1 {
-            self.int = Some(0xFF)
-        };
+        // Maskable interrupt accepted : further maskable interrupts are disabled.
+        // The pending source with the highest priority is acknowledged : it releases the INT line and puts its data on the bus.
+        // The daisy chain has priority over the other sources.
+        let mut ack_cycles = 0;
+        if int_accepted {
+            self.iff1 = false;
+            self.iff2 = false;
+            let data = match chain_request {
+                Some(i) => vec![self.daisy_chain[i].ack()],
+                None => self
+                    .int_sources
+                    .pop_first()
+                    .map(|(_, data)| data)
+                    .unwrap_or_default(),
+            };
+
+            // Interrupt mode 2 ? Push PC onto the stack, build jump address and jump to that address.
+            // The interrupt acknowledge is a step of its own (19 T-states) : the handler is executed by the next step.
+            if self.im == 2 {
+                self.inc_r();
+                self.interrupt_stack_push(bus);
+                let addr = ((self.reg.i as u16) << 8) | (*data.first().unwrap_or(&0xFF) as u16);
+                self.reg.pc = bus.read_word(addr);
+                self.memptr = self.reg.pc;
+                self.q = 0;
+                return Ok(19);
+            }
+
+            // Interrupt mode 1 ? Restart at address 0038h (opcode 0xFF), whatever the data on the bus
+            let data = if self.im == 1 { vec![0xFF] } else { data };
+
+            // Interrupt mode 0 or 1 : the instruction is read from the data bus instead of memory.
+            // PC is not incremented while the instruction is read, so it is moved back by the instruction length :
+            // CALL and RST will stack the address of the interrupted instruction.
+            // The acknowledge cycle adds 2 wait states (13 T-states for a RST).
+            self.reg.pc = self.reg.pc.wrapping_sub(data.len() as u16);
+            self.int_data = Some(data);
+            ack_cycles = 2;
+        }
 
-        // Interrupt requested in interrupt mode 2 ? Push PC onto the stack, build jump address and jump to that address
-        if self.iff1 && self.int.is_some() && self.im == 2 {
-            self.interrupt_stack_push(bus);
-            let addr = ((self.reg.i as u16) << 8) | (self.int.unwrap() as u16);
-            self.reg.pc = bus.read_word(addr);
-            self.int = None;
+        // We retrieve the opcode, wether it comes from an interrupt request or normal fetch
+        let address = self.reg.pc;
+        let opcode = match self.next_opcode.take() {
+            Some((pc, opcode)) if pc == address && self.int_data.is_none() => opcode,
+            _ => self.fetch_opcode(bus, 0),
+        };
+        // The byte following a prefix is read by a second opcode fetch
+        let next = match opcode {
+            0xDD | 0xFD | 0xED | 0xCB => Some(self.fetch_opcode(bus, 1)),
+            _ => None,
         };
 
-        // We retrieve the opcode, wether it comes from an interrupt request or normal fetch
-        let opcode = match self.iff1 {
-            false => bus.read_byte(self.reg.pc),
-            // interrupts enabled : is there a pending interrupt ?
-            true => match self.int {
-                None => bus.read_byte(self.reg.pc),
-                Some(o) => o,
-            },
+        self.flags_written = false;
+
+        // The memory refresh register is incremented on every opcode fetch (M1 cycle) : twice for prefixed instructions
+        let cycles = match (opcode, next) {
+            // A DD / FD prefix followed by an instruction not using HL, or by another prefix, acts as a NOP :
+            // the following instruction is executed normally by the next step, so the last prefix of a chain wins
+            (0xDD | 0xFD, Some(next)) if !is_indexed_opcode(next) => {
+                self.inc_r();
+                self.reg.pc = self.reg.pc.wrapping_add(1);
+                if self.int_data.is_none() {
+                    self.next_opcode = Some((self.reg.pc, next));
+                }
+                self.prefix = true;
+                Some(4)
+            }
+            (_, Some(next)) => {
+                self.inc_r();
+                self.inc_r();
+                self.execute_2bytes(bus, (opcode as u16) << 8 | next as u16)
+            }
+            _ => {
+                self.inc_r();
+                self.execute_1byte(bus, opcode)
+            }
         };
 
-        let cycles = match opcode {
-            0xDD | 0xFD | 0xED | 0xCB => self.execute_2bytes(bus),
-            _ => self.execute_1byte(bus, opcode),
+        // The internal Q register holds the flags when the instruction has modified them
+        self.q = if self.flags_written { self.flags() } else { 0 };
+
+        let cycles = match cycles {
+            Some(cycles) => cycles,
+            // PC is left on the unknown instruction
+            None => {
+                self.reg.pc = address;
+                let unknown = self.unknown_opcode(opcode, next);
+                self.int_data = None;
+                self.unknown_opcode_policy(bus, unknown)?
+            }
         };
+        self.int_data = None;
 
-        self.int = None;
-        cycles
+        Ok(cycles + ack_cycles)
     }
 
     /// Fetches and executes one instruction from (pc). Returns the sleep time when slice_max_cycles is reached.
-    pub fn execute_timed(&mut self, bus: &mut Bus) -> Option<u32> {
-        let mut sleep_time: Option<u32> = None;
-        if self.slice_current_cycles > self.slice_max_cycles {
-            self.slice_current_cycles = 0;
-            // d = time taken to execute the slice_max_cycles
-            if let Ok(d) = self.slice_start_time.elapsed() {
-                sleep_time = Some(self.slice_duration.saturating_sub(d.as_millis() as u32));
-                self.slice_start_time = SystemTime::now();
-            }
+    /// The sleep time is computed from the total time the executed cycles should have taken since the timed execution started,
+    /// so the time lost or gained in a slice is compensated in the next ones.
+    pub fn execute_timed(&mut self, bus: &mut M) -> Option<Duration> {
+        let start = *self.timed_start.get_or_insert_with(|| self.clock.now());
+        let cycles_before = self.cycles;
+        self.execute(bus);
+        let cycles = self.cycles - cycles_before;
+        self.timed_cycles += cycles;
+        self.slice_current_cycles += cycles as u32;
+        if self.slice_current_cycles < self.slice_max_cycles {
+            return None;
         }
-        let cycles = self.execute(bus);
-        self.slice_current_cycles += cycles;
-        sleep_time
+        self.slice_current_cycles = 0;
+        // Time the executed cycles take on the real CPU, compared to the time actually elapsed
+        let emulated = Duration::from_nanos((self.timed_cycles as f64 * 1e9 / self.freq) as u64);
+        let elapsed = self.clock.now().saturating_sub(start);
+        if elapsed > emulated + Duration::from_millis(self.slice_duration as u64) {
+            // More than a slice late (host too slow or execution paused) : restarting from now rather than running flat out to catch up
+            self.timed_start = None;
+            self.timed_cycles = 0;
+        }
+        Some(emulated.saturating_sub(elapsed))
     }
 
     /// Sets CPU frequency (MHz)
@@ -127,16 +659,44 @@ impl CPU {
     /// c.set_freq(1.7);            // CPU will run at 1.7 Mhz
     /// ```
     pub fn set_freq(&mut self, f: f32) {
-        let cycles = (f * 1000000_f32) / (1000 / self.slice_duration) as f32;
-        self.slice_max_cycles = cycles as u32;
+        self.freq = f as f64 * 1_000_000.0;
+        self.update_slice();
+    }
+
+    /// Sets CPU frequency (Hz)
+    /// ```rust
+    /// use zilog_z80::cpu::CPU;
+    /// let mut c = CPU::new();
+    /// c.set_freq_hz(3_546_900);   // CPU will run at 3.5469 Mhz
+    /// ```
+    pub fn set_freq_hz(&mut self, hz: u32) {
+        self.freq = hz as f64;
+        self.update_slice();
     }
 
     /// Sets slice duration (in milliseconds) for timed execution. Typically 16 for 60 Hz, 20 for 50 Hz screen refresh.
     pub fn set_slice_duration(&mut self, slice_duration: u32) {
         self.slice_duration = slice_duration;
+        self.update_slice();
+    }
+
+    /// Sets the time source of the timed execution. Defaults to the host monotonic clock.
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = clock;
+        self.timed_start = None;
+        self.timed_cycles = 0;
     }
 
-    fn execute_1byte(&mut self, bus: &mut Bus, opcode: u8) -> u32 {
+    // Number of cycles of a slice, from the frequency and the slice duration. The timed execution restarts from now.
+    fn update_slice(&mut self) {
+        self.slice_max_cycles =
+            ((self.freq * self.slice_duration as f64 / 1000.0).round() as u32).max(1);
+        self.slice_current_cycles = 0;
+        self.timed_start = None;
+        self.timed_cycles = 0;
+    }
+
+    fn execute_1byte(&mut self, bus: &mut M, opcode: u8) -> Option<u32> {
         let mut cycles = CYCLES[opcode as usize].into();
 
         // Saving current PC for debug output
@@ -277,43 +837,43 @@ impl CPU {
             // LD r,n
             0x06 => {
                 // LD B,n
-                let data = bus.read_byte(self.reg.pc + 1);
+                let data = self.fetch_byte(bus, 1);
                 self.reg.b = data;
             }
             0x0E => {
                 // LD C,n
-                let data = bus.read_byte(self.reg.pc + 1);
+                let data = self.fetch_byte(bus, 1);
                 self.reg.c = data;
             }
             0x16 => {
                 // LD D,n
-                let data = bus.read_byte(self.reg.pc + 1);
+                let data = self.fetch_byte(bus, 1);
                 self.reg.d = data;
             }
             0x1E => {
                 // LD E,n
-                let data = bus.read_byte(self.reg.pc + 1);
+                let data = self.fetch_byte(bus, 1);
                 self.reg.e = data;
             }
             0x26 => {
                 // LD H,n
-                let data = bus.read_byte(self.reg.pc + 1);
+                let data = self.fetch_byte(bus, 1);
                 self.reg.h = data;
             }
             0x2E => {
                 // LD L,n
-                let data = bus.read_byte(self.reg.pc + 1);
+                let data = self.fetch_byte(bus, 1);
                 self.reg.l = data;
             }
             0x36 => {
                 // LD (HL),n
-                let data = bus.read_byte(self.reg.pc + 1);
+                let data = self.fetch_byte(bus, 1);
                 let addr = self.reg.get_hl();
                 bus.write_byte(addr, data);
             }
             0x3E => {
                 // LD A,n
-                let data = bus.read_byte(self.reg.pc + 1);
+                let data = self.fetch_byte(bus, 1);
                 self.reg.a = data;
             }
 
@@ -321,73 +881,81 @@ impl CPU {
             0x0A => {
                 let addr = self.reg.get_bc();
                 self.reg.a = bus.read_byte(addr);
+                self.memptr = addr.wrapping_add(1);
             }
 
             // LD A,(DE)
             0x1A => {
                 let addr = self.reg.get_de();
                 self.reg.a = bus.read_byte(addr);
+                self.memptr = addr.wrapping_add(1);
             }
 
             // LD A,(nn)
             0x3A => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
                 self.reg.a = bus.read_byte(addr);
+                self.memptr = addr.wrapping_add(1);
             }
 
             // LD (BC),A
             0x02 => {
                 let addr = self.reg.get_bc();
                 bus.write_byte(addr, self.reg.a);
+                self.memptr = ((self.reg.a as u16) << 8) | (addr.wrapping_add(1) & 0x00FF);
             }
 
             // LD (DE),A
             0x12 => {
                 let addr = self.reg.get_de();
                 bus.write_byte(addr, self.reg.a);
+                self.memptr = ((self.reg.a as u16) << 8) | (addr.wrapping_add(1) & 0x00FF);
             }
 
             // LD (nn),A
             0x32 => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
                 bus.write_byte(addr, self.reg.a);
+                self.memptr = ((self.reg.a as u16) << 8) | (addr.wrapping_add(1) & 0x00FF);
             }
 
             // 16-Bit Load Group
             // LD dd,nn
             0x01 => {
                 // LD BC,nn
-                let d16 = bus.read_word(self.reg.pc + 1);
+                let d16 = self.fetch_word(bus, 1);
                 self.reg.set_bc(d16);
             }
             0x11 => {
                 // LD DE,nn
-                let d16 = bus.read_word(self.reg.pc + 1);
+                let d16 = self.fetch_word(bus, 1);
                 self.reg.set_de(d16);
             }
             0x21 => {
                 // LD HL,nn
-                let d16 = bus.read_word(self.reg.pc + 1);
+                let d16 = self.fetch_word(bus, 1);
                 self.reg.set_hl(d16);
             }
             0x31 => {
                 // LD SP,nn
-                let d16 = bus.read_word(self.reg.pc + 1);
+                let d16 = self.fetch_word(bus, 1);
                 self.reg.sp = d16;
             }
 
             // LD HL,(nn)
             0x2A => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
                 let d = bus.read_word(addr);
+                self.memptr = addr.wrapping_add(1);
                 self.reg.set_hl(d);
             }
 
             // LD (nn),HL
             0x22 => {
                 let d = self.reg.get_hl();
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
                 bus.write_word(addr, d);
+                self.memptr = addr.wrapping_add(1);
             }
 
             // LD SP,HL
@@ -482,6 +1050,7 @@ impl CPU {
                 let hl = self.reg.get_hl();
                 bus.write_word(self.reg.sp, hl);
                 self.reg.set_hl(pointed_by_sp);
+                self.memptr = pointed_by_sp;
             }
 
             // 8-Bit Arithmetic Group
@@ -502,7 +1071,7 @@ impl CPU {
 
             // ADD A,n
             0xC6 => {
-                let n = bus.read_byte(self.reg.pc + 1);
+                let n = self.fetch_byte(bus, 1);
                 self.add(n);
             }
 
@@ -524,7 +1093,7 @@ impl CPU {
             // ADC a,n
             0xCE => {
                 // ADC A,(HL)
-                let n = bus.read_byte(self.reg.pc + 1);
+                let n = self.fetch_byte(bus, 1);
                 self.adc(n)
             }
 
@@ -545,7 +1114,7 @@ impl CPU {
 
             0xD6 => {
                 // SUB A,n
-                let n = bus.read_byte(self.reg.pc + 1);
+                let n = self.fetch_byte(bus, 1);
                 self.sub(n);
             }
 
@@ -566,7 +1135,7 @@ impl CPU {
 
             0xDE => {
                 // SBC A,n
-                let n = bus.read_byte(self.reg.pc + 1);
+                let n = self.fetch_byte(bus, 1);
                 self.sbc(n);
             }
 
@@ -587,7 +1156,7 @@ impl CPU {
 
             0xE6 => {
                 // AND n
-                let n = bus.read_byte(self.reg.pc + 1);
+                let n = self.fetch_byte(bus, 1);
                 self.and(n);
             }
 
@@ -608,7 +1177,7 @@ impl CPU {
 
             0xF6 => {
                 // OR n
-                let n = bus.read_byte(self.reg.pc + 1);
+                let n = self.fetch_byte(bus, 1);
                 self.or(n);
             }
 
@@ -629,7 +1198,7 @@ impl CPU {
 
             0xEE => {
                 // XOR n
-                let n = bus.read_byte(self.reg.pc + 1);
+                let n = self.fetch_byte(bus, 1);
                 self.xor(n);
             }
 
@@ -650,7 +1219,7 @@ impl CPU {
 
             0xFE => {
                 // CP n
-                let n = bus.read_byte(self.reg.pc + 1);
+                let n = self.fetch_byte(bus, 1);
                 self.cp(n);
             }
 
@@ -693,10 +1262,12 @@ impl CPU {
                 self.reg.a = !self.reg.a;
                 self.reg.flags.h = true;
                 self.reg.flags.n = true;
+                self.set_xy(self.reg.a);
             }
 
             // CCF
             0x3F => {
+                self.set_xy((self.q ^ self.flags()) | self.reg.a);
                 self.reg.flags.h = self.reg.flags.c;
                 self.reg.flags.c = !self.reg.flags.c;
                 self.reg.flags.n = false;
@@ -704,6 +1275,7 @@ impl CPU {
 
             // SCF
             0x37 => {
+                self.set_xy((self.q ^ self.flags()) | self.reg.a);
                 self.reg.flags.c = true;
                 self.reg.flags.h = false;
                 self.reg.flags.n = false;
@@ -725,6 +1297,7 @@ impl CPU {
             0xFB => {
                 self.iff1 = true;
                 self.iff2 = true;
+                self.int_delay = true;
             }
 
             // 16-Bit Arithmetic Group
@@ -820,112 +1393,139 @@ impl CPU {
             // Jump group
             // JP nn
             0xC3 => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
+                self.memptr = addr;
                 self.reg.pc = addr;
             }
 
             // JP C,nn
             0xDA => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
+                self.memptr = addr;
                 if self.reg.flags.c {
                     self.reg.pc = addr;
                 } else {
-                    self.reg.pc += 3
+                    self.reg.pc = self.reg.pc.wrapping_add(3)
                 }
             }
 
             // JP NC,nn
             0xD2 => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
+                self.memptr = addr;
                 if !self.reg.flags.c {
                     self.reg.pc = addr;
                 } else {
-                    self.reg.pc += 3
+                    self.reg.pc = self.reg.pc.wrapping_add(3)
                 }
             }
 
             // JP Z,nn
             0xCA => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
+                self.memptr = addr;
                 if self.reg.flags.z {
                     self.reg.pc = addr;
                 } else {
-                    self.reg.pc += 3
+                    self.reg.pc = self.reg.pc.wrapping_add(3)
                 }
             }
 
             // JP NZ,nn
             0xC2 => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
+                self.memptr = addr;
                 if !self.reg.flags.z {
                     self.reg.pc = addr;
                 } else {
-                    self.reg.pc += 3
+                    self.reg.pc = self.reg.pc.wrapping_add(3)
                 }
             }
 
             // JP M,nn
             0xFA => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
+                self.memptr = addr;
                 if self.reg.flags.s {
                     self.reg.pc = addr;
                 } else {
-                    self.reg.pc += 3
+                    self.reg.pc = self.reg.pc.wrapping_add(3)
                 }
             }
 
             // JP P,nn
             0xF2 => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
+                self.memptr = addr;
                 if !self.reg.flags.s {
                     self.reg.pc = addr;
                 } else {
-                    self.reg.pc += 3
+                    self.reg.pc = self.reg.pc.wrapping_add(3)
                 }
             }
 
             // JP PE,nn
             0xEA => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
+                self.memptr = addr;
                 if self.reg.flags.p {
                     self.reg.pc = addr;
                 } else {
-                    self.reg.pc += 3
+                    self.reg.pc = self.reg.pc.wrapping_add(3)
                 }
             }
 
             // JP PO,nn
             0xE2 => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
+                self.memptr = addr;
                 if !self.reg.flags.p {
                     self.reg.pc = addr;
                 } else {
-                    self.reg.pc += 3
+                    self.reg.pc = self.reg.pc.wrapping_add(3)
                 }
             }
 
             // JR e
             0x18 => {
-                let displacement = bus.read_byte(self.reg.pc + 1);
+                let displacement = self.fetch_byte(bus, 1);
                 if bit::get(displacement, 7) {
-                    self.reg.pc = self.reg.pc + 2 - (signed_to_abs(displacement) as u16)
+                    self.reg.pc = self
+                        .reg
+                        .pc
+                        .wrapping_add(2)
+                        .wrapping_sub(signed_to_abs(displacement) as u16)
                 } else {
-                    self.reg.pc = self.reg.pc + (displacement as u16) + 2
+                    self.reg.pc = self
+                        .reg
+                        .pc
+                        .wrapping_add(displacement as u16)
+                        .wrapping_add(2)
                 }
+                self.memptr = self.reg.pc;
             }
 
             // JR C,e
             0x38 => {
                 if self.reg.flags.c {
-                    let displacement = bus.read_byte(self.reg.pc + 1);
+                    let displacement = self.fetch_byte(bus, 1);
                     if bit::get(displacement, 7) {
-                        self.reg.pc = self.reg.pc + 2 - (signed_to_abs(displacement) as u16)
+                        self.reg.pc = self
+                            .reg
+                            .pc
+                            .wrapping_add(2)
+                            .wrapping_sub(signed_to_abs(displacement) as u16)
                     } else {
-                        self.reg.pc = self.reg.pc + (displacement as u16) + 2
+                        self.reg.pc = self
+                            .reg
+                            .pc
+                            .wrapping_add(displacement as u16)
+                            .wrapping_add(2)
                     }
+                    self.memptr = self.reg.pc;
                     cycles += 5;
                 } else {
-                    self.reg.pc += 2
+                    self.reg.pc = self.reg.pc.wrapping_add(2)
                 }
                 cycles += 7;
             }
@@ -933,15 +1533,24 @@ impl CPU {
             // JR NC,e
             0x30 => {
                 if !self.reg.flags.c {
-                    let displacement = bus.read_byte(self.reg.pc + 1);
+                    let displacement = self.fetch_byte(bus, 1);
                     if bit::get(displacement, 7) {
-                        self.reg.pc = self.reg.pc + 2 - (signed_to_abs(displacement) as u16)
+                        self.reg.pc = self
+                            .reg
+                            .pc
+                            .wrapping_add(2)
+                            .wrapping_sub(signed_to_abs(displacement) as u16)
                     } else {
-                        self.reg.pc = self.reg.pc + (displacement as u16) + 2
+                        self.reg.pc = self
+                            .reg
+                            .pc
+                            .wrapping_add(displacement as u16)
+                            .wrapping_add(2)
                     }
+                    self.memptr = self.reg.pc;
                     cycles += 5;
                 } else {
-                    self.reg.pc += 2
+                    self.reg.pc = self.reg.pc.wrapping_add(2)
                 }
                 cycles += 7;
             }
@@ -949,15 +1558,24 @@ impl CPU {
             // JR Z,e
             0x28 => {
                 if self.reg.flags.z {
-                    let displacement = bus.read_byte(self.reg.pc + 1);
+                    let displacement = self.fetch_byte(bus, 1);
                     if bit::get(displacement, 7) {
-                        self.reg.pc = self.reg.pc + 2 - (signed_to_abs(displacement) as u16)
+                        self.reg.pc = self
+                            .reg
+                            .pc
+                            .wrapping_add(2)
+                            .wrapping_sub(signed_to_abs(displacement) as u16)
                     } else {
-                        self.reg.pc = self.reg.pc + (displacement as u16) + 2
+                        self.reg.pc = self
+                            .reg
+                            .pc
+                            .wrapping_add(displacement as u16)
+                            .wrapping_add(2)
                     }
+                    self.memptr = self.reg.pc;
                     cycles += 5;
                 } else {
-                    self.reg.pc += 2
+                    self.reg.pc = self.reg.pc.wrapping_add(2)
                 }
                 cycles += 7;
             }
@@ -965,15 +1583,24 @@ impl CPU {
             // JR NZ,e
             0x20 => {
                 if !self.reg.flags.z {
-                    let displacement = bus.read_byte(self.reg.pc + 1);
+                    let displacement = self.fetch_byte(bus, 1);
                     if bit::get(displacement, 7) {
-                        self.reg.pc = self.reg.pc + 2 - (signed_to_abs(displacement) as u16)
+                        self.reg.pc = self
+                            .reg
+                            .pc
+                            .wrapping_add(2)
+                            .wrapping_sub(signed_to_abs(displacement) as u16)
                     } else {
-                        self.reg.pc = self.reg.pc + (displacement as u16) + 2
+                        self.reg.pc = self
+                            .reg
+                            .pc
+                            .wrapping_add(displacement as u16)
+                            .wrapping_add(2)
                     }
+                    self.memptr = self.reg.pc;
                     cycles += 5;
                 } else {
-                    self.reg.pc += 2
+                    self.reg.pc = self.reg.pc.wrapping_add(2)
                 }
                 cycles += 7;
             }
@@ -987,15 +1614,24 @@ impl CPU {
             0x10 => {
                 self.reg.b = (self.reg.b).wrapping_sub(1);
                 if self.reg.b != 0 {
-                    let displacement = bus.read_byte(self.reg.pc + 1);
+                    let displacement = self.fetch_byte(bus, 1);
                     if bit::get(displacement, 7) {
-                        self.reg.pc = self.reg.pc + 2 - (signed_to_abs(displacement) as u16)
+                        self.reg.pc = self
+                            .reg
+                            .pc
+                            .wrapping_add(2)
+                            .wrapping_sub(signed_to_abs(displacement) as u16)
                     } else {
-                        self.reg.pc = self.reg.pc + (displacement as u16) + 2
+                        self.reg.pc = self
+                            .reg
+                            .pc
+                            .wrapping_add(displacement as u16)
+                            .wrapping_add(2)
                     }
+                    self.memptr = self.reg.pc;
                     cycles += 5;
                 } else {
-                    self.reg.pc += 2
+                    self.reg.pc = self.reg.pc.wrapping_add(2)
                 }
                 cycles += 8;
             }
@@ -1003,104 +1639,113 @@ impl CPU {
             // Call and Return Group
             // CALL nn
             0xCD => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
+                self.memptr = addr;
                 self.call_stack_push(bus);
                 self.reg.pc = addr;
             }
 
             // CALL C,nn
             0xDC => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
+                self.memptr = addr;
                 if self.reg.flags.c {
                     self.call_stack_push(bus);
                     self.reg.pc = addr;
                     cycles += 7;
                 } else {
-                    self.reg.pc += 3
+                    self.reg.pc = self.reg.pc.wrapping_add(3)
                 }
             }
 
             // CALL NC,nn
             0xD4 => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
+                self.memptr = addr;
                 if !self.reg.flags.c {
                     self.call_stack_push(bus);
                     self.reg.pc = addr;
                     cycles += 7;
                 } else {
-                    self.reg.pc += 3
+                    self.reg.pc = self.reg.pc.wrapping_add(3)
                 }
             }
 
             // CALL Z,nn
             0xCC => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
+                self.memptr = addr;
                 if self.reg.flags.z {
                     self.call_stack_push(bus);
                     self.reg.pc = addr;
                     cycles += 7;
                 } else {
-                    self.reg.pc += 3
+                    self.reg.pc = self.reg.pc.wrapping_add(3)
                 }
             }
 
             // CALL NZ,nn
             0xC4 => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
+                self.memptr = addr;
                 if !self.reg.flags.z {
                     self.call_stack_push(bus);
                     self.reg.pc = addr;
                     cycles += 7;
                 } else {
-                    self.reg.pc += 3
+                    self.reg.pc = self.reg.pc.wrapping_add(3)
                 }
             }
 
             // CALL M,nn
             0xFC => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
+                self.memptr = addr;
                 if self.reg.flags.s {
                     self.call_stack_push(bus);
                     self.reg.pc = addr;
                     cycles += 7;
                 } else {
-                    self.reg.pc += 3
+                    self.reg.pc = self.reg.pc.wrapping_add(3)
                 }
             }
 
             // CALL P,nn
             0xF4 => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
+                self.memptr = addr;
                 if !self.reg.flags.s {
                     self.call_stack_push(bus);
                     self.reg.pc = addr;
                     cycles += 7;
                 } else {
-                    self.reg.pc += 3
+                    self.reg.pc = self.reg.pc.wrapping_add(3)
                 }
             }
 
             // CALL PE,nn
             0xEC => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
+                self.memptr = addr;
                 if self.reg.flags.p {
                     self.call_stack_push(bus);
                     self.reg.pc = addr;
                     cycles += 7;
                 } else {
-                    self.reg.pc += 3
+                    self.reg.pc = self.reg.pc.wrapping_add(3)
                 }
             }
 
             // CALL PO,nn
             0xE4 => {
-                let addr = bus.read_word(self.reg.pc + 1);
+                let addr = self.fetch_word(bus, 1);
+                self.memptr = addr;
                 if !self.reg.flags.p {
                     self.call_stack_push(bus);
                     self.reg.pc = addr;
                     cycles += 7;
                 } else {
-                    self.reg.pc += 3
+                    self.reg.pc = self.reg.pc.wrapping_add(3)
                 }
             }
 
@@ -1113,7 +1758,7 @@ impl CPU {
                     self.call_stack_pop(bus);
                     cycles += 6;
                 } else {
-                    self.reg.pc += 1;
+                    self.reg.pc = self.reg.pc.wrapping_add(1);
                 }
             }
 
@@ -1123,7 +1768,7 @@ impl CPU {
                     self.call_stack_pop(bus);
                     cycles += 6;
                 } else {
-                    self.reg.pc += 1;
+                    self.reg.pc = self.reg.pc.wrapping_add(1);
                 }
             }
 
@@ -1133,7 +1778,7 @@ impl CPU {
                     self.call_stack_pop(bus);
                     cycles += 6;
                 } else {
-                    self.reg.pc += 1;
+                    self.reg.pc = self.reg.pc.wrapping_add(1);
                 }
             }
 
@@ -1143,7 +1788,7 @@ impl CPU {
                     self.call_stack_pop(bus);
                     cycles += 6;
                 } else {
-                    self.reg.pc += 1;
+                    self.reg.pc = self.reg.pc.wrapping_add(1);
                 }
             }
 
@@ -1153,7 +1798,7 @@ impl CPU {
                     self.call_stack_pop(bus);
                     cycles += 6;
                 } else {
-                    self.reg.pc += 1;
+                    self.reg.pc = self.reg.pc.wrapping_add(1);
                 }
             }
 
@@ -1163,7 +1808,7 @@ impl CPU {
                     self.call_stack_pop(bus);
                     cycles += 6;
                 } else {
-                    self.reg.pc += 1;
+                    self.reg.pc = self.reg.pc.wrapping_add(1);
                 }
             }
 
@@ -1173,7 +1818,7 @@ impl CPU {
                     self.call_stack_pop(bus);
                     cycles += 6;
                 } else {
-                    self.reg.pc += 1;
+                    self.reg.pc = self.reg.pc.wrapping_add(1);
                 }
             }
 
@@ -1183,111 +1828,96 @@ impl CPU {
                     self.call_stack_pop(bus);
                     cycles += 6;
                 } else {
-                    self.reg.pc += 1;
+                    self.reg.pc = self.reg.pc.wrapping_add(1);
                 }
             }
 
             // RST 0
             0xC7 => {
-                match self.int {
-                    Some(_) => self.interrupt_stack_push(bus),
-                    None => {
-                        self.reg.pc += 1;
-                        self.interrupt_stack_push(bus);
-                    }
-                }
+                self.reg.pc = self.reg.pc.wrapping_add(1);
+                self.interrupt_stack_push(bus);
                 self.reg.pc = 0x0000;
+                self.memptr = self.reg.pc;
             }
 
             // RST 08
             0xCF => {
-                match self.int {
-                    Some(_) => self.interrupt_stack_push(bus),
-                    None => {
-                        self.reg.pc += 1;
-                        self.interrupt_stack_push(bus);
-                    }
-                }
+                self.reg.pc = self.reg.pc.wrapping_add(1);
+                self.interrupt_stack_push(bus);
                 self.reg.pc = 0x0008;
+                self.memptr = self.reg.pc;
             }
 
             // RST 10
             0xD7 => {
-                match self.int {
-                    Some(_) => self.interrupt_stack_push(bus),
-                    None => {
-                        self.reg.pc += 1;
-                        self.interrupt_stack_push(bus);
-                    }
-                }
+                self.reg.pc = self.reg.pc.wrapping_add(1);
+                self.interrupt_stack_push(bus);
                 self.reg.pc = 0x0010;
+                self.memptr = self.reg.pc;
             }
 
             // RST 18
             0xDF => {
-                match self.int {
-                    Some(_) => self.interrupt_stack_push(bus),
-                    None => {
-                        self.reg.pc += 1;
-                        self.interrupt_stack_push(bus);
-                    }
-                }
+                self.reg.pc = self.reg.pc.wrapping_add(1);
+                self.interrupt_stack_push(bus);
                 self.reg.pc = 0x0018;
+                self.memptr = self.reg.pc;
             }
 
             // RST 20
             0xE7 => {
-                match self.int {
-                    Some(_) => self.interrupt_stack_push(bus),
-                    None => {
-                        self.reg.pc += 1;
-                        self.interrupt_stack_push(bus);
-                    }
-                }
+                self.reg.pc = self.reg.pc.wrapping_add(1);
+                self.interrupt_stack_push(bus);
                 self.reg.pc = 0x0020;
+                self.memptr = self.reg.pc;
             }
 
             // RST 28
             0xEF => {
-                match self.int {
-                    Some(_) => self.interrupt_stack_push(bus),
-                    None => {
-                        self.reg.pc += 1;
-                        self.interrupt_stack_push(bus);
-                    }
-                }
+                self.reg.pc = self.reg.pc.wrapping_add(1);
+                self.interrupt_stack_push(bus);
                 self.reg.pc = 0x0028;
+                self.memptr = self.reg.pc;
             }
 
             // RST 30
             0xF7 => {
-                match self.int {
-                    Some(_) => self.interrupt_stack_push(bus),
-                    None => {
-                        self.reg.pc += 1;
-                        self.interrupt_stack_push(bus);
-                    }
-                }
+                self.reg.pc = self.reg.pc.wrapping_add(1);
+                self.interrupt_stack_push(bus);
                 self.reg.pc = 0x0030;
+                self.memptr = self.reg.pc;
             }
 
             // RST 38
             0xFF => {
-                match self.int {
-                    Some(_) => self.interrupt_stack_push(bus),
-                    None => {
-                        self.reg.pc += 1;
-                        self.interrupt_stack_push(bus);
-                    }
-                }
+                self.reg.pc = self.reg.pc.wrapping_add(1);
+                self.interrupt_stack_push(bus);
                 self.reg.pc = 0x0038;
+                self.memptr = self.reg.pc;
+            }
+
+            // Input and Output Group
+            // IN A,(n)
+            0xDB => {
+                let port = self.fetch_byte(bus, 1);
+                let addr = ((self.reg.a as u16) << 8) | port as u16;
+                self.reg.a = self.port_in(bus, addr);
+                self.memptr = addr.wrapping_add(1);
+            }
+
+            // OUT (n),A
+            0xD3 => {
+                let port = self.fetch_byte(bus, 1);
+                let addr = ((self.reg.a as u16) << 8) | port as u16;
+                self.port_out(bus, addr, self.reg.a);
+                self.memptr = ((self.reg.a as u16) << 8) | (port.wrapping_add(1) as u16);
             }
 
             _ => {
                 if self.debug.unknw_instr {
                     self.debug.string = format!("{:#04X}", opcode);
                 }
-                cycles = 0xFF;
+                return None;
             }
         }
 
@@ -1296,7 +1926,7 @@ impl CPU {
             print!(
                 "{:#06X}\t{}\nSP : {:#06X}\tS : {}\tZ : {}\tH : {}\tP : {}\tN : {}\tC : {}\nB : {:#04X}\tC : {:#04X}\tD : {:#04X}\tE : {:#04X}\tH : {:#04X}\tL : {:#04X}\tA : {:#04X}\t(SP) : {:#06X}\n",
                 pc,
-                Bus::dasm(bus, pc).0,
+                disassemble(|a| bus.read_byte(a), pc).0,
                 self.reg.sp,
                 self.reg.flags.s as i32,
                 self.reg.flags.z as i32,
@@ -1321,16 +1951,17 @@ impl CPU {
             | 0xF8 | 0xF0 | 0xE8 | 0xE0 | 0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF
             | 0x76 | 0x18 | 0x38 | 0x30 | 0x28 | 0x20 | 0x10 => {}
             0x06 | 0x0E | 0x16 | 0x1E | 0x26 | 0x2E | 0x36 | 0x3E | 0xC6 | 0xCE | 0xD6 | 0xDE
-            | 0xE6 | 0xF6 | 0xEE | 0xFE | 0xDB | 0xD3 => self.reg.pc += 2,
-            0x32 | 0x01 | 0x11 | 0x21 | 0x31 | 0x2A | 0x22 | 0x3A => self.reg.pc += 3,
-            _ => self.reg.pc += 1,
+            | 0xE6 | 0xF6 | 0xEE | 0xFE | 0xDB | 0xD3 => self.reg.pc = self.reg.pc.wrapping_add(2),
+            0x32 | 0x01 | 0x11 | 0x21 | 0x31 | 0x2A | 0x22 | 0x3A => {
+                self.reg.pc = self.reg.pc.wrapping_add(3)
+            }
+            _ => self.reg.pc = self.reg.pc.wrapping_add(1),
         }
 
-        cycles
+        Some(cycles)
     }
 
-    fn execute_2bytes(&mut self, bus: &mut Bus) -> u32 {
-        let opcode = bus.read_le_word(self.reg.pc);
+    fn execute_2bytes(&mut self, bus: &mut M, opcode: u16) -> Option<u32> {
         let mut cycles = match opcode & 0xFF00 {
             0xDD00 | 0xFD00 => CYCLES_DD_FD[(opcode & 0x00FF) as usize].into(),
             0xED00 => CYCLES_ED[(opcode & 0x00FF) as usize].into(),
@@ -1340,13 +1971,19 @@ impl CPU {
 
         match opcode {
             // 4 bytes instructions
-            0xDDCB | 0xFDCB => return self.execute_4bytes(bus),
+            0xDDCB | 0xFDCB => {
+                let opcode = (opcode as u32) << 16
+                    | (self.fetch_byte(bus, 2) as u32) << 8
+                    | self.fetch_byte(bus, 3) as u32;
+                return Some(self.execute_4bytes(bus, opcode));
+            }
 
             // 8-Bit Load Group
             // LD r,(IX+d)
             0xDD46 => {
                 // LD B,(IX+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     self.reg.b =
                         bus.read_byte(self.reg.get_ix() - (signed_to_abs(displacement) as u16))
@@ -1356,7 +1993,8 @@ impl CPU {
             }
             0xDD4E => {
                 // LD C,(IX+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     self.reg.c =
                         bus.read_byte(self.reg.get_ix() - (signed_to_abs(displacement) as u16))
@@ -1366,7 +2004,8 @@ impl CPU {
             }
             0xDD56 => {
                 // LD D,(IX+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     self.reg.d =
                         bus.read_byte(self.reg.get_ix() - (signed_to_abs(displacement) as u16))
@@ -1376,7 +2015,8 @@ impl CPU {
             }
             0xDD5E => {
                 // LD E,(IX+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     self.reg.e =
                         bus.read_byte(self.reg.get_ix() - (signed_to_abs(displacement) as u16))
@@ -1386,7 +2026,8 @@ impl CPU {
             }
             0xDD66 => {
                 // LD H,(IX+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     self.reg.h =
                         bus.read_byte(self.reg.get_ix() - (signed_to_abs(displacement) as u16))
@@ -1396,7 +2037,8 @@ impl CPU {
             }
             0xDD6E => {
                 // LD L,(IX+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     self.reg.l =
                         bus.read_byte(self.reg.get_ix() - (signed_to_abs(displacement) as u16))
@@ -1406,7 +2048,8 @@ impl CPU {
             }
             0xDD7E => {
                 // LD A,(IX+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     self.reg.a =
                         bus.read_byte(self.reg.get_ix() - (signed_to_abs(displacement) as u16))
@@ -1418,7 +2061,8 @@ impl CPU {
             // LD r,(IY+d)
             0xFD46 => {
                 // LD B,(IY+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     self.reg.b =
                         bus.read_byte(self.reg.get_iy() - (signed_to_abs(displacement) as u16))
@@ -1428,7 +2072,8 @@ impl CPU {
             }
             0xFD4E => {
                 // LD C,(IY+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     self.reg.c =
                         bus.read_byte(self.reg.get_iy() - (signed_to_abs(displacement) as u16))
@@ -1438,7 +2083,8 @@ impl CPU {
             }
             0xFD56 => {
                 // LD D,(IY+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     self.reg.d =
                         bus.read_byte(self.reg.get_iy() - (signed_to_abs(displacement) as u16))
@@ -1448,7 +2094,8 @@ impl CPU {
             }
             0xFD5E => {
                 // LD E,(IY+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     self.reg.e =
                         bus.read_byte(self.reg.get_iy() - (signed_to_abs(displacement) as u16))
@@ -1458,7 +2105,8 @@ impl CPU {
             }
             0xFD66 => {
                 // LD H,(IY+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     self.reg.h =
                         bus.read_byte(self.reg.get_iy() - (signed_to_abs(displacement) as u16))
@@ -1468,7 +2116,8 @@ impl CPU {
             }
             0xFD6E => {
                 // LD L,(IY+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     self.reg.l =
                         bus.read_byte(self.reg.get_iy() - (signed_to_abs(displacement) as u16))
@@ -1478,7 +2127,8 @@ impl CPU {
             }
             0xFD7E => {
                 // LD A,(IY+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     self.reg.a =
                         bus.read_byte(self.reg.get_iy() - (signed_to_abs(displacement) as u16))
@@ -1490,7 +2140,8 @@ impl CPU {
             // LD (IX+d),r
             0xDD70 => {
                 // LD (IX+d),B
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     bus.write_byte(
                         self.reg.get_ix() - (signed_to_abs(displacement) as u16),
@@ -1502,7 +2153,8 @@ impl CPU {
             }
             0xDD71 => {
                 // LD (IX+d),C
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     bus.write_byte(
                         self.reg.get_ix() - (signed_to_abs(displacement) as u16),
@@ -1514,7 +2166,8 @@ impl CPU {
             }
             0xDD72 => {
                 // LD (IX+d),D
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     bus.write_byte(
                         self.reg.get_ix() - (signed_to_abs(displacement) as u16),
@@ -1526,7 +2179,8 @@ impl CPU {
             }
             0xDD73 => {
                 // LD (IX+d),E
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     bus.write_byte(
                         self.reg.get_ix() - (signed_to_abs(displacement) as u16),
@@ -1538,7 +2192,8 @@ impl CPU {
             }
             0xDD74 => {
                 // LD (IX+d),H
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     bus.write_byte(
                         self.reg.get_ix() - (signed_to_abs(displacement) as u16),
@@ -1550,7 +2205,8 @@ impl CPU {
             }
             0xDD75 => {
                 // LD (IX+d),L
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     bus.write_byte(
                         self.reg.get_ix() - (signed_to_abs(displacement) as u16),
@@ -1562,7 +2218,8 @@ impl CPU {
             }
             0xDD77 => {
                 // LD (IX+d),A
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     bus.write_byte(
                         self.reg.get_ix() - (signed_to_abs(displacement) as u16),
@@ -1576,7 +2233,8 @@ impl CPU {
             // LD (IY+d),r
             0xFD70 => {
                 // LD (IY+d),B
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     bus.write_byte(
                         self.reg.get_iy() - (signed_to_abs(displacement) as u16),
@@ -1588,7 +2246,8 @@ impl CPU {
             }
             0xFD71 => {
                 // LD (IY+d),C
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     bus.write_byte(
                         self.reg.get_iy() - (signed_to_abs(displacement) as u16),
@@ -1600,7 +2259,8 @@ impl CPU {
             }
             0xFD72 => {
                 // LD (IY+d),D
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     bus.write_byte(
                         self.reg.get_iy() - (signed_to_abs(displacement) as u16),
@@ -1612,7 +2272,8 @@ impl CPU {
             }
             0xFD73 => {
                 // LD (IY+d),E
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     bus.write_byte(
                         self.reg.get_iy() - (signed_to_abs(displacement) as u16),
@@ -1624,7 +2285,8 @@ impl CPU {
             }
             0xFD74 => {
                 // LD (IY+d),H
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     bus.write_byte(
                         self.reg.get_iy() - (signed_to_abs(displacement) as u16),
@@ -1636,7 +2298,8 @@ impl CPU {
             }
             0xFD75 => {
                 // LD (IY+d),L
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     bus.write_byte(
                         self.reg.get_iy() - (signed_to_abs(displacement) as u16),
@@ -1648,7 +2311,8 @@ impl CPU {
             }
             0xFD77 => {
                 // LD (IY+d),A
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     bus.write_byte(
                         self.reg.get_iy() - (signed_to_abs(displacement) as u16),
@@ -1661,8 +2325,9 @@ impl CPU {
 
             // LD (IX+d),n
             0xDD36 => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
-                let data = bus.read_byte(self.reg.pc + 3);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
+                let data = self.fetch_byte(bus, 3);
                 if bit::get(displacement, 7) {
                     bus.write_byte(
                         self.reg.get_ix() - (signed_to_abs(displacement) as u16),
@@ -1675,18 +2340,19 @@ impl CPU {
 
             // LD IX,nn
             0xDD21 => {
-                self.reg.set_ix(bus.read_word(self.reg.pc + 2));
+                self.reg.set_ix(self.fetch_word(bus, 2));
             }
 
             // LD IY,nn
             0xFD21 => {
-                self.reg.set_iy(bus.read_word(self.reg.pc + 2));
+                self.reg.set_iy(self.fetch_word(bus, 2));
             }
 
             // LD (IY+d),n
             0xFD36 => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
-                let data = bus.read_byte(self.reg.pc + 3);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
+                let data = self.fetch_byte(bus, 3);
                 if bit::get(displacement, 7) {
                     bus.write_byte(
                         self.reg.get_iy() - (signed_to_abs(displacement) as u16),
@@ -1705,6 +2371,7 @@ impl CPU {
                 self.reg.flags.h = false;
                 self.reg.flags.p = self.iff2;
                 self.reg.flags.n = false;
+                self.set_xy(self.reg.a);
                 // TODO :
                 // If an interrupt occurs during execution of this instruction, the Parity flag contains a 0.
             }
@@ -1717,6 +2384,7 @@ impl CPU {
                 self.reg.flags.h = false;
                 self.reg.flags.p = self.iff2;
                 self.reg.flags.n = false;
+                self.set_xy(self.reg.a);
                 // TODO :
                 // If an interrupt occurs during execution of this instruction, the Parity flag contains a 0.
             }
@@ -1731,81 +2399,93 @@ impl CPU {
             // LD dd,(nn)
             0xED4B => {
                 // LD BC,(nn)
-                let addr = bus.read_word(self.reg.pc + 2);
+                let addr = self.fetch_word(bus, 2);
                 let d = bus.read_word(addr);
+                self.memptr = addr.wrapping_add(1);
                 self.reg.set_bc(d);
             }
 
             0xED5B => {
                 // LD DE,(nn)
-                let addr = bus.read_word(self.reg.pc + 2);
+                let addr = self.fetch_word(bus, 2);
                 let d = bus.read_word(addr);
+                self.memptr = addr.wrapping_add(1);
                 self.reg.set_de(d);
             }
 
             0xED6B => {
                 // LD HL,(nn)
-                let addr = bus.read_word(self.reg.pc + 2);
+                let addr = self.fetch_word(bus, 2);
                 let d = bus.read_word(addr);
+                self.memptr = addr.wrapping_add(1);
                 self.reg.set_hl(d);
             }
 
             0xED7B => {
                 // LD SP,(nn)
-                let addr = bus.read_word(self.reg.pc + 2);
+                let addr = self.fetch_word(bus, 2);
                 let d = bus.read_word(addr);
+                self.memptr = addr.wrapping_add(1);
                 self.reg.sp = d;
             }
 
             // LD IX,(nn)
             0xDD2A => {
-                let addr = bus.read_word(self.reg.pc + 2);
+                let addr = self.fetch_word(bus, 2);
                 let d = bus.read_word(addr);
+                self.memptr = addr.wrapping_add(1);
                 self.reg.set_ix(d);
             }
 
             // LD IY,(nn)
             0xFD2A => {
-                let addr = bus.read_word(self.reg.pc + 2);
+                let addr = self.fetch_word(bus, 2);
                 let d = bus.read_word(addr);
+                self.memptr = addr.wrapping_add(1);
                 self.reg.set_iy(d);
             }
 
             // LD (nn),dd
             0xED43 => {
                 // LD (nn),BC
-                let addr = bus.read_word(self.reg.pc + 2);
+                let addr = self.fetch_word(bus, 2);
                 bus.write_word(addr, self.reg.get_bc());
+                self.memptr = addr.wrapping_add(1);
             }
 
             0xED53 => {
                 // LD (nn),DE
-                let addr = bus.read_word(self.reg.pc + 2);
+                let addr = self.fetch_word(bus, 2);
                 bus.write_word(addr, self.reg.get_de());
+                self.memptr = addr.wrapping_add(1);
             }
 
             0xED63 => {
                 // LD (nn),HL
-                let addr = bus.read_word(self.reg.pc + 2);
+                let addr = self.fetch_word(bus, 2);
                 bus.write_word(addr, self.reg.get_hl());
+                self.memptr = addr.wrapping_add(1);
             }
 
             0xED73 => {
                 // LD (nn),SP
-                let addr = bus.read_word(self.reg.pc + 2);
+                let addr = self.fetch_word(bus, 2);
                 bus.write_word(addr, self.reg.sp);
+                self.memptr = addr.wrapping_add(1);
             }
 
             // LD (nn),IX
             0xDD22 => {
-                let addr = bus.read_word(self.reg.pc + 2);
+                let addr = self.fetch_word(bus, 2);
                 bus.write_word(addr, self.reg.get_ix());
+                self.memptr = addr.wrapping_add(1);
             }
 
             // LD (nn),IY
             0xFD22 => {
-                let addr = bus.read_word(self.reg.pc + 2);
+                let addr = self.fetch_word(bus, 2);
                 bus.write_word(addr, self.reg.get_iy());
+                self.memptr = addr.wrapping_add(1);
             }
 
             // LD SP,IX
@@ -1844,6 +2524,7 @@ impl CPU {
                 let pointed_by_sp = bus.read_word(self.reg.sp);
                 bus.write_word(self.reg.sp, self.reg.get_ix());
                 self.reg.set_ix(pointed_by_sp);
+                self.memptr = pointed_by_sp;
             }
 
             // EX (SP),IY
@@ -1851,50 +2532,25 @@ impl CPU {
                 let pointed_by_sp = bus.read_word(self.reg.sp);
                 bus.write_word(self.reg.sp, self.reg.get_iy());
                 self.reg.set_iy(pointed_by_sp);
+                self.memptr = pointed_by_sp;
             }
 
             // LDI
-            0xEDA0 => {
-                self.ldi(bus);
-                self.reg.flags.h = false;
-                let bc = self.reg.get_bc();
-                self.reg.flags.p = bc != 0;
-                self.reg.flags.n = false;
-            }
+            0xEDA0 => self.ldi(bus),
 
             // LDIR
             0xEDB0 => {
-                // TODO : When the BC is set to 0 prior to instruction execution, the instruction loops through 64 KB.
-                while self.reg.get_bc() != 0 {
-                    self.ldi(bus);
-                    let bc = self.reg.get_bc();
-                    self.reg.flags.h = false;
-                    self.reg.flags.p = bc != 0;
-                    self.reg.flags.n = false;
-                    // TODO : return cycles * number of executions
-                }
+                self.ldi(bus);
+                cycles = self.block_repeat(self.reg.get_bc() != 0);
             }
 
             // LDD
-            0xEDA8 => {
-                self.ldd(bus);
-                self.reg.flags.h = false;
-                let bc = self.reg.get_bc();
-                self.reg.flags.p = bc != 0;
-                self.reg.flags.n = false;
-            }
+            0xEDA8 => self.ldd(bus),
 
             // LDDR
             0xEDB8 => {
-                // TODO : When the BC is set to 0 prior to instruction execution, the instruction loops through 64 KB.
-                while self.reg.get_bc() != 0 {
-                    self.ldd(bus);
-                    let bc = self.reg.get_bc();
-                    self.reg.flags.h = false;
-                    self.reg.flags.p = bc != 0;
-                    self.reg.flags.n = false;
-                    // TODO : return cycles * number of executions
-                }
+                self.ldd(bus);
+                cycles = self.block_repeat(self.reg.get_bc() != 0);
             }
 
             // CPI
@@ -1902,14 +2558,8 @@ impl CPU {
 
             // CPIR
             0xEDB1 => {
-                // TODO : When the BC is set to 0 prior to instruction execution, the instruction loops through 64 KB.
-                while self.reg.get_bc() != 0 {
-                    self.cpi(bus);
-                    if self.reg.flags.z {
-                        break;
-                    }
-                    // TODO : return cycles * number of executions
-                }
+                self.cpi(bus);
+                cycles = self.block_repeat(self.reg.get_bc() != 0 && !self.reg.flags.z);
             }
 
             // CPD
@@ -1917,19 +2567,75 @@ impl CPU {
 
             // CPDR
             0xEDB9 => {
-                while self.reg.get_bc() != 0 {
-                    self.cpd(bus);
-                    if self.reg.flags.z {
-                        break;
-                    }
-                    // TODO : return cycles * number of executions
-                }
+                self.cpd(bus);
+                cycles = self.block_repeat(self.reg.get_bc() != 0 && !self.reg.flags.z);
+            }
+
+            // Input and Output Group
+            // IN r,(C)
+            0xED40 => self.reg.b = self.in_c(bus), // IN B,(C)
+            0xED48 => self.reg.c = self.in_c(bus), // IN C,(C)
+            0xED50 => self.reg.d = self.in_c(bus), // IN D,(C)
+            0xED58 => self.reg.e = self.in_c(bus), // IN E,(C)
+            0xED60 => self.reg.h = self.in_c(bus), // IN H,(C)
+            0xED68 => self.reg.l = self.in_c(bus), // IN L,(C)
+            0xED70 => {
+                // IN (C) : undocumented, only affects flags
+                self.in_c(bus);
+            }
+            0xED78 => self.reg.a = self.in_c(bus), // IN A,(C)
+
+            // OUT (C),r
+            0xED41 => self.out_c(bus, self.reg.b), // OUT (C),B
+            0xED49 => self.out_c(bus, self.reg.c), // OUT (C),C
+            0xED51 => self.out_c(bus, self.reg.d), // OUT (C),D
+            0xED59 => self.out_c(bus, self.reg.e), // OUT (C),E
+            0xED61 => self.out_c(bus, self.reg.h), // OUT (C),H
+            0xED69 => self.out_c(bus, self.reg.l), // OUT (C),L
+            0xED71 => self.out_c(bus, 0),          // OUT (C),0 : undocumented
+            0xED79 => self.out_c(bus, self.reg.a), // OUT (C),A
+
+            // INI
+            0xEDA2 => self.ini(bus),
+
+            // INIR
+            0xEDB2 => {
+                self.ini(bus);
+                cycles = self.block_repeat(self.reg.b != 0);
+            }
+
+            // IND
+            0xEDAA => self.ind(bus),
+
+            // INDR
+            0xEDBA => {
+                self.ind(bus);
+                cycles = self.block_repeat(self.reg.b != 0);
+            }
+
+            // OUTI
+            0xEDA3 => self.outi(bus),
+
+            // OTIR
+            0xEDB3 => {
+                self.outi(bus);
+                cycles = self.block_repeat(self.reg.b != 0);
+            }
+
+            // OUTD
+            0xEDAB => self.outd(bus),
+
+            // OTDR
+            0xEDBB => {
+                self.outd(bus);
+                cycles = self.block_repeat(self.reg.b != 0);
             }
 
             // 8-Bit Arithmetic Group
             // ADD A,(IX+d)
             0xDD86 => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let d = bus.read_byte(self.reg.get_ix() - (signed_to_abs(displacement) as u16));
                     self.add(d);
@@ -1941,7 +2647,8 @@ impl CPU {
 
             // ADD A,(IY+d)
             0xFD86 => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let d = bus.read_byte(self.reg.get_iy() - (signed_to_abs(displacement) as u16));
                     self.add(d);
@@ -1953,7 +2660,8 @@ impl CPU {
 
             // ADC A,(IX+d)
             0xDD8E => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let d = bus.read_byte(self.reg.get_ix() - (signed_to_abs(displacement) as u16));
                     self.adc(d);
@@ -1965,7 +2673,8 @@ impl CPU {
 
             // ADC A,(IY+d)
             0xFD8E => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let d = bus.read_byte(self.reg.get_iy() - (signed_to_abs(displacement) as u16));
                     self.adc(d);
@@ -1977,7 +2686,8 @@ impl CPU {
 
             // SUB (IX+d)
             0xDD96 => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let d = bus.read_byte(self.reg.get_ix() - (signed_to_abs(displacement) as u16));
                     self.sub(d);
@@ -1989,7 +2699,8 @@ impl CPU {
 
             // SUB (IY+d)
             0xFD96 => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let d = bus.read_byte(self.reg.get_iy() - (signed_to_abs(displacement) as u16));
                     self.sub(d);
@@ -2001,7 +2712,8 @@ impl CPU {
 
             // SBC (IX+d)
             0xDD9E => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let d = bus.read_byte(self.reg.get_ix() - (signed_to_abs(displacement) as u16));
                     self.sbc(d);
@@ -2013,7 +2725,8 @@ impl CPU {
 
             // SBC (IY+d)
             0xFD9E => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let d = bus.read_byte(self.reg.get_iy() - (signed_to_abs(displacement) as u16));
                     self.sbc(d);
@@ -2025,7 +2738,8 @@ impl CPU {
 
             // AND (IX+d)
             0xDDA6 => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let d = bus.read_byte(self.reg.get_ix() - (signed_to_abs(displacement) as u16));
                     self.and(d);
@@ -2037,7 +2751,8 @@ impl CPU {
 
             // AND (IY+d)
             0xFDA6 => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let d = bus.read_byte(self.reg.get_iy() - (signed_to_abs(displacement) as u16));
                     self.and(d);
@@ -2049,7 +2764,8 @@ impl CPU {
 
             // OR (IX+d)
             0xDDB6 => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let d = bus.read_byte(self.reg.get_ix() - (signed_to_abs(displacement) as u16));
                     self.or(d);
@@ -2061,7 +2777,8 @@ impl CPU {
 
             // OR (IY+d)
             0xFDB6 => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let d = bus.read_byte(self.reg.get_iy() - (signed_to_abs(displacement) as u16));
                     self.or(d);
@@ -2073,7 +2790,8 @@ impl CPU {
 
             // XOR (IX+d)
             0xDDAE => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let d = bus.read_byte(self.reg.get_ix() - (signed_to_abs(displacement) as u16));
                     self.xor(d);
@@ -2085,7 +2803,8 @@ impl CPU {
 
             // XOR (IY+d)
             0xFDAE => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let d = bus.read_byte(self.reg.get_iy() - (signed_to_abs(displacement) as u16));
                     self.xor(d);
@@ -2097,7 +2816,8 @@ impl CPU {
 
             // CP (IX+d)
             0xDDBE => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let d = bus.read_byte(self.reg.get_ix() - (signed_to_abs(displacement) as u16));
                     self.cp(d);
@@ -2109,7 +2829,8 @@ impl CPU {
 
             // CP (IY+d)
             0xFDBE => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let d = bus.read_byte(self.reg.get_iy() - (signed_to_abs(displacement) as u16));
                     self.cp(d);
@@ -2121,7 +2842,8 @@ impl CPU {
 
             // INC (IX+d)
             0xDD34 => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_ix() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -2137,7 +2859,8 @@ impl CPU {
 
             // INC (IY+d)
             0xFD34 => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_iy() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -2153,7 +2876,8 @@ impl CPU {
 
             // DEC (IX+d)
             0xDD35 => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_ix() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -2169,7 +2893,8 @@ impl CPU {
 
             // DEC (IY+d)
             0xFD35 => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                let displacement = self.fetch_byte(bus, 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_iy() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -2189,8 +2914,12 @@ impl CPU {
                 self.neg();
             }
 
-            // RETI
-            0xED4D => self.call_stack_pop(bus),
+            // RETI : decoded by the daisy chain devices to end the interrupt service. Like RETN, it copies IFF2 into IFF1
+            0xED4D => {
+                self.iff1 = self.iff2;
+                self.call_stack_pop(bus);
+                self.daisy_chain_reti();
+            }
 
             // RETN
             0xED45 => {
@@ -2691,6 +3420,8 @@ impl CPU {
                 self.reg.flags.h = false;
                 self.reg.flags.p = r.count_ones() & 0x01 == 0x00;
                 self.reg.flags.n = false;
+                self.set_xy(r);
+                self.memptr = self.reg.get_hl().wrapping_add(1);
             }
 
             // RRD
@@ -2709,17 +3440,19 @@ impl CPU {
                 self.reg.flags.h = false;
                 self.reg.flags.p = r.count_ones() & 0x01 == 0x00;
                 self.reg.flags.n = false;
+                self.set_xy(r);
+                self.memptr = self.reg.get_hl().wrapping_add(1);
             }
 
             // Bit Set, Reset, and Test Group
             // BIT b,r
-            0xCB40..=0xCB7F => self.bit(bus, bus.read_byte(self.reg.pc + 1)),
+            0xCB40..=0xCB7F => self.bit(bus, opcode as u8),
 
             // SET b,r
-            0xCBC0..=0xCBFF => self.set(bus, bus.read_byte(self.reg.pc + 1)),
+            0xCBC0..=0xCBFF => self.set(bus, opcode as u8),
 
             // RES b,r
-            0xCB80..=0xCBBF => self.reset(bus, bus.read_byte(self.reg.pc + 1)),
+            0xCB80..=0xCBBF => self.res(bus, opcode as u8),
 
             // Jump group
             // JP (IX)
@@ -2953,42 +3686,58 @@ impl CPU {
                 self.reg.ixl = r;
             }
 
+            // INC IYH
+            0xFD24 => {
+                let n = self.reg.iyh;
+                let r = self.inc(n);
+                self.reg.iyh = r;
+            }
+
+            // DEC IYH
+            0xFD25 => {
+                let n = self.reg.iyh;
+                let r = self.dec(n);
+                self.reg.iyh = r;
+            }
+
+            // INC IYL
+            0xFD2C => {
+                let n = self.reg.iyl;
+                let r = self.inc(n);
+                self.reg.iyl = r;
+            }
+
+            // DEC IYL
+            0xFD2D => {
+                let n = self.reg.iyl;
+                let r = self.dec(n);
+                self.reg.iyl = r;
+            }
+
             // LD IXH,n
             0xDD26 => {
-                let n = bus.read_byte(self.reg.pc + 3);
+                let n = self.fetch_byte(bus, 2);
                 self.reg.ixh = n;
             }
 
             // LD IYH,n
             0xFD26 => {
-                let n = bus.read_byte(self.reg.pc + 3);
+                let n = self.fetch_byte(bus, 2);
                 self.reg.iyh = n;
             }
 
             // LD IXL,n
             0xDD2E => {
-                let n = bus.read_byte(self.reg.pc + 3);
+                let n = self.fetch_byte(bus, 2);
                 self.reg.ixl = n;
             }
 
             // LD IYL,n
             0xFD2E => {
-                let n = bus.read_byte(self.reg.pc + 3);
+                let n = self.fetch_byte(bus, 2);
                 self.reg.iyl = n;
             }
 
-            // LD B,B
-            0xDD40 | 0xFD40 => {}
-
-            // LD B,C
-            0xDD41 | 0xFD41 => self.reg.b = self.reg.c,
-
-            // LD B,D
-            0xDD42 | 0xFD42 => self.reg.b = self.reg.d,
-
-            // LD B,E
-            0xDD43 | 0xFD43 => self.reg.b = self.reg.e,
-
             // LD B,IXH
             0xDD44 => self.reg.b = self.reg.ixh,
 
@@ -3001,21 +3750,6 @@ impl CPU {
             // LD B,IYL
             0xFD45 => self.reg.b = self.reg.iyl,
 
-            // LD B,A
-            0xDD47 | 0xFD47 => self.reg.b = self.reg.a,
-
-            // LD C,B
-            0xDD48 | 0xFD48 => self.reg.c = self.reg.b,
-
-            // LD C,C
-            0xDD49 | 0xFD49 => {}
-
-            // LD C,D
-            0xDD4A | 0xFD4A => self.reg.c = self.reg.d,
-
-            // LD C,E
-            0xDD4B | 0xFD4B => self.reg.c = self.reg.e,
-
             // LD C,IXH
             0xDD4C => self.reg.c = self.reg.ixh,
 
@@ -3028,21 +3762,6 @@ impl CPU {
             // LD C,IYL
             0xFD4D => self.reg.c = self.reg.iyl,
 
-            // LD C,A
-            0xDD4F | 0xFD4F => self.reg.c = self.reg.a,
-
-            // LD D,B
-            0xDD50 | 0xFD50 => self.reg.d = self.reg.b,
-
-            // LD D,C
-            0xDD51 | 0xFD51 => self.reg.d = self.reg.c,
-
-            // LD D,D
-            0xDD52 | 0xFD52 => {}
-
-            // LD D,E
-            0xDD53 | 0xFD53 => self.reg.d = self.reg.e,
-
             // LD D,IXH
             0xDD54 => self.reg.d = self.reg.ixh,
 
@@ -3055,21 +3774,6 @@ impl CPU {
             // LD D,IYL
             0xFD55 => self.reg.d = self.reg.iyl,
 
-            // LD D,A
-            0xDD57 | 0xFD57 => self.reg.d = self.reg.a,
-
-            // LD E,B
-            0xDD58 | 0xFD58 => self.reg.e = self.reg.b,
-
-            // LD E,C
-            0xDD59 | 0xFD59 => self.reg.e = self.reg.c,
-
-            // LD E,D
-            0xDD5A | 0xFD5A => self.reg.e = self.reg.d,
-
-            // LD E,E
-            0xDD5B | 0xFD5B => {}
-
             // LD E,IXH
             0xDD5C => self.reg.e = self.reg.ixh,
 
@@ -3082,9 +3786,6 @@ impl CPU {
             // LD E,IYL
             0xFD5D => self.reg.e = self.reg.iyl,
 
-            // LD E,A
-            0xDD5F | 0xFD5F => self.reg.e = self.reg.a,
-
             // LD IXH,B
             0xDD60 => self.reg.ixh = self.reg.b,
 
@@ -3169,18 +3870,6 @@ impl CPU {
             // LD IYL,A
             0xFD6F => self.reg.iyl = self.reg.a,
 
-            // LD A,B
-            0xDD78 | 0xFD78 => self.reg.a = self.reg.b,
-
-            // LD A,C
-            0xDD79 | 0xFD79 => self.reg.a = self.reg.c,
-
-            // LD A,D
-            0xDD7A | 0xFD7A => self.reg.a = self.reg.d,
-
-            // LD A,E
-            0xDD7B | 0xFD7B => self.reg.a = self.reg.e,
-
             // LD A,IXH
             0xDD7C => self.reg.a = self.reg.ixh,
 
@@ -3193,9 +3882,6 @@ impl CPU {
             // LD A,IYL
             0xFD7D => self.reg.a = self.reg.iyl,
 
-            // LD A,A
-            0xDD7F | 0xFD7F => {}
-
             // SLL B
             0xCB30 => {
                 let r = self.sll(self.reg.b);
@@ -3249,7 +3935,7 @@ impl CPU {
                 if self.debug.unknw_instr {
                     self.debug.string = format!("{:#06X}", opcode);
                 }
-                cycles = 0xFF;
+                return None;
             }
         }
 
@@ -3260,30 +3946,32 @@ impl CPU {
             | 0xDD74 | 0xDD75 | 0xDD77 | 0xFD70 | 0xFD71 | 0xFD72 | 0xFD73 | 0xFD74 | 0xFD75
             | 0xFD77 | 0xDD86 | 0xFD86 | 0xDD8E | 0xFD8E | 0xDD96 | 0xFD96 | 0xDD9E | 0xFD9E
             | 0xDDA6 | 0xFDA6 | 0xDDB6 | 0xFDB6 | 0xDDAE | 0xFDAE | 0xDDBE | 0xFDBE | 0xDD34
-            | 0xFD34 | 0xDD35 | 0xFD35 => self.reg.pc += 3,
+            | 0xFD34 | 0xDD35 | 0xFD35 | 0xDD26 | 0xFD26 | 0xDD2E | 0xFD2E => {
+                self.reg.pc = self.reg.pc.wrapping_add(3)
+            }
             0xDD36 | 0xFD36 | 0xDD21 | 0xFD21 | 0xED4B | 0xED5B | 0xED6B | 0xED7B | 0xDD2A
             | 0xFD2A | 0xED43 | 0xED53 | 0xED63 | 0xED73 | 0xDD22 | 0xFD22 | 0xDDCB | 0xFDCB => {
-                self.reg.pc += 4
+                self.reg.pc = self.reg.pc.wrapping_add(4)
             }
-            _ => self.reg.pc += 2,
+            _ => self.reg.pc = self.reg.pc.wrapping_add(2),
         }
 
         if self.debug.opcode {
             self.debug.string = format!("{:#06X}", opcode)
         }
 
-        cycles
+        Some(cycles)
     }
 
     // DDCB FDCB
-    fn execute_4bytes(&mut self, bus: &mut Bus) -> u32 {
-        let opcode = bus.read_le_dword(self.reg.pc);
+    fn execute_4bytes(&mut self, bus: &mut M, opcode: u32) -> u32 {
+        let displacement = (opcode >> 8) as u8;
         let cycles;
 
         match opcode & 0xFFFF00FF {
             0xDDCB0006 => {
                 // RLC (IX+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_ix() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -3300,7 +3988,7 @@ impl CPU {
 
             0xFDCB0006 => {
                 // RLC (IY+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_iy() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -3317,7 +4005,7 @@ impl CPU {
 
             0xDDCB0016 => {
                 // RL (IX+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_ix() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -3334,7 +4022,7 @@ impl CPU {
 
             0xFDCB0016 => {
                 // RL (IY+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_iy() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -3351,7 +4039,7 @@ impl CPU {
 
             0xDDCB000E => {
                 // RRC (IX+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_ix() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -3368,7 +4056,7 @@ impl CPU {
 
             0xFDCB000E => {
                 // RRC (IY+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_iy() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -3385,7 +4073,7 @@ impl CPU {
 
             0xDDCB001E => {
                 // RR (IX+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_ix() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -3402,7 +4090,7 @@ impl CPU {
 
             0xFDCB001E => {
                 // RR (IY+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_iy() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -3419,7 +4107,7 @@ impl CPU {
 
             0xDDCB0026 => {
                 // SLA (IX+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_ix() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -3436,7 +4124,7 @@ impl CPU {
 
             0xFDCB0026 => {
                 // SLA (IY+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_iy() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -3453,7 +4141,7 @@ impl CPU {
 
             0xDDCB002E => {
                 // SRA (IX+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_ix() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -3470,7 +4158,7 @@ impl CPU {
 
             0xFDCB002E => {
                 // SRA (IY+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_iy() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -3487,7 +4175,7 @@ impl CPU {
 
             0xDDCB003E => {
                 // SRL (IX+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_ix() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -3504,7 +4192,7 @@ impl CPU {
 
             0xFDCB003E => {
                 // SRL (IY+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_iy() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -3522,23 +4210,17 @@ impl CPU {
             0xDDCB0046 | 0xDDCB004E | 0xDDCB0056 | 0xDDCB005E | 0xDDCB0066 | 0xDDCB006E
             | 0xDDCB0076 | 0xDDCB007E => {
                 // BIT b,(IX+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
-                let operand = bus.read_byte(self.reg.pc + 3);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
+                let operand = opcode as u8;
                 let bit = ((operand & 0x38) >> 3) as usize;
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_ix() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
-                    let r = bit::get(d, bit);
-                    self.reg.flags.z = !r;
-                    self.reg.flags.h = true;
-                    self.reg.flags.n = false;
+                    self.bit_flags(bit, d, (m >> 8) as u8);
                 } else {
                     let m = self.reg.get_ix() + (displacement as u16);
                     let d = bus.read_byte(m);
-                    let r = bit::get(d, bit);
-                    self.reg.flags.z = !r;
-                    self.reg.flags.h = true;
-                    self.reg.flags.n = false;
+                    self.bit_flags(bit, d, (m >> 8) as u8);
                 }
                 cycles = 20;
             }
@@ -3546,23 +4228,17 @@ impl CPU {
             0xFDCB0046 | 0xFDCB004E | 0xFDCB0056 | 0xFDCB005E | 0xFDCB0066 | 0xFDCB006E
             | 0xFDCB0076 | 0xFDCB007E => {
                 // BIT b,(IY+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
-                let operand = bus.read_byte(self.reg.pc + 3);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
+                let operand = opcode as u8;
                 let bit = ((operand & 0x38) >> 3) as usize;
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_iy() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
-                    let r = bit::get(d, bit);
-                    self.reg.flags.z = !r;
-                    self.reg.flags.h = true;
-                    self.reg.flags.n = false;
+                    self.bit_flags(bit, d, (m >> 8) as u8);
                 } else {
                     let m = self.reg.get_iy() + (displacement as u16);
                     let d = bus.read_byte(m);
-                    let r = bit::get(d, bit);
-                    self.reg.flags.z = !r;
-                    self.reg.flags.h = true;
-                    self.reg.flags.n = false;
+                    self.bit_flags(bit, d, (m >> 8) as u8);
                 }
                 cycles = 20;
             }
@@ -3570,8 +4246,8 @@ impl CPU {
             0xDDCB00C6 | 0xDDCB00CE | 0xDDCB00D6 | 0xDDCB00DE | 0xDDCB00E6 | 0xDDCB00EE
             | 0xDDCB00F6 | 0xDDCB00FE => {
                 // SET b,(IX+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
-                let operand = bus.read_byte(self.reg.pc + 3);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
+                let operand = opcode as u8;
                 let bit = ((operand & 0x38) >> 3) as usize;
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_ix() - (signed_to_abs(displacement) as u16);
@@ -3590,8 +4266,8 @@ impl CPU {
             0xFDCB00C6 | 0xFDCB00CE | 0xFDCB00D6 | 0xFDCB00DE | 0xFDCB00E6 | 0xFDCB00EE
             | 0xFDCB00F6 | 0xFDCB00FE => {
                 // SET b,(IY+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
-                let operand = bus.read_byte(self.reg.pc + 3);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
+                let operand = opcode as u8;
                 let bit = ((operand & 0x38) >> 3) as usize;
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_iy() - (signed_to_abs(displacement) as u16);
@@ -3610,8 +4286,8 @@ impl CPU {
             0xDDCB0086 | 0xDDCB008E | 0xDDCB0096 | 0xDDCB009E | 0xDDCB00A6 | 0xDDCB00AE
             | 0xDDCB00B6 | 0xDDCB00BE => {
                 // RES b,(IX+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
-                let operand = bus.read_byte(self.reg.pc + 3);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
+                let operand = opcode as u8;
                 let bit = ((operand & 0x38) >> 3) as usize;
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_ix() - (signed_to_abs(displacement) as u16);
@@ -3630,8 +4306,8 @@ impl CPU {
             0xFDCB0086 | 0xFDCB008E | 0xFDCB0096 | 0xFDCB009E | 0xFDCB00A6 | 0xFDCB00AE
             | 0xFDCB00B6 | 0xFDCB00BE => {
                 // RES b,(IY+d)
-                let displacement = bus.read_byte(self.reg.pc + 2);
-                let operand = bus.read_byte(self.reg.pc + 3);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
+                let operand = opcode as u8;
                 let bit = ((operand & 0x38) >> 3) as usize;
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_iy() - (signed_to_abs(displacement) as u16);
@@ -3650,7 +4326,7 @@ impl CPU {
             // Undocumented instructions
             // SLL (IX+d)
             0xDDCB0036 => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                self.memptr = self.reg.get_ix().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_ix() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -3667,7 +4343,7 @@ impl CPU {
 
             // SLL (IY+d)
             0xFDCB0036 => {
-                let displacement = bus.read_byte(self.reg.pc + 2);
+                self.memptr = self.reg.get_iy().wrapping_add(displacement as i8 as u16);
                 if bit::get(displacement, 7) {
                     let m = self.reg.get_iy() - (signed_to_abs(displacement) as u16);
                     let d = bus.read_byte(m);
@@ -3682,42 +4358,95 @@ impl CPU {
                 cycles = 23;
             }
 
-            _ => {
-                if self.debug.unknw_instr {
-                    self.debug.string = format!("{:#10X}", opcode)
+            // Undocumented instructions
+            // BIT b,(IX+d) / BIT b,(IY+d) aliases
+            // RLC (IX+d),r ... SET b,(IY+d),r : the result is also copied to register r
+            0xDDCB0000..=0xDDCB00FF | 0xFDCB0000..=0xFDCB00FF => {
+                let operand = opcode as u8;
+                let index = match opcode >> 24 {
+                    0xDD => self.reg.get_ix(),
+                    _ => self.reg.get_iy(),
                 };
-                cycles = 0xFF;
+                let m = index.wrapping_add(displacement as i8 as u16);
+                self.memptr = m;
+                let d = bus.read_byte(m);
+                let bit = ((operand & 0x38) >> 3) as usize;
+                if operand & 0xC0 == 0x40 {
+                    self.bit_flags(bit, d, (m >> 8) as u8);
+                    cycles = 20;
+                } else {
+                    let r = match operand & 0xF8 {
+                        0x00 => self.rlc(d),
+                        0x08 => self.rrc(d),
+                        0x10 => self.rl(d),
+                        0x18 => self.rr(d),
+                        0x20 => self.sla(d),
+                        0x28 => self.sra(d),
+                        0x30 => self.sll(d),
+                        0x38 => self.srl(d),
+                        0x80..=0xB8 => bit::reset(d, bit),
+                        _ => bit::set(d, bit),
+                    };
+                    bus.write_byte(m, r);
+                    match operand & 0x07 {
+                        0 => self.reg.b = r,
+                        1 => self.reg.c = r,
+                        2 => self.reg.d = r,
+                        3 => self.reg.e = r,
+                        4 => self.reg.h = r,
+                        5 => self.reg.l = r,
+                        _ => self.reg.a = r,
+                    }
+                    cycles = 23;
+                }
             }
+
+            // All the DDCB / FDCB instructions are decoded above
+            _ => unreachable!(),
         }
-        self.reg.pc += 4;
+        self.reg.pc = self.reg.pc.wrapping_add(4);
         if self.debug.opcode {
             self.debug.string = format!("{:#10X}", opcode)
         }
         cycles
     }
 
-    fn ldi(&mut self, bus: &mut Bus) {
+    fn ldi(&mut self, bus: &mut M) {
         let bc = self.reg.get_bc();
         let de = self.reg.get_de();
         let hl = self.reg.get_hl();
-        bus.write_byte(de, bus.read_byte(hl));
+        let data = bus.read_byte(hl);
+        bus.write_byte(de, data);
         self.reg.set_de(de.wrapping_add(1));
         self.reg.set_hl(hl.wrapping_add(1));
         self.reg.set_bc(bc.wrapping_sub(1));
+        self.reg.flags.h = false;
+        self.reg.flags.p = bc != 1;
+        self.reg.flags.n = false;
+        // X and Y are bits 3 and 1 of A + transferred byte
+        let n = self.reg.a.wrapping_add(data);
+        self.set_xy((n & 0x08) | ((n & 0x02) << 4));
     }
 
-    fn ldd(&mut self, bus: &mut Bus) {
+    fn ldd(&mut self, bus: &mut M) {
         let bc = self.reg.get_bc();
         let de = self.reg.get_de();
         let hl = self.reg.get_hl();
-        bus.write_byte(de, bus.read_byte(hl));
+        let data = bus.read_byte(hl);
+        bus.write_byte(de, data);
         self.reg.set_de(de.wrapping_sub(1));
         self.reg.set_hl(hl.wrapping_sub(1));
         self.reg.set_bc(bc.wrapping_sub(1));
+        self.reg.flags.h = false;
+        self.reg.flags.p = bc != 1;
+        self.reg.flags.n = false;
+        // X and Y are bits 3 and 1 of A + transferred byte
+        let n = self.reg.a.wrapping_add(data);
+        self.set_xy((n & 0x08) | ((n & 0x02) << 4));
     }
 
     // Returns A - (HL)
-    fn cpi(&mut self, bus: &mut Bus) {
+    fn cpi(&mut self, bus: &mut M) {
         let bc = self.reg.get_bc();
         let hl = self.reg.get_hl();
         let h = bus.read_byte(hl);
@@ -3725,16 +4454,20 @@ impl CPU {
 
         self.reg.set_hl(hl.wrapping_add(1));
         self.reg.set_bc(bc.wrapping_sub(1));
+        self.memptr = self.memptr.wrapping_add(1);
 
         self.reg.flags.s = r & 0x80 == 0x80;
         self.reg.flags.z = self.reg.a == h;
         self.reg.flags.h = (self.reg.a as i8 & 0x0F) < (h as i8 & 0x0F);
         self.reg.flags.p = self.reg.get_bc() != 0;
         self.reg.flags.n = true;
+        // X and Y are bits 3 and 1 of A - (HL) - H
+        let n = r.wrapping_sub(u8::from(self.reg.flags.h));
+        self.set_xy((n & 0x08) | ((n & 0x02) << 4));
     }
 
     // Returns A - (HL)
-    fn cpd(&mut self, bus: &mut Bus) {
+    fn cpd(&mut self, bus: &mut M) {
         let bc = self.reg.get_bc();
         let hl = self.reg.get_hl();
         let h = bus.read_byte(hl);
@@ -3742,12 +4475,125 @@ impl CPU {
 
         self.reg.set_hl(hl.wrapping_sub(1));
         self.reg.set_bc(bc.wrapping_sub(1));
+        self.memptr = self.memptr.wrapping_sub(1);
 
         self.reg.flags.s = r & 0x80 == 0x80;
         self.reg.flags.z = self.reg.a == h;
         self.reg.flags.h = (self.reg.a as i8 & 0x0F) < (h as i8 & 0x0F);
         self.reg.flags.p = self.reg.get_bc() != 0;
         self.reg.flags.n = true;
+        // X and Y are bits 3 and 1 of A - (HL) - H
+        let n = r.wrapping_sub(u8::from(self.reg.flags.h));
+        self.set_xy((n & 0x08) | ((n & 0x02) << 4));
+    }
+
+    // Reads a port
+    fn port_in(&mut self, bus: &mut M, addr: u16) -> u8 {
+        let data = bus.io_read(addr);
+        if self.debug.instr_in {
+            self.debug.string = format!("IN : {:#06X} -> {:#04X}", addr, data);
+        }
+        data
+    }
+
+    // Writes to a port
+    fn port_out(&mut self, bus: &mut M, addr: u16, data: u8) {
+        if self.debug.io {
+            self.debug.string = format!("OUT : {:#04X} -> {:#06X}", data, addr);
+        }
+        bus.io_write(addr, data);
+    }
+
+    // IN r,(C)
+    fn in_c(&mut self, bus: &mut M) -> u8 {
+        let r = self.port_in(bus, self.reg.get_bc());
+        self.memptr = self.reg.get_bc().wrapping_add(1);
+        self.reg.flags.s = r & 0x80 == 0x80;
+        self.reg.flags.z = r == 0x00;
+        self.reg.flags.h = false;
+        self.reg.flags.p = r.count_ones() & 0x01 == 0x00;
+        self.reg.flags.n = false;
+        self.set_xy(r);
+        r
+    }
+
+    // OUT (C),r
+    fn out_c(&mut self, bus: &mut M, data: u8) {
+        self.port_out(bus, self.reg.get_bc(), data);
+        self.memptr = self.reg.get_bc().wrapping_add(1);
+    }
+
+    // INI
+    fn ini(&mut self, bus: &mut M) {
+        let hl = self.reg.get_hl();
+        let data = self.port_in(bus, self.reg.get_bc());
+        self.memptr = self.reg.get_bc().wrapping_add(1);
+        bus.write_byte(hl, data);
+        self.reg.b = self.reg.b.wrapping_sub(1);
+        self.reg.set_hl(hl.wrapping_add(1));
+        let k = data as u16 + self.reg.c.wrapping_add(1) as u16;
+        self.block_io_flags(data, k);
+    }
+
+    // IND
+    fn ind(&mut self, bus: &mut M) {
+        let hl = self.reg.get_hl();
+        let data = self.port_in(bus, self.reg.get_bc());
+        self.memptr = self.reg.get_bc().wrapping_sub(1);
+        bus.write_byte(hl, data);
+        self.reg.b = self.reg.b.wrapping_sub(1);
+        self.reg.set_hl(hl.wrapping_sub(1));
+        let k = data as u16 + self.reg.c.wrapping_sub(1) as u16;
+        self.block_io_flags(data, k);
+    }
+
+    // OUTI
+    fn outi(&mut self, bus: &mut M) {
+        let hl = self.reg.get_hl();
+        let data = bus.read_byte(hl);
+        self.reg.b = self.reg.b.wrapping_sub(1);
+        self.port_out(bus, self.reg.get_bc(), data);
+        self.memptr = self.reg.get_bc().wrapping_add(1);
+        self.reg.set_hl(hl.wrapping_add(1));
+        let k = data as u16 + self.reg.l as u16;
+        self.block_io_flags(data, k);
+    }
+
+    // OUTD
+    fn outd(&mut self, bus: &mut M) {
+        let hl = self.reg.get_hl();
+        let data = bus.read_byte(hl);
+        self.reg.b = self.reg.b.wrapping_sub(1);
+        self.port_out(bus, self.reg.get_bc(), data);
+        self.memptr = self.reg.get_bc().wrapping_sub(1);
+        self.reg.set_hl(hl.wrapping_sub(1));
+        let k = data as u16 + self.reg.l as u16;
+        self.block_io_flags(data, k);
+    }
+
+    // Repeating block instructions execute one iteration per step : while repeating, PC is moved back to the instruction (21 T-states).
+    // The last iteration takes 16 T-states.
+    fn block_repeat(&mut self, repeat: bool) -> u32 {
+        if repeat {
+            self.memptr = self.reg.pc.wrapping_add(1);
+            self.reg.pc = self.reg.pc.wrapping_sub(2);
+            21
+        } else {
+            16
+        }
+    }
+
+    // Flags of the block I/O instructions (mostly undocumented).
+    // data is the transferred byte, k the sum of data and the updated C (INI/IND) or L (OUTI/OUTD) register.
+    fn block_io_flags(&mut self, data: u8, k: u16) {
+        let b = self.reg.b;
+        self.reg.flags.s = b & 0x80 == 0x80;
+        self.reg.flags.z = b == 0x00;
+        self.reg.flags.n = data & 0x80 == 0x80;
+        self.reg.flags.h = k > 0xFF;
+        self.reg.flags.c = k > 0xFF;
+        self.reg.flags.p = (((k & 0x07) as u8) ^ b).count_ones() & 0x01 == 0x00;
+        self.set_xy(b);
     }
 
     // ADD A,r
@@ -3760,6 +4606,7 @@ impl CPU {
         self.reg.flags.h = (a & 0x0f) + (n & 0x0f) > 0x0f;
         self.reg.flags.c = u16::from(a) + u16::from(n) > 0xff;
         self.reg.flags.n = false;
+        self.set_xy(r);
         self.reg.a = r;
     }
 
@@ -3777,6 +4624,7 @@ impl CPU {
         self.reg.flags.h = (a & 0x0f) + (n & 0x0f) + c > 0x0f;
         self.reg.flags.c = u16::from(a) + u16::from(n) + u16::from(c) > 0xff;
         self.reg.flags.n = false;
+        self.set_xy(r);
         self.reg.a = r;
     }
 
@@ -3790,6 +4638,7 @@ impl CPU {
         self.reg.flags.h = (a as i8 & 0x0f) < (n as i8 & 0x0f);
         self.reg.flags.c = u16::from(a) < u16::from(n);
         self.reg.flags.n = true;
+        self.set_xy(r);
         self.reg.a = r;
     }
 
@@ -3807,6 +4656,7 @@ impl CPU {
         self.reg.flags.h = (a & 0x0f) < (n & 0x0f) + c;
         self.reg.flags.c = u16::from(a) < (u16::from(n) + u16::from(c));
         self.reg.flags.n = true;
+        self.set_xy(r);
         self.reg.a = r;
     }
 
@@ -3819,6 +4669,7 @@ impl CPU {
         self.reg.flags.h = true;
         self.reg.flags.c = false;
         self.reg.flags.n = false;
+        self.set_xy(r);
         self.reg.a = r;
     }
 
@@ -3831,6 +4682,7 @@ impl CPU {
         self.reg.flags.h = false;
         self.reg.flags.c = false;
         self.reg.flags.n = false;
+        self.set_xy(r);
         self.reg.a = r;
     }
 
@@ -3844,6 +4696,7 @@ impl CPU {
         self.reg.flags.h = false;
         self.reg.flags.c = false;
         self.reg.flags.n = false;
+        self.set_xy(r);
         self.reg.a = r;
     }
 
@@ -3852,6 +4705,8 @@ impl CPU {
         let r = self.reg.a;
         self.sub(n);
         self.reg.a = r;
+        // X and Y are copied from the operand
+        self.set_xy(n);
     }
 
     // Increment
@@ -3862,6 +4717,7 @@ impl CPU {
         self.reg.flags.p = n == 0x7F;
         self.reg.flags.h = (n & 0x0f) + 0x01 > 0x0f;
         self.reg.flags.n = false;
+        self.set_xy(r);
         r
     }
 
@@ -3873,6 +4729,7 @@ impl CPU {
         self.reg.flags.p = n == 0x80;
         self.reg.flags.h = ((n & 0x0f) as i8) < 1;
         self.reg.flags.n = true;
+        self.set_xy(r);
         r
     }
 
@@ -3930,6 +4787,7 @@ impl CPU {
         self.reg.flags.z = self.reg.a == 0x00;
         self.reg.flags.s = bit::get(self.reg.a, 7);
         self.reg.flags.p = self.reg.a.count_ones() & 0x01 == 0x00;
+        self.set_xy(self.reg.a);
     }
 
     // NEG
@@ -3942,15 +4800,19 @@ impl CPU {
         self.reg.flags.s = r & 0x80 == 0x80;
         self.reg.flags.h = 0 < (self.reg.a & 0x0F);
         self.reg.flags.n = true;
+        self.set_xy(r);
         self.reg.a = r;
     }
 
     // 16 bits add
     fn add_16(&mut self, n1: u16, n2: u16) -> u16 {
         let r = n1.wrapping_add(n2);
+        self.memptr = n1.wrapping_add(1);
         self.reg.flags.c = u32::from(n1) + u32::from(n2) > 0xffff;
         self.reg.flags.h = (n1 & 0x0FFF) + (n2 & 0x0FFF) > 0x0FFF;
         self.reg.flags.n = false;
+        // X and Y are copied from the high byte of the result
+        self.set_xy((r >> 8) as u8);
         r
     }
 
@@ -3962,16 +4824,15 @@ impl CPU {
         };
         let h = self.reg.get_hl();
         let r = h.wrapping_add(n).wrapping_add(c);
+        self.memptr = h.wrapping_add(1);
         self.reg.set_hl(r);
         self.reg.flags.s = r & 0x8000 == 0x8000;
         self.reg.flags.z = r == 0x00;
         self.reg.flags.c = u32::from(h) + u32::from(n) + c as u32 > 0xffff;
         self.reg.flags.h = (h & 0x0FFF) + (n & 0x0FFF) + c > 0x0FFF;
         self.reg.flags.n = false;
-        self.reg.flags.p = {
-            let r = (h as i16).overflowing_add((n + c) as i16);
-            r.1
-        }
+        self.reg.flags.p = ((h ^ r) & (n ^ r)) & 0x8000 != 0;
+        self.set_xy((r >> 8) as u8);
     }
 
     // Register pair substraction with carry
@@ -3982,16 +4843,15 @@ impl CPU {
         };
         let h = self.reg.get_hl();
         let r = h.wrapping_sub(n).wrapping_sub(c);
+        self.memptr = h.wrapping_add(1);
         self.reg.set_hl(r);
         self.reg.flags.z = r == 0x00;
         self.reg.flags.s = r & 0x8000 == 0x8000;
         self.reg.flags.h = (h & 0x0fff) < (n & 0x0fff) + c;
-        self.reg.flags.c = h < n + c;
+        self.reg.flags.c = u32::from(h) < u32::from(n) + u32::from(c);
         self.reg.flags.n = true;
-        self.reg.flags.p = {
-            let r = (h as i16).overflowing_sub((n + c) as i16);
-            r.1
-        }
+        self.reg.flags.p = ((h ^ n) & (h ^ r)) & 0x8000 != 0;
+        self.set_xy((r >> 8) as u8);
     }
 
     // Rotate Accumulator left
@@ -4001,6 +4861,7 @@ impl CPU {
         self.reg.flags.c = bit::get(self.reg.a, 7);
         self.reg.flags.h = false;
         self.reg.flags.n = false;
+        self.set_xy(r);
         self.reg.a = r;
     }
 
@@ -4013,6 +4874,7 @@ impl CPU {
         self.reg.flags.h = false;
         self.reg.flags.n = false;
         self.reg.flags.p = r.count_ones() & 0x01 == 0x00;
+        self.set_xy(r);
         r
     }
 
@@ -4026,6 +4888,7 @@ impl CPU {
         };
         self.reg.flags.h = false;
         self.reg.flags.n = false;
+        self.set_xy(r);
         self.reg.a = r;
     }
 
@@ -4042,6 +4905,7 @@ impl CPU {
         self.reg.flags.h = false;
         self.reg.flags.p = r.count_ones() & 0x01 == 0x00;
         self.reg.flags.n = false;
+        self.set_xy(r);
         r
     }
 
@@ -4055,6 +4919,7 @@ impl CPU {
             true => (self.reg.a << 1) | 0x01,
             false => self.reg.a << 1,
         };
+        self.set_xy(r);
         self.reg.a = r;
     }
 
@@ -4071,6 +4936,7 @@ impl CPU {
         self.reg.flags.z = r == 0x00;
         self.reg.flags.s = r & 0x80 == 0x80;
         self.reg.flags.p = r.count_ones() & 0x01 == 0x00;
+        self.set_xy(r);
         r
     }
 
@@ -4084,6 +4950,7 @@ impl CPU {
             true => (self.reg.a >> 1) | 0x80,
             false => self.reg.a >> 1,
         };
+        self.set_xy(r);
         self.reg.a = r;
     }
 
@@ -4100,6 +4967,7 @@ impl CPU {
         self.reg.flags.z = r == 0x00;
         self.reg.flags.s = r & 0x80 == 0x80;
         self.reg.flags.p = r.count_ones() & 0x01 == 0x00;
+        self.set_xy(r);
         r
     }
 
@@ -4112,6 +4980,7 @@ impl CPU {
         self.reg.flags.p = r.count_ones() & 0x01 == 0x00;
         self.reg.flags.n = false;
         self.reg.flags.c = bit::get(n, 7);
+        self.set_xy(r);
         r
     }
 
@@ -4124,6 +4993,7 @@ impl CPU {
         self.reg.flags.p = r.count_ones() & 0x01 == 0x00;
         self.reg.flags.n = false;
         self.reg.flags.c = bit::get(n, 7);
+        self.set_xy(r);
         r
     }
 
@@ -4138,6 +5008,7 @@ impl CPU {
         self.reg.flags.p = r.count_ones() & 0x01 == 0x00;
         self.reg.flags.n = false;
         self.reg.flags.c = bit::get(n, 0);
+        self.set_xy(r);
         r
     }
 
@@ -4152,31 +5023,45 @@ impl CPU {
         self.reg.flags.p = r.count_ones() & 0x01 == 0x00;
         self.reg.flags.n = false;
         self.reg.flags.c = bit::get(n, 0);
+        self.set_xy(r);
         r
     }
 
     // Bit test
-    fn bit(&mut self, bus: &mut Bus, operand: u8) {
+    fn bit(&mut self, bus: &mut M, operand: u8) {
         let bit = ((operand & 0x38) >> 3) as usize;
         let register = operand & 0x07;
         let r = match register {
-            0 => bit::get(self.reg.b, bit),
-            1 => bit::get(self.reg.c, bit),
-            2 => bit::get(self.reg.d, bit),
-            3 => bit::get(self.reg.e, bit),
-            4 => bit::get(self.reg.h, bit),
-            5 => bit::get(self.reg.l, bit),
-            6 => bit::get(bus.read_byte(self.reg.get_hl()), bit),
-            7 => bit::get(self.reg.a, bit),
-            _ => false,
+            0 => self.reg.b,
+            1 => self.reg.c,
+            2 => self.reg.d,
+            3 => self.reg.e,
+            4 => self.reg.h,
+            5 => self.reg.l,
+            6 => bus.read_byte(self.reg.get_hl()),
+            _ => self.reg.a,
+        };
+        // BIT n,(HL) takes X and Y from the high byte of MEMPTR
+        let xy = match register {
+            6 => (self.memptr >> 8) as u8,
+            _ => r,
         };
+        self.bit_flags(bit, r, xy);
+    }
+
+    // Flags of the BIT instructions. xy is the byte X and Y are copied from
+    fn bit_flags(&mut self, bit: usize, n: u8, xy: u8) {
+        let r = bit::get(n, bit);
+        self.reg.flags.s = bit == 7 && r;
         self.reg.flags.z = !r;
         self.reg.flags.h = true;
+        self.reg.flags.p = !r;
         self.reg.flags.n = false;
+        self.set_xy(xy);
     }
 
     // Bit set
-    fn set(&mut self, bus: &mut Bus, operand: u8) {
+    fn set(&mut self, bus: &mut M, operand: u8) {
         let bit = ((operand & 0x38) >> 3) as usize;
         let register = operand & 0x07;
         match register {
@@ -4186,17 +5071,17 @@ impl CPU {
             3 => self.reg.e = bit::set(self.reg.e, bit),
             4 => self.reg.h = bit::set(self.reg.h, bit),
             5 => self.reg.l = bit::set(self.reg.l, bit),
-            6 => bus.write_byte(
-                self.reg.get_hl(),
-                bit::set(bus.read_byte(self.reg.get_hl()), bit),
-            ),
+            6 => {
+                let data = bus.read_byte(self.reg.get_hl());
+                bus.write_byte(self.reg.get_hl(), bit::set(data, bit))
+            }
             7 => self.reg.a = bit::set(self.reg.a, bit),
             _ => {}
         };
     }
 
     // Bit reset
-    fn reset(&mut self, bus: &mut Bus, operand: u8) {
+    fn res(&mut self, bus: &mut M, operand: u8) {
         let bit = ((operand & 0x38) >> 3) as usize;
         let register = operand & 0x07;
         match register {
@@ -4206,29 +5091,119 @@ impl CPU {
             3 => self.reg.e = bit::reset(self.reg.e, bit),
             4 => self.reg.h = bit::reset(self.reg.h, bit),
             5 => self.reg.l = bit::reset(self.reg.l, bit),
-            6 => bus.write_byte(
-                self.reg.get_hl(),
-                bit::reset(bus.read_byte(self.reg.get_hl()), bit),
-            ),
+            6 => {
+                let data = bus.read_byte(self.reg.get_hl());
+                bus.write_byte(self.reg.get_hl(), bit::reset(data, bit))
+            }
             7 => self.reg.a = bit::reset(self.reg.a, bit),
             _ => {}
         };
     }
 
+    // Sets the undocumented X (bit 3) and Y (bit 5) flags from a byte
+    fn set_xy(&mut self, n: u8) {
+        self.reg.flags.b3 = n & 0x08 != 0;
+        self.reg.flags.b5 = n & 0x20 != 0;
+        self.flags_written = true;
+    }
+
+    // Reads an opcode byte of the current instruction during an opcode fetch (M1 cycle)
+    fn fetch_opcode(&self, bus: &mut M, offset: u16) -> u8 {
+        match &self.int_data {
+            Some(_) => self.fetch_byte(bus, offset),
+            None => bus.fetch_opcode(self.reg.pc.wrapping_add(offset)),
+        }
+    }
+
+    // Reads a byte of the current instruction, from memory or from the data bus during an interrupt acknowledge
+    fn fetch_byte(&self, bus: &mut M, offset: u16) -> u8 {
+        match &self.int_data {
+            Some(data) => *data.get(offset as usize).unwrap_or(&0xFF),
+            None => bus.read_byte(self.reg.pc.wrapping_add(offset)),
+        }
+    }
+
+    // Reads a word of the current instruction
+    fn fetch_word(&self, bus: &mut M, offset: u16) -> u16 {
+        u16::from(self.fetch_byte(bus, offset)) | (u16::from(self.fetch_byte(bus, offset + 1)) << 8)
+    }
+
+    // Increments the 7 lower bits of the memory refresh register
+    fn inc_r(&mut self) {
+        self.reg.r = (self.reg.r & 0x80) | (self.reg.r.wrapping_add(1) & 0x7F);
+    }
+
     // call stack push
-    fn call_stack_push(&mut self, bus: &mut Bus) {
+    fn call_stack_push(&mut self, bus: &mut M) {
         self.reg.sp = self.reg.sp.wrapping_sub(2);
         bus.write_word(self.reg.sp, self.reg.pc.wrapping_add(3));
     }
 
     // call stack pop
-    fn call_stack_pop(&mut self, bus: &mut Bus) {
+    fn call_stack_pop(&mut self, bus: &mut M) {
         self.reg.pc = bus.read_word(self.reg.sp);
         self.reg.sp = self.reg.sp.wrapping_add(2);
+        self.memptr = self.reg.pc;
+    }
+
+    // Describes the unknown instruction at (pc), from its already fetched opcode bytes
+    fn unknown_opcode(&self, opcode: u8, next: Option<u8>) -> UnknownOpcode {
+        let prefix = match opcode {
+            0xDD => Prefix::Dd,
+            0xFD => Prefix::Fd,
+            0xED => Prefix::Ed,
+            0xCB => Prefix::Cb,
+            _ => Prefix::None,
+        };
+        UnknownOpcode {
+            address: self.reg.pc,
+            prefix,
+            bytes: [Some(opcode), next].into_iter().flatten().collect(),
+        }
+    }
+
+    // Executes an unknown instruction according to the policy
+    fn unknown_opcode_policy(
+        &mut self,
+        bus: &mut M,
+        unknown: UnknownOpcode,
+    ) -> Result<u32, ExecError> {
+        // The policy is taken out while a callback runs, so that it can access the CPU
+        let mut policy = std::mem::replace(&mut self.unknown_policy, UnknownOpcodePolicy::Trap);
+        let result = match &mut policy {
+            UnknownOpcodePolicy::Trap => Err(ExecError::UnknownOpcode(unknown)),
+            UnknownOpcodePolicy::Nop => {
+                self.reg.pc = unknown.address.wrapping_add(unknown.bytes.len() as u16);
+                Ok(unknown.prefix.nop_cycles())
+            }
+            UnknownOpcodePolicy::Callback(handler) => handler(self, bus, &unknown),
+        };
+        self.unknown_policy = policy;
+        result
+    }
+
+    // Index of the daisy chain device requesting an interrupt, if not blocked by a higher priority device in service
+    fn daisy_chain_request(&self) -> Option<usize> {
+        for (i, device) in self.daisy_chain.iter().enumerate() {
+            if device.int_pending() {
+                return Some(i);
+            }
+            if device.in_service() {
+                return None;
+            }
+        }
+        None
+    }
+
+    // RETI ends the service of the highest priority device in service
+    fn daisy_chain_reti(&mut self) {
+        if let Some(device) = self.daisy_chain.iter_mut().find(|d| d.in_service()) {
+            device.reti();
+        }
     }
 
     // interrupt stack push
-    fn interrupt_stack_push(&mut self, bus: &mut Bus) {
+    fn interrupt_stack_push(&mut self, bus: &mut M) {
         self.reg.sp = self.reg.sp.wrapping_sub(2);
         bus.write_word(self.reg.sp, self.reg.pc);
     }
@@ -4248,6 +5223,141 @@ fn check_sub_overflow(n1: u8, n2: u8) -> bool {
     r.1
 }
 
+// Returns true if a DD / FD prefix applies to the opcode following it (HL, H, L or (HL) replaced by IX / IY)
+pub(crate) fn is_indexed_opcode(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        0x09 | 0x19
+            | 0x21..=0x26
+            | 0x29..=0x2E
+            | 0x34..=0x36
+            | 0x39
+            | 0x44..=0x46
+            | 0x4C..=0x4E
+            | 0x54..=0x56
+            | 0x5C..=0x5E
+            | 0x60..=0x75
+            | 0x77
+            | 0x7C..=0x7E
+            | 0x84..=0x86
+            | 0x8C..=0x8E
+            | 0x94..=0x96
+            | 0x9C..=0x9E
+            | 0xA4..=0xA6
+            | 0xAC..=0xAE
+            | 0xB4..=0xB6
+            | 0xBC..=0xBE
+            | 0xCB
+            | 0xE1
+            | 0xE3
+            | 0xE5
+            | 0xE9
+            | 0xF9
+    )
+}
+
+/// Contents of the registers undefined at power-on (BC, DE, HL, IX, IY and the alternate registers), for `CPU::power_on_with`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UndefinedRegisters {
+    /// All the registers are set to the same value
+    Fill(u16),
+    /// The registers are set to pseudo-random values, the same for a given seed
+    Random(u64),
+}
+
+// SplitMix64 pseudo-random number generator
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Prefix of an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prefix {
+    None,
+    Cb,
+    Ed,
+    Dd,
+    Fd,
+    DdCb,
+    FdCb,
+}
+
+impl Prefix {
+    // T-states of an unknown instruction executed as a NOP : invalid ED instructions take 8 T-states on the Z80
+    fn nop_cycles(self) -> u32 {
+        match self {
+            Prefix::None => 4,
+            Prefix::DdCb | Prefix::FdCb => 23,
+            _ => 8,
+        }
+    }
+}
+
+/// An instruction unknown to the emulator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownOpcode {
+    /// Address of the instruction
+    pub address: u16,
+    pub prefix: Prefix,
+    /// Bytes of the instruction, prefix included (the displacement too for DDCB / FDCB instructions)
+    pub bytes: Vec<u8>,
+}
+
+/// Error returned by `CPU::try_execute`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecError {
+    /// An unknown instruction was trapped
+    UnknownOpcode(UnknownOpcode),
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExecError::UnknownOpcode(unknown) => {
+                write!(f, "unknown opcode")?;
+                for byte in &unknown.bytes {
+                    write!(f, " {:02X}", byte)?;
+                }
+                write!(f, " at {:#06X}", unknown.address)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExecError {}
+
+/// Function called for an unknown instruction, PC pointing to it. It returns the clock cycles consumed,
+/// after emulating the instruction and moving PC past it, or an error to trap the instruction.
+pub type UnknownOpcodeHandler<M = Bus> =
+    Box<dyn FnMut(&mut CPU<M>, &mut M, &UnknownOpcode) -> Result<u32, ExecError> + Send>;
+
+/// How unknown instructions are executed, see `CPU::set_unknown_opcode_policy`
+pub enum UnknownOpcodePolicy<M = Bus> {
+    /// The instruction is not executed : `CPU::try_execute` returns an error, `CPU::execute` returns 0xFF
+    Trap,
+    /// The instruction is executed as a NOP of the same length, like invalid ED instructions on the Z80
+    Nop,
+    /// The instruction is handed to a function
+    Callback(UnknownOpcodeHandler<M>),
+}
+
+/// Reason why `CPU::run_until` returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The breakpoint predicate returned true
+    Breakpoint,
+    /// A HALT instruction was executed : the CPU waits for an interrupt
+    Halt,
+    /// The cycle budget has been consumed
+    CycleLimit,
+    /// An unknown opcode was trapped (see `CPU::set_unknown_opcode_policy`)
+    UnknownOpcode,
+}
+
 #[doc(hidden)]
 // Converts a signed byte to its absolute value
 pub fn signed_to_abs(n: u8) -> u8 {
@@ -4257,7 +5367,7 @@ pub fn signed_to_abs(n: u8) -> u8 {
 pub struct Debug {
     pub unknw_instr: bool,
     pub opcode: bool,
-    // IO MPMC Messages
+    // Data written by OUT instruction
     pub io: bool,
     // Data read by IN instruction
     pub instr_in: bool,
@@ -0,0 +1,61 @@
+/// A Z80 family peripheral (PIO, CTC, SIO...) plugged into the interrupt daisy chain of the CPU.
+///
+/// Devices are prioritized by their position in the chain, the first one added having the highest priority.
+/// A device in service (its interrupt acknowledged, RETI not executed yet) blocks the requests of the devices behind it,
+/// like its IEO output going low. The daisy chain has priority over the sources of `CPU::int_assert`.
+/// ```rust
+/// use zilog_z80::{bus::Bus, cpu::CPU, daisy_chain::DaisyChainDevice};
+///
+/// struct Timer {
+///     pending: bool,
+///     in_service: bool,
+/// }
+///
+/// impl DaisyChainDevice for Timer {
+///     fn int_pending(&self) -> bool {
+///         self.pending
+///     }
+///
+///     fn in_service(&self) -> bool {
+///         self.in_service
+///     }
+///
+///     fn ack(&mut self) -> u8 {
+///         self.pending = false;
+///         self.in_service = true;
+///         0x10
+///     }
+///
+///     fn reti(&mut self) {
+///         self.in_service = false;
+///     }
+/// }
+///
+/// let mut b = Bus::new(0xFFFF);
+/// let mut c = CPU::new();
+/// b.write_byte(0x0000, 0xED);         // IM 2
+/// b.write_byte(0x0001, 0x5E);
+/// b.write_byte(0x0002, 0xFB);         // EI
+/// b.write_byte(0x0003, 0x00);         // NOP
+/// b.write_word(0x0010, 0x1000);       // Interrupt vector
+/// c.reg.sp = 0xFF00;
+/// c.daisy_chain_add(Box::new(Timer { pending: true, in_service: false }));
+/// for _ in 0..4 {
+///     c.execute(&mut b);
+/// }
+/// assert_eq!(c.reg.pc, 0x1000);
+/// ```
+pub trait DaisyChainDevice: Send {
+    /// Returns true if the device requests an interrupt.
+    fn int_pending(&self) -> bool;
+
+    /// Returns true while the interrupt of the device is being serviced.
+    fn in_service(&self) -> bool;
+
+    /// Called when the CPU acknowledges the interrupt of the device, which should clear its request and enter the in-service state.
+    /// Returns the byte put on the data bus : the vector low byte in interrupt mode 2.
+    fn ack(&mut self) -> u8;
+
+    /// Called when the CPU executes RETI and the device is the highest priority one in service. It should leave the in-service state.
+    fn reti(&mut self);
+}
@@ -1,6 +1,6 @@
 use crate::bit;
 use crate::bus::Bus;
-use crate::cpu::signed_to_abs;
+use crate::cpu::{is_indexed_opcode, signed_to_abs};
 
 pub const DASM_CB: [&str; 256] = [
     "RLC B",
@@ -520,6 +520,524 @@ pub const DASM_DD: [&str; 256] = [
     "?",            // DDFF
 ];
 
+pub const DASM_DDCB: [&str; 256] = [
+    "RLC (IX+d),B",   // DDCB d 00
+    "RLC (IX+d),C",   // DDCB d 01
+    "RLC (IX+d),D",   // DDCB d 02
+    "RLC (IX+d),E",   // DDCB d 03
+    "RLC (IX+d),H",   // DDCB d 04
+    "RLC (IX+d),L",   // DDCB d 05
+    "RLC (IX+d)",     // DDCB d 06
+    "RLC (IX+d),A",   // DDCB d 07
+    "RRC (IX+d),B",   // DDCB d 08
+    "RRC (IX+d),C",   // DDCB d 09
+    "RRC (IX+d),D",   // DDCB d 0A
+    "RRC (IX+d),E",   // DDCB d 0B
+    "RRC (IX+d),H",   // DDCB d 0C
+    "RRC (IX+d),L",   // DDCB d 0D
+    "RRC (IX+d)",     // DDCB d 0E
+    "RRC (IX+d),A",   // DDCB d 0F
+    "RL (IX+d),B",    // DDCB d 10
+    "RL (IX+d),C",    // DDCB d 11
+    "RL (IX+d),D",    // DDCB d 12
+    "RL (IX+d),E",    // DDCB d 13
+    "RL (IX+d),H",    // DDCB d 14
+    "RL (IX+d),L",    // DDCB d 15
+    "RL (IX+d)",      // DDCB d 16
+    "RL (IX+d),A",    // DDCB d 17
+    "RR (IX+d),B",    // DDCB d 18
+    "RR (IX+d),C",    // DDCB d 19
+    "RR (IX+d),D",    // DDCB d 1A
+    "RR (IX+d),E",    // DDCB d 1B
+    "RR (IX+d),H",    // DDCB d 1C
+    "RR (IX+d),L",    // DDCB d 1D
+    "RR (IX+d)",      // DDCB d 1E
+    "RR (IX+d),A",    // DDCB d 1F
+    "SLA (IX+d),B",   // DDCB d 20
+    "SLA (IX+d),C",   // DDCB d 21
+    "SLA (IX+d),D",   // DDCB d 22
+    "SLA (IX+d),E",   // DDCB d 23
+    "SLA (IX+d),H",   // DDCB d 24
+    "SLA (IX+d),L",   // DDCB d 25
+    "SLA (IX+d)",     // DDCB d 26
+    "SLA (IX+d),A",   // DDCB d 27
+    "SRA (IX+d),B",   // DDCB d 28
+    "SRA (IX+d),C",   // DDCB d 29
+    "SRA (IX+d),D",   // DDCB d 2A
+    "SRA (IX+d),E",   // DDCB d 2B
+    "SRA (IX+d),H",   // DDCB d 2C
+    "SRA (IX+d),L",   // DDCB d 2D
+    "SRA (IX+d)",     // DDCB d 2E
+    "SRA (IX+d),A",   // DDCB d 2F
+    "SLL (IX+d),B",   // DDCB d 30
+    "SLL (IX+d),C",   // DDCB d 31
+    "SLL (IX+d),D",   // DDCB d 32
+    "SLL (IX+d),E",   // DDCB d 33
+    "SLL (IX+d),H",   // DDCB d 34
+    "SLL (IX+d),L",   // DDCB d 35
+    "SLL (IX+d)",     // DDCB d 36
+    "SLL (IX+d),A",   // DDCB d 37
+    "SRL (IX+d),B",   // DDCB d 38
+    "SRL (IX+d),C",   // DDCB d 39
+    "SRL (IX+d),D",   // DDCB d 3A
+    "SRL (IX+d),E",   // DDCB d 3B
+    "SRL (IX+d),H",   // DDCB d 3C
+    "SRL (IX+d),L",   // DDCB d 3D
+    "SRL (IX+d)",     // DDCB d 3E
+    "SRL (IX+d),A",   // DDCB d 3F
+    "BIT 0,(IX+d)",   // DDCB d 40
+    "BIT 0,(IX+d)",   // DDCB d 41
+    "BIT 0,(IX+d)",   // DDCB d 42
+    "BIT 0,(IX+d)",   // DDCB d 43
+    "BIT 0,(IX+d)",   // DDCB d 44
+    "BIT 0,(IX+d)",   // DDCB d 45
+    "BIT 0,(IX+d)",   // DDCB d 46
+    "BIT 0,(IX+d)",   // DDCB d 47
+    "BIT 1,(IX+d)",   // DDCB d 48
+    "BIT 1,(IX+d)",   // DDCB d 49
+    "BIT 1,(IX+d)",   // DDCB d 4A
+    "BIT 1,(IX+d)",   // DDCB d 4B
+    "BIT 1,(IX+d)",   // DDCB d 4C
+    "BIT 1,(IX+d)",   // DDCB d 4D
+    "BIT 1,(IX+d)",   // DDCB d 4E
+    "BIT 1,(IX+d)",   // DDCB d 4F
+    "BIT 2,(IX+d)",   // DDCB d 50
+    "BIT 2,(IX+d)",   // DDCB d 51
+    "BIT 2,(IX+d)",   // DDCB d 52
+    "BIT 2,(IX+d)",   // DDCB d 53
+    "BIT 2,(IX+d)",   // DDCB d 54
+    "BIT 2,(IX+d)",   // DDCB d 55
+    "BIT 2,(IX+d)",   // DDCB d 56
+    "BIT 2,(IX+d)",   // DDCB d 57
+    "BIT 3,(IX+d)",   // DDCB d 58
+    "BIT 3,(IX+d)",   // DDCB d 59
+    "BIT 3,(IX+d)",   // DDCB d 5A
+    "BIT 3,(IX+d)",   // DDCB d 5B
+    "BIT 3,(IX+d)",   // DDCB d 5C
+    "BIT 3,(IX+d)",   // DDCB d 5D
+    "BIT 3,(IX+d)",   // DDCB d 5E
+    "BIT 3,(IX+d)",   // DDCB d 5F
+    "BIT 4,(IX+d)",   // DDCB d 60
+    "BIT 4,(IX+d)",   // DDCB d 61
+    "BIT 4,(IX+d)",   // DDCB d 62
+    "BIT 4,(IX+d)",   // DDCB d 63
+    "BIT 4,(IX+d)",   // DDCB d 64
+    "BIT 4,(IX+d)",   // DDCB d 65
+    "BIT 4,(IX+d)",   // DDCB d 66
+    "BIT 4,(IX+d)",   // DDCB d 67
+    "BIT 5,(IX+d)",   // DDCB d 68
+    "BIT 5,(IX+d)",   // DDCB d 69
+    "BIT 5,(IX+d)",   // DDCB d 6A
+    "BIT 5,(IX+d)",   // DDCB d 6B
+    "BIT 5,(IX+d)",   // DDCB d 6C
+    "BIT 5,(IX+d)",   // DDCB d 6D
+    "BIT 5,(IX+d)",   // DDCB d 6E
+    "BIT 5,(IX+d)",   // DDCB d 6F
+    "BIT 6,(IX+d)",   // DDCB d 70
+    "BIT 6,(IX+d)",   // DDCB d 71
+    "BIT 6,(IX+d)",   // DDCB d 72
+    "BIT 6,(IX+d)",   // DDCB d 73
+    "BIT 6,(IX+d)",   // DDCB d 74
+    "BIT 6,(IX+d)",   // DDCB d 75
+    "BIT 6,(IX+d)",   // DDCB d 76
+    "BIT 6,(IX+d)",   // DDCB d 77
+    "BIT 7,(IX+d)",   // DDCB d 78
+    "BIT 7,(IX+d)",   // DDCB d 79
+    "BIT 7,(IX+d)",   // DDCB d 7A
+    "BIT 7,(IX+d)",   // DDCB d 7B
+    "BIT 7,(IX+d)",   // DDCB d 7C
+    "BIT 7,(IX+d)",   // DDCB d 7D
+    "BIT 7,(IX+d)",   // DDCB d 7E
+    "BIT 7,(IX+d)",   // DDCB d 7F
+    "RES 0,(IX+d),B", // DDCB d 80
+    "RES 0,(IX+d),C", // DDCB d 81
+    "RES 0,(IX+d),D", // DDCB d 82
+    "RES 0,(IX+d),E", // DDCB d 83
+    "RES 0,(IX+d),H", // DDCB d 84
+    "RES 0,(IX+d),L", // DDCB d 85
+    "RES 0,(IX+d)",   // DDCB d 86
+    "RES 0,(IX+d),A", // DDCB d 87
+    "RES 1,(IX+d),B", // DDCB d 88
+    "RES 1,(IX+d),C", // DDCB d 89
+    "RES 1,(IX+d),D", // DDCB d 8A
+    "RES 1,(IX+d),E", // DDCB d 8B
+    "RES 1,(IX+d),H", // DDCB d 8C
+    "RES 1,(IX+d),L", // DDCB d 8D
+    "RES 1,(IX+d)",   // DDCB d 8E
+    "RES 1,(IX+d),A", // DDCB d 8F
+    "RES 2,(IX+d),B", // DDCB d 90
+    "RES 2,(IX+d),C", // DDCB d 91
+    "RES 2,(IX+d),D", // DDCB d 92
+    "RES 2,(IX+d),E", // DDCB d 93
+    "RES 2,(IX+d),H", // DDCB d 94
+    "RES 2,(IX+d),L", // DDCB d 95
+    "RES 2,(IX+d)",   // DDCB d 96
+    "RES 2,(IX+d),A", // DDCB d 97
+    "RES 3,(IX+d),B", // DDCB d 98
+    "RES 3,(IX+d),C", // DDCB d 99
+    "RES 3,(IX+d),D", // DDCB d 9A
+    "RES 3,(IX+d),E", // DDCB d 9B
+    "RES 3,(IX+d),H", // DDCB d 9C
+    "RES 3,(IX+d),L", // DDCB d 9D
+    "RES 3,(IX+d)",   // DDCB d 9E
+    "RES 3,(IX+d),A", // DDCB d 9F
+    "RES 4,(IX+d),B", // DDCB d A0
+    "RES 4,(IX+d),C", // DDCB d A1
+    "RES 4,(IX+d),D", // DDCB d A2
+    "RES 4,(IX+d),E", // DDCB d A3
+    "RES 4,(IX+d),H", // DDCB d A4
+    "RES 4,(IX+d),L", // DDCB d A5
+    "RES 4,(IX+d)",   // DDCB d A6
+    "RES 4,(IX+d),A", // DDCB d A7
+    "RES 5,(IX+d),B", // DDCB d A8
+    "RES 5,(IX+d),C", // DDCB d A9
+    "RES 5,(IX+d),D", // DDCB d AA
+    "RES 5,(IX+d),E", // DDCB d AB
+    "RES 5,(IX+d),H", // DDCB d AC
+    "RES 5,(IX+d),L", // DDCB d AD
+    "RES 5,(IX+d)",   // DDCB d AE
+    "RES 5,(IX+d),A", // DDCB d AF
+    "RES 6,(IX+d),B", // DDCB d B0
+    "RES 6,(IX+d),C", // DDCB d B1
+    "RES 6,(IX+d),D", // DDCB d B2
+    "RES 6,(IX+d),E", // DDCB d B3
+    "RES 6,(IX+d),H", // DDCB d B4
+    "RES 6,(IX+d),L", // DDCB d B5
+    "RES 6,(IX+d)",   // DDCB d B6
+    "RES 6,(IX+d),A", // DDCB d B7
+    "RES 7,(IX+d),B", // DDCB d B8
+    "RES 7,(IX+d),C", // DDCB d B9
+    "RES 7,(IX+d),D", // DDCB d BA
+    "RES 7,(IX+d),E", // DDCB d BB
+    "RES 7,(IX+d),H", // DDCB d BC
+    "RES 7,(IX+d),L", // DDCB d BD
+    "RES 7,(IX+d)",   // DDCB d BE
+    "RES 7,(IX+d),A", // DDCB d BF
+    "SET 0,(IX+d),B", // DDCB d C0
+    "SET 0,(IX+d),C", // DDCB d C1
+    "SET 0,(IX+d),D", // DDCB d C2
+    "SET 0,(IX+d),E", // DDCB d C3
+    "SET 0,(IX+d),H", // DDCB d C4
+    "SET 0,(IX+d),L", // DDCB d C5
+    "SET 0,(IX+d)",   // DDCB d C6
+    "SET 0,(IX+d),A", // DDCB d C7
+    "SET 1,(IX+d),B", // DDCB d C8
+    "SET 1,(IX+d),C", // DDCB d C9
+    "SET 1,(IX+d),D", // DDCB d CA
+    "SET 1,(IX+d),E", // DDCB d CB
+    "SET 1,(IX+d),H", // DDCB d CC
+    "SET 1,(IX+d),L", // DDCB d CD
+    "SET 1,(IX+d)",   // DDCB d CE
+    "SET 1,(IX+d),A", // DDCB d CF
+    "SET 2,(IX+d),B", // DDCB d D0
+    "SET 2,(IX+d),C", // DDCB d D1
+    "SET 2,(IX+d),D", // DDCB d D2
+    "SET 2,(IX+d),E", // DDCB d D3
+    "SET 2,(IX+d),H", // DDCB d D4
+    "SET 2,(IX+d),L", // DDCB d D5
+    "SET 2,(IX+d)",   // DDCB d D6
+    "SET 2,(IX+d),A", // DDCB d D7
+    "SET 3,(IX+d),B", // DDCB d D8
+    "SET 3,(IX+d),C", // DDCB d D9
+    "SET 3,(IX+d),D", // DDCB d DA
+    "SET 3,(IX+d),E", // DDCB d DB
+    "SET 3,(IX+d),H", // DDCB d DC
+    "SET 3,(IX+d),L", // DDCB d DD
+    "SET 3,(IX+d)",   // DDCB d DE
+    "SET 3,(IX+d),A", // DDCB d DF
+    "SET 4,(IX+d),B", // DDCB d E0
+    "SET 4,(IX+d),C", // DDCB d E1
+    "SET 4,(IX+d),D", // DDCB d E2
+    "SET 4,(IX+d),E", // DDCB d E3
+    "SET 4,(IX+d),H", // DDCB d E4
+    "SET 4,(IX+d),L", // DDCB d E5
+    "SET 4,(IX+d)",   // DDCB d E6
+    "SET 4,(IX+d),A", // DDCB d E7
+    "SET 5,(IX+d),B", // DDCB d E8
+    "SET 5,(IX+d),C", // DDCB d E9
+    "SET 5,(IX+d),D", // DDCB d EA
+    "SET 5,(IX+d),E", // DDCB d EB
+    "SET 5,(IX+d),H", // DDCB d EC
+    "SET 5,(IX+d),L", // DDCB d ED
+    "SET 5,(IX+d)",   // DDCB d EE
+    "SET 5,(IX+d),A", // DDCB d EF
+    "SET 6,(IX+d),B", // DDCB d F0
+    "SET 6,(IX+d),C", // DDCB d F1
+    "SET 6,(IX+d),D", // DDCB d F2
+    "SET 6,(IX+d),E", // DDCB d F3
+    "SET 6,(IX+d),H", // DDCB d F4
+    "SET 6,(IX+d),L", // DDCB d F5
+    "SET 6,(IX+d)",   // DDCB d F6
+    "SET 6,(IX+d),A", // DDCB d F7
+    "SET 7,(IX+d),B", // DDCB d F8
+    "SET 7,(IX+d),C", // DDCB d F9
+    "SET 7,(IX+d),D", // DDCB d FA
+    "SET 7,(IX+d),E", // DDCB d FB
+    "SET 7,(IX+d),H", // DDCB d FC
+    "SET 7,(IX+d),L", // DDCB d FD
+    "SET 7,(IX+d)",   // DDCB d FE
+    "SET 7,(IX+d),A", // DDCB d FF
+];
+
+pub const DASM_ED: [&str; 256] = [
+    "?",          // ED00
+    "?",          // ED01
+    "?",          // ED02
+    "?",          // ED03
+    "?",          // ED04
+    "?",          // ED05
+    "?",          // ED06
+    "?",          // ED07
+    "?",          // ED08
+    "?",          // ED09
+    "?",          // ED0A
+    "?",          // ED0B
+    "?",          // ED0C
+    "?",          // ED0D
+    "?",          // ED0E
+    "?",          // ED0F
+    "?",          // ED10
+    "?",          // ED11
+    "?",          // ED12
+    "?",          // ED13
+    "?",          // ED14
+    "?",          // ED15
+    "?",          // ED16
+    "?",          // ED17
+    "?",          // ED18
+    "?",          // ED19
+    "?",          // ED1A
+    "?",          // ED1B
+    "?",          // ED1C
+    "?",          // ED1D
+    "?",          // ED1E
+    "?",          // ED1F
+    "?",          // ED20
+    "?",          // ED21
+    "?",          // ED22
+    "?",          // ED23
+    "?",          // ED24
+    "?",          // ED25
+    "?",          // ED26
+    "?",          // ED27
+    "?",          // ED28
+    "?",          // ED29
+    "?",          // ED2A
+    "?",          // ED2B
+    "?",          // ED2C
+    "?",          // ED2D
+    "?",          // ED2E
+    "?",          // ED2F
+    "?",          // ED30
+    "?",          // ED31
+    "?",          // ED32
+    "?",          // ED33
+    "?",          // ED34
+    "?",          // ED35
+    "?",          // ED36
+    "?",          // ED37
+    "?",          // ED38
+    "?",          // ED39
+    "?",          // ED3A
+    "?",          // ED3B
+    "?",          // ED3C
+    "?",          // ED3D
+    "?",          // ED3E
+    "?",          // ED3F
+    "IN B,(C)",   // ED40
+    "OUT (C),B",  // ED41
+    "SBC HL,BC",  // ED42
+    "LD (nn),BC", // ED43
+    "NEG",        // ED44
+    "RETN",       // ED45
+    "IM 0",       // ED46
+    "LD I,A",     // ED47
+    "IN C,(C)",   // ED48
+    "OUT (C),C",  // ED49
+    "ADC HL,BC",  // ED4A
+    "LD BC,(nn)", // ED4B
+    "?",          // ED4C
+    "RETI",       // ED4D
+    "?",          // ED4E
+    "LD R,A",     // ED4F
+    "IN D,(C)",   // ED50
+    "OUT (C),D",  // ED51
+    "SBC HL,DE",  // ED52
+    "LD (nn),DE", // ED53
+    "?",          // ED54
+    "?",          // ED55
+    "IM 1",       // ED56
+    "LD A,I",     // ED57
+    "IN E,(C)",   // ED58
+    "OUT (C),E",  // ED59
+    "ADC HL,DE",  // ED5A
+    "LD DE,(nn)", // ED5B
+    "?",          // ED5C
+    "?",          // ED5D
+    "IM 2",       // ED5E
+    "LD A,R",     // ED5F
+    "IN H,(C)",   // ED60
+    "OUT (C),H",  // ED61
+    "SBC HL,HL",  // ED62
+    "LD (nn),HL", // ED63
+    "?",          // ED64
+    "?",          // ED65
+    "?",          // ED66
+    "RRD",        // ED67
+    "IN L,(C)",   // ED68
+    "OUT (C),L",  // ED69
+    "ADC HL,HL",  // ED6A
+    "LD HL,(nn)", // ED6B
+    "?",          // ED6C
+    "?",          // ED6D
+    "?",          // ED6E
+    "RLD",        // ED6F
+    "IN (C)",     // ED70
+    "OUT (C),0",  // ED71
+    "SBC HL,SP",  // ED72
+    "LD (nn),SP", // ED73
+    "?",          // ED74
+    "?",          // ED75
+    "?",          // ED76
+    "?",          // ED77
+    "IN A,(C)",   // ED78
+    "OUT (C),A",  // ED79
+    "ADC HL,SP",  // ED7A
+    "LD SP,(nn)", // ED7B
+    "?",          // ED7C
+    "?",          // ED7D
+    "?",          // ED7E
+    "?",          // ED7F
+    "?",          // ED80
+    "?",          // ED81
+    "?",          // ED82
+    "?",          // ED83
+    "?",          // ED84
+    "?",          // ED85
+    "?",          // ED86
+    "?",          // ED87
+    "?",          // ED88
+    "?",          // ED89
+    "?",          // ED8A
+    "?",          // ED8B
+    "?",          // ED8C
+    "?",          // ED8D
+    "?",          // ED8E
+    "?",          // ED8F
+    "?",          // ED90
+    "?",          // ED91
+    "?",          // ED92
+    "?",          // ED93
+    "?",          // ED94
+    "?",          // ED95
+    "?",          // ED96
+    "?",          // ED97
+    "?",          // ED98
+    "?",          // ED99
+    "?",          // ED9A
+    "?",          // ED9B
+    "?",          // ED9C
+    "?",          // ED9D
+    "?",          // ED9E
+    "?",          // ED9F
+    "LDI",        // EDA0
+    "CPI",        // EDA1
+    "INI",        // EDA2
+    "OUTI",       // EDA3
+    "?",          // EDA4
+    "?",          // EDA5
+    "?",          // EDA6
+    "?",          // EDA7
+    "LDD",        // EDA8
+    "CPD",        // EDA9
+    "IND",        // EDAA
+    "OUTD",       // EDAB
+    "?",          // EDAC
+    "?",          // EDAD
+    "?",          // EDAE
+    "?",          // EDAF
+    "LDIR",       // EDB0
+    "CPIR",       // EDB1
+    "INIR",       // EDB2
+    "OTIR",       // EDB3
+    "?",          // EDB4
+    "?",          // EDB5
+    "?",          // EDB6
+    "?",          // EDB7
+    "LDDR",       // EDB8
+    "CPDR",       // EDB9
+    "INDR",       // EDBA
+    "OTDR",       // EDBB
+    "?",          // EDBC
+    "?",          // EDBD
+    "?",          // EDBE
+    "?",          // EDBF
+    "?",          // EDC0
+    "?",          // EDC1
+    "?",          // EDC2
+    "?",          // EDC3
+    "?",          // EDC4
+    "?",          // EDC5
+    "?",          // EDC6
+    "?",          // EDC7
+    "?",          // EDC8
+    "?",          // EDC9
+    "?",          // EDCA
+    "?",          // EDCB
+    "?",          // EDCC
+    "?",          // EDCD
+    "?",          // EDCE
+    "?",          // EDCF
+    "?",          // EDD0
+    "?",          // EDD1
+    "?",          // EDD2
+    "?",          // EDD3
+    "?",          // EDD4
+    "?",          // EDD5
+    "?",          // EDD6
+    "?",          // EDD7
+    "?",          // EDD8
+    "?",          // EDD9
+    "?",          // EDDA
+    "?",          // EDDB
+    "?",          // EDDC
+    "?",          // EDDD
+    "?",          // EDDE
+    "?",          // EDDF
+    "?",          // EDE0
+    "?",          // EDE1
+    "?",          // EDE2
+    "?",          // EDE3
+    "?",          // EDE4
+    "?",          // EDE5
+    "?",          // EDE6
+    "?",          // EDE7
+    "?",          // EDE8
+    "?",          // EDE9
+    "?",          // EDEA
+    "?",          // EDEB
+    "?",          // EDEC
+    "?",          // EDED
+    "?",          // EDEE
+    "?",          // EDEF
+    "?",          // EDF0
+    "?",          // EDF1
+    "?",          // EDF2
+    "?",          // EDF3
+    "?",          // EDF4
+    "?",          // EDF5
+    "?",          // EDF6
+    "?",          // EDF7
+    "?",          // EDF8
+    "?",          // EDF9
+    "?",          // EDFA
+    "?",          // EDFB
+    "?",          // EDFC
+    "?",          // EDFD
+    "?",          // EDFE
+    "?",          // EDFF
+];
+
 pub const DASM_FD: [&str; 256] = [
     "?",            // FD00
     "?",            // FD01
@@ -779,891 +1297,1196 @@ pub const DASM_FD: [&str; 256] = [
     "?",            // FDFF
 ];
 
+pub const DASM_FDCB: [&str; 256] = [
+    "RLC (IY+d),B",   // FDCB d 00
+    "RLC (IY+d),C",   // FDCB d 01
+    "RLC (IY+d),D",   // FDCB d 02
+    "RLC (IY+d),E",   // FDCB d 03
+    "RLC (IY+d),H",   // FDCB d 04
+    "RLC (IY+d),L",   // FDCB d 05
+    "RLC (IY+d)",     // FDCB d 06
+    "RLC (IY+d),A",   // FDCB d 07
+    "RRC (IY+d),B",   // FDCB d 08
+    "RRC (IY+d),C",   // FDCB d 09
+    "RRC (IY+d),D",   // FDCB d 0A
+    "RRC (IY+d),E",   // FDCB d 0B
+    "RRC (IY+d),H",   // FDCB d 0C
+    "RRC (IY+d),L",   // FDCB d 0D
+    "RRC (IY+d)",     // FDCB d 0E
+    "RRC (IY+d),A",   // FDCB d 0F
+    "RL (IY+d),B",    // FDCB d 10
+    "RL (IY+d),C",    // FDCB d 11
+    "RL (IY+d),D",    // FDCB d 12
+    "RL (IY+d),E",    // FDCB d 13
+    "RL (IY+d),H",    // FDCB d 14
+    "RL (IY+d),L",    // FDCB d 15
+    "RL (IY+d)",      // FDCB d 16
+    "RL (IY+d),A",    // FDCB d 17
+    "RR (IY+d),B",    // FDCB d 18
+    "RR (IY+d),C",    // FDCB d 19
+    "RR (IY+d),D",    // FDCB d 1A
+    "RR (IY+d),E",    // FDCB d 1B
+    "RR (IY+d),H",    // FDCB d 1C
+    "RR (IY+d),L",    // FDCB d 1D
+    "RR (IY+d)",      // FDCB d 1E
+    "RR (IY+d),A",    // FDCB d 1F
+    "SLA (IY+d),B",   // FDCB d 20
+    "SLA (IY+d),C",   // FDCB d 21
+    "SLA (IY+d),D",   // FDCB d 22
+    "SLA (IY+d),E",   // FDCB d 23
+    "SLA (IY+d),H",   // FDCB d 24
+    "SLA (IY+d),L",   // FDCB d 25
+    "SLA (IY+d)",     // FDCB d 26
+    "SLA (IY+d),A",   // FDCB d 27
+    "SRA (IY+d),B",   // FDCB d 28
+    "SRA (IY+d),C",   // FDCB d 29
+    "SRA (IY+d),D",   // FDCB d 2A
+    "SRA (IY+d),E",   // FDCB d 2B
+    "SRA (IY+d),H",   // FDCB d 2C
+    "SRA (IY+d),L",   // FDCB d 2D
+    "SRA (IY+d)",     // FDCB d 2E
+    "SRA (IY+d),A",   // FDCB d 2F
+    "SLL (IY+d),B",   // FDCB d 30
+    "SLL (IY+d),C",   // FDCB d 31
+    "SLL (IY+d),D",   // FDCB d 32
+    "SLL (IY+d),E",   // FDCB d 33
+    "SLL (IY+d),H",   // FDCB d 34
+    "SLL (IY+d),L",   // FDCB d 35
+    "SLL (IY+d)",     // FDCB d 36
+    "SLL (IY+d),A",   // FDCB d 37
+    "SRL (IY+d),B",   // FDCB d 38
+    "SRL (IY+d),C",   // FDCB d 39
+    "SRL (IY+d),D",   // FDCB d 3A
+    "SRL (IY+d),E",   // FDCB d 3B
+    "SRL (IY+d),H",   // FDCB d 3C
+    "SRL (IY+d),L",   // FDCB d 3D
+    "SRL (IY+d)",     // FDCB d 3E
+    "SRL (IY+d),A",   // FDCB d 3F
+    "BIT 0,(IY+d)",   // FDCB d 40
+    "BIT 0,(IY+d)",   // FDCB d 41
+    "BIT 0,(IY+d)",   // FDCB d 42
+    "BIT 0,(IY+d)",   // FDCB d 43
+    "BIT 0,(IY+d)",   // FDCB d 44
+    "BIT 0,(IY+d)",   // FDCB d 45
+    "BIT 0,(IY+d)",   // FDCB d 46
+    "BIT 0,(IY+d)",   // FDCB d 47
+    "BIT 1,(IY+d)",   // FDCB d 48
+    "BIT 1,(IY+d)",   // FDCB d 49
+    "BIT 1,(IY+d)",   // FDCB d 4A
+    "BIT 1,(IY+d)",   // FDCB d 4B
+    "BIT 1,(IY+d)",   // FDCB d 4C
+    "BIT 1,(IY+d)",   // FDCB d 4D
+    "BIT 1,(IY+d)",   // FDCB d 4E
+    "BIT 1,(IY+d)",   // FDCB d 4F
+    "BIT 2,(IY+d)",   // FDCB d 50
+    "BIT 2,(IY+d)",   // FDCB d 51
+    "BIT 2,(IY+d)",   // FDCB d 52
+    "BIT 2,(IY+d)",   // FDCB d 53
+    "BIT 2,(IY+d)",   // FDCB d 54
+    "BIT 2,(IY+d)",   // FDCB d 55
+    "BIT 2,(IY+d)",   // FDCB d 56
+    "BIT 2,(IY+d)",   // FDCB d 57
+    "BIT 3,(IY+d)",   // FDCB d 58
+    "BIT 3,(IY+d)",   // FDCB d 59
+    "BIT 3,(IY+d)",   // FDCB d 5A
+    "BIT 3,(IY+d)",   // FDCB d 5B
+    "BIT 3,(IY+d)",   // FDCB d 5C
+    "BIT 3,(IY+d)",   // FDCB d 5D
+    "BIT 3,(IY+d)",   // FDCB d 5E
+    "BIT 3,(IY+d)",   // FDCB d 5F
+    "BIT 4,(IY+d)",   // FDCB d 60
+    "BIT 4,(IY+d)",   // FDCB d 61
+    "BIT 4,(IY+d)",   // FDCB d 62
+    "BIT 4,(IY+d)",   // FDCB d 63
+    "BIT 4,(IY+d)",   // FDCB d 64
+    "BIT 4,(IY+d)",   // FDCB d 65
+    "BIT 4,(IY+d)",   // FDCB d 66
+    "BIT 4,(IY+d)",   // FDCB d 67
+    "BIT 5,(IY+d)",   // FDCB d 68
+    "BIT 5,(IY+d)",   // FDCB d 69
+    "BIT 5,(IY+d)",   // FDCB d 6A
+    "BIT 5,(IY+d)",   // FDCB d 6B
+    "BIT 5,(IY+d)",   // FDCB d 6C
+    "BIT 5,(IY+d)",   // FDCB d 6D
+    "BIT 5,(IY+d)",   // FDCB d 6E
+    "BIT 5,(IY+d)",   // FDCB d 6F
+    "BIT 6,(IY+d)",   // FDCB d 70
+    "BIT 6,(IY+d)",   // FDCB d 71
+    "BIT 6,(IY+d)",   // FDCB d 72
+    "BIT 6,(IY+d)",   // FDCB d 73
+    "BIT 6,(IY+d)",   // FDCB d 74
+    "BIT 6,(IY+d)",   // FDCB d 75
+    "BIT 6,(IY+d)",   // FDCB d 76
+    "BIT 6,(IY+d)",   // FDCB d 77
+    "BIT 7,(IY+d)",   // FDCB d 78
+    "BIT 7,(IY+d)",   // FDCB d 79
+    "BIT 7,(IY+d)",   // FDCB d 7A
+    "BIT 7,(IY+d)",   // FDCB d 7B
+    "BIT 7,(IY+d)",   // FDCB d 7C
+    "BIT 7,(IY+d)",   // FDCB d 7D
+    "BIT 7,(IY+d)",   // FDCB d 7E
+    "BIT 7,(IY+d)",   // FDCB d 7F
+    "RES 0,(IY+d),B", // FDCB d 80
+    "RES 0,(IY+d),C", // FDCB d 81
+    "RES 0,(IY+d),D", // FDCB d 82
+    "RES 0,(IY+d),E", // FDCB d 83
+    "RES 0,(IY+d),H", // FDCB d 84
+    "RES 0,(IY+d),L", // FDCB d 85
+    "RES 0,(IY+d)",   // FDCB d 86
+    "RES 0,(IY+d),A", // FDCB d 87
+    "RES 1,(IY+d),B", // FDCB d 88
+    "RES 1,(IY+d),C", // FDCB d 89
+    "RES 1,(IY+d),D", // FDCB d 8A
+    "RES 1,(IY+d),E", // FDCB d 8B
+    "RES 1,(IY+d),H", // FDCB d 8C
+    "RES 1,(IY+d),L", // FDCB d 8D
+    "RES 1,(IY+d)",   // FDCB d 8E
+    "RES 1,(IY+d),A", // FDCB d 8F
+    "RES 2,(IY+d),B", // FDCB d 90
+    "RES 2,(IY+d),C", // FDCB d 91
+    "RES 2,(IY+d),D", // FDCB d 92
+    "RES 2,(IY+d),E", // FDCB d 93
+    "RES 2,(IY+d),H", // FDCB d 94
+    "RES 2,(IY+d),L", // FDCB d 95
+    "RES 2,(IY+d)",   // FDCB d 96
+    "RES 2,(IY+d),A", // FDCB d 97
+    "RES 3,(IY+d),B", // FDCB d 98
+    "RES 3,(IY+d),C", // FDCB d 99
+    "RES 3,(IY+d),D", // FDCB d 9A
+    "RES 3,(IY+d),E", // FDCB d 9B
+    "RES 3,(IY+d),H", // FDCB d 9C
+    "RES 3,(IY+d),L", // FDCB d 9D
+    "RES 3,(IY+d)",   // FDCB d 9E
+    "RES 3,(IY+d),A", // FDCB d 9F
+    "RES 4,(IY+d),B", // FDCB d A0
+    "RES 4,(IY+d),C", // FDCB d A1
+    "RES 4,(IY+d),D", // FDCB d A2
+    "RES 4,(IY+d),E", // FDCB d A3
+    "RES 4,(IY+d),H", // FDCB d A4
+    "RES 4,(IY+d),L", // FDCB d A5
+    "RES 4,(IY+d)",   // FDCB d A6
+    "RES 4,(IY+d),A", // FDCB d A7
+    "RES 5,(IY+d),B", // FDCB d A8
+    "RES 5,(IY+d),C", // FDCB d A9
+    "RES 5,(IY+d),D", // FDCB d AA
+    "RES 5,(IY+d),E", // FDCB d AB
+    "RES 5,(IY+d),H", // FDCB d AC
+    "RES 5,(IY+d),L", // FDCB d AD
+    "RES 5,(IY+d)",   // FDCB d AE
+    "RES 5,(IY+d),A", // FDCB d AF
+    "RES 6,(IY+d),B", // FDCB d B0
+    "RES 6,(IY+d),C", // FDCB d B1
+    "RES 6,(IY+d),D", // FDCB d B2
+    "RES 6,(IY+d),E", // FDCB d B3
+    "RES 6,(IY+d),H", // FDCB d B4
+    "RES 6,(IY+d),L", // FDCB d B5
+    "RES 6,(IY+d)",   // FDCB d B6
+    "RES 6,(IY+d),A", // FDCB d B7
+    "RES 7,(IY+d),B", // FDCB d B8
+    "RES 7,(IY+d),C", // FDCB d B9
+    "RES 7,(IY+d),D", // FDCB d BA
+    "RES 7,(IY+d),E", // FDCB d BB
+    "RES 7,(IY+d),H", // FDCB d BC
+    "RES 7,(IY+d),L", // FDCB d BD
+    "RES 7,(IY+d)",   // FDCB d BE
+    "RES 7,(IY+d),A", // FDCB d BF
+    "SET 0,(IY+d),B", // FDCB d C0
+    "SET 0,(IY+d),C", // FDCB d C1
+    "SET 0,(IY+d),D", // FDCB d C2
+    "SET 0,(IY+d),E", // FDCB d C3
+    "SET 0,(IY+d),H", // FDCB d C4
+    "SET 0,(IY+d),L", // FDCB d C5
+    "SET 0,(IY+d)",   // FDCB d C6
+    "SET 0,(IY+d),A", // FDCB d C7
+    "SET 1,(IY+d),B", // FDCB d C8
+    "SET 1,(IY+d),C", // FDCB d C9
+    "SET 1,(IY+d),D", // FDCB d CA
+    "SET 1,(IY+d),E", // FDCB d CB
+    "SET 1,(IY+d),H", // FDCB d CC
+    "SET 1,(IY+d),L", // FDCB d CD
+    "SET 1,(IY+d)",   // FDCB d CE
+    "SET 1,(IY+d),A", // FDCB d CF
+    "SET 2,(IY+d),B", // FDCB d D0
+    "SET 2,(IY+d),C", // FDCB d D1
+    "SET 2,(IY+d),D", // FDCB d D2
+    "SET 2,(IY+d),E", // FDCB d D3
+    "SET 2,(IY+d),H", // FDCB d D4
+    "SET 2,(IY+d),L", // FDCB d D5
+    "SET 2,(IY+d)",   // FDCB d D6
+    "SET 2,(IY+d),A", // FDCB d D7
+    "SET 3,(IY+d),B", // FDCB d D8
+    "SET 3,(IY+d),C", // FDCB d D9
+    "SET 3,(IY+d),D", // FDCB d DA
+    "SET 3,(IY+d),E", // FDCB d DB
+    "SET 3,(IY+d),H", // FDCB d DC
+    "SET 3,(IY+d),L", // FDCB d DD
+    "SET 3,(IY+d)",   // FDCB d DE
+    "SET 3,(IY+d),A", // FDCB d DF
+    "SET 4,(IY+d),B", // FDCB d E0
+    "SET 4,(IY+d),C", // FDCB d E1
+    "SET 4,(IY+d),D", // FDCB d E2
+    "SET 4,(IY+d),E", // FDCB d E3
+    "SET 4,(IY+d),H", // FDCB d E4
+    "SET 4,(IY+d),L", // FDCB d E5
+    "SET 4,(IY+d)",   // FDCB d E6
+    "SET 4,(IY+d),A", // FDCB d E7
+    "SET 5,(IY+d),B", // FDCB d E8
+    "SET 5,(IY+d),C", // FDCB d E9
+    "SET 5,(IY+d),D", // FDCB d EA
+    "SET 5,(IY+d),E", // FDCB d EB
+    "SET 5,(IY+d),H", // FDCB d EC
+    "SET 5,(IY+d),L", // FDCB d ED
+    "SET 5,(IY+d)",   // FDCB d EE
+    "SET 5,(IY+d),A", // FDCB d EF
+    "SET 6,(IY+d),B", // FDCB d F0
+    "SET 6,(IY+d),C", // FDCB d F1
+    "SET 6,(IY+d),D", // FDCB d F2
+    "SET 6,(IY+d),E", // FDCB d F3
+    "SET 6,(IY+d),H", // FDCB d F4
+    "SET 6,(IY+d),L", // FDCB d F5
+    "SET 6,(IY+d)",   // FDCB d F6
+    "SET 6,(IY+d),A", // FDCB d F7
+    "SET 7,(IY+d),B", // FDCB d F8
+    "SET 7,(IY+d),C", // FDCB d F9
+    "SET 7,(IY+d),D", // FDCB d FA
+    "SET 7,(IY+d),E", // FDCB d FB
+    "SET 7,(IY+d),H", // FDCB d FC
+    "SET 7,(IY+d),L", // FDCB d FD
+    "SET 7,(IY+d)",   // FDCB d FE
+    "SET 7,(IY+d),A", // FDCB d FF
+];
+
 impl Bus {
     /// Disassembles opcode and operand at (address), returns a tuple (disassembled string, instruction size in bytes)
     pub fn dasm(&self, address: u16) -> (String, u8) {
-        let opcode = self.read_byte(address);
-        let mut opcode_16: u16 = 0x0000;
-        let instr = match opcode {
-            0xCB => {
-                // Reading the byte following the prefix
-                let oc = self.read_byte(address + 1);
-                // Reading corresponding disassembled string from the table
-                let dasm_str = String::from(DASM_CB[oc as usize]);
-                format!("CB{:02X}          {}", oc, dasm_str)
-            }
-            0xDD => {
-                // Reading the byte following the prefix
-                let oc = self.read_byte(address + 1);
-                opcode_16 = 0xDD00 | (oc as u16);
-                //println!("Debug opcode_16 : {:04X}", opcode_16);
-                // Reading corresponding disassembled string from the table
-                let dasm_str = String::from(DASM_DD[oc as usize]);
-                match opcode_16 {
-                    0xDD46 | 0xFD46 | 0xDD4E | 0xFD4E | 0xDD56 | 0xFD56 | 0xDD5E | 0xFD5E
-                    | 0xDD66 | 0xFD66 | 0xDD6E | 0xFD6E | 0xDD7E | 0xFD7E | 0xDD70 | 0xDD71
-                    | 0xDD72 | 0xDD73 | 0xDD74 | 0xDD75 | 0xDD77 | 0xFD70 | 0xFD71 | 0xFD72
-                    | 0xFD73 | 0xFD74 | 0xFD75 | 0xFD77 | 0xDD86 | 0xFD86 | 0xDD8E | 0xFD8E
-                    | 0xDD96 | 0xFD96 | 0xDD9E | 0xFD9E | 0xDDA6 | 0xFDA6 | 0xDDB6 | 0xFDB6
-                    | 0xDDAE | 0xFDAE | 0xDDBE | 0xFDBE | 0xDD34 | 0xFD34 | 0xDD35 | 0xFD35 => {
-                        let operand = self.read_byte(address + 2);
-                        format!("{:04X} {:02X}        {}", opcode_16, operand, dasm_str)
-                    }
-                    0xDD36 | 0xFD36 | 0xDD21 | 0xFD21 | 0xED4B | 0xED5B | 0xED6B | 0xED7B
-                    | 0xDD2A | 0xFD2A | 0xED43 | 0xED53 | 0xED63 | 0xED73 | 0xDD22 | 0xFD22
-                    | 0xDDCB | 0xFDCB => {
-                        let operand = self.read_word(address + 2);
-                        format!(
-                            "{:04X} {:02X} {:02X}    {}",
-                            opcode_16,
-                            (operand & 0x00FF) as u8,
-                            (operand >> 8 & 0x00FF) as u8,
-                            dasm_str
-                        )
-                    }
-                    _ => format!("DD{:02X}          {}", oc, dasm_str),
+        disassemble(|a| self.read_byte(a), address)
+    }
+}
+
+/// Disassembles opcode and operand at (address), reading memory through a function, like `Bus::dasm` for any memory type.
+/// Returns a tuple (disassembled string, instruction size in bytes)
+/// ```rust
+/// use zilog_z80::dasm::disassemble;
+/// let rom = [0x3E, 0x12];             // LD A,$12
+/// assert_eq!(disassemble(|a| rom[a as usize], 0x0000).1, 2);
+/// ```
+pub fn disassemble<F: FnMut(u16) -> u8>(mut read: F, address: u16) -> (String, u8) {
+    let opcode = read(address);
+    let mut opcode_16: u16 = 0x0000;
+    let instr = match opcode {
+        0xCB => {
+            // Reading the byte following the prefix
+            let oc = read(address + 1);
+            // Reading corresponding disassembled string from the table
+            let dasm_str = String::from(DASM_CB[oc as usize]);
+            format!("CB{:02X}          {}", oc, dasm_str)
+        }
+        0xED => {
+            // Reading the byte following the prefix
+            let oc = read(address + 1);
+            opcode_16 = 0xED00 | (oc as u16);
+            // Reading corresponding disassembled string from the table
+            let dasm_str = String::from(DASM_ED[oc as usize]);
+            match opcode_16 {
+                0xED4B | 0xED5B | 0xED6B | 0xED7B | 0xED43 | 0xED53 | 0xED63 | 0xED73 => {
+                    let operand = read_word(&mut read, address + 2);
+                    format!(
+                        "{:04X} {:02X} {:02X}    {}",
+                        opcode_16,
+                        (operand & 0x00FF) as u8,
+                        (operand >> 8 & 0x00FF) as u8,
+                        dasm_str
+                    )
                 }
+                _ => format!("ED{:02X}          {}", oc, dasm_str),
             }
-            0xFD => {
-                // Reading the byte following the prefix
-                let oc = self.read_byte(address + 1);
-                opcode_16 = 0xFD00 | (oc as u16);
-                //println!("Debug opcode_16 : {:04X}", opcode_16);
-                // Reading corresponding disassembled string from the table
-                let dasm_str = String::from(DASM_FD[oc as usize]);
-                match opcode_16 {
-                    0xDD46 | 0xFD46 | 0xDD4E | 0xFD4E | 0xDD56 | 0xFD56 | 0xDD5E | 0xFD5E
-                    | 0xDD66 | 0xFD66 | 0xDD6E | 0xFD6E | 0xDD7E | 0xFD7E | 0xDD70 | 0xDD71
-                    | 0xDD72 | 0xDD73 | 0xDD74 | 0xDD75 | 0xDD77 | 0xFD70 | 0xFD71 | 0xFD72
-                    | 0xFD73 | 0xFD74 | 0xFD75 | 0xFD77 | 0xDD86 | 0xFD86 | 0xDD8E | 0xFD8E
-                    | 0xDD96 | 0xFD96 | 0xDD9E | 0xFD9E | 0xDDA6 | 0xFDA6 | 0xDDB6 | 0xFDB6
-                    | 0xDDAE | 0xFDAE | 0xDDBE | 0xFDBE | 0xDD34 | 0xFD34 | 0xDD35 | 0xFD35 => {
-                        let operand = self.read_byte(address + 2);
-                        format!("{:04X} {:02X}        {}", opcode_16, operand, dasm_str)
-                    }
-                    0xDD36 | 0xFD36 | 0xDD21 | 0xFD21 | 0xED4B | 0xED5B | 0xED6B | 0xED7B
-                    | 0xDD2A | 0xFD2A | 0xED43 | 0xED53 | 0xED63 | 0xED73 | 0xDD22 | 0xFD22
-                    | 0xDDCB | 0xFDCB => {
-                        let operand = self.read_word(address + 2);
-                        format!(
-                            "{:04X} {:02X} {:02X}    {}",
-                            opcode_16,
-                            (operand & 0x00FF) as u8,
-                            (operand >> 8 & 0x00FF) as u8,
-                            dasm_str
-                        )
-                    }
-                    _ => format!("FD{:02X}          {}", oc, dasm_str),
+        }
+        0xDD | 0xFD if !is_indexed_opcode(read(address + 1)) => {
+            // The prefix does not apply to the following instruction, which is disassembled on its own
+            format!("{:02X}            NOP (ignored prefix)", opcode)
+        }
+        0xDD => {
+            // Reading the byte following the prefix
+            let oc = read(address + 1);
+            opcode_16 = 0xDD00 | (oc as u16);
+            //println!("Debug opcode_16 : {:04X}", opcode_16);
+            // Reading corresponding disassembled string from the table
+            let dasm_str = String::from(DASM_DD[oc as usize]);
+            match opcode_16 {
+                0xDDCB => {
+                    // The sub-opcode follows the displacement
+                    let displacement = read(address + 2);
+                    let oc = read(address + 3);
+                    format!(
+                        "DDCB {:02X} {:02X}    {}",
+                        displacement, oc, DASM_DDCB[oc as usize]
+                    )
                 }
+                0xDD46 | 0xFD46 | 0xDD4E | 0xFD4E | 0xDD56 | 0xFD56 | 0xDD5E | 0xFD5E | 0xDD66
+                | 0xFD66 | 0xDD6E | 0xFD6E | 0xDD7E | 0xFD7E | 0xDD70 | 0xDD71 | 0xDD72
+                | 0xDD73 | 0xDD74 | 0xDD75 | 0xDD77 | 0xFD70 | 0xFD71 | 0xFD72 | 0xFD73
+                | 0xFD74 | 0xFD75 | 0xFD77 | 0xDD86 | 0xFD86 | 0xDD8E | 0xFD8E | 0xDD96
+                | 0xFD96 | 0xDD9E | 0xFD9E | 0xDDA6 | 0xFDA6 | 0xDDB6 | 0xFDB6 | 0xDDAE
+                | 0xFDAE | 0xDDBE | 0xFDBE | 0xDD34 | 0xFD34 | 0xDD35 | 0xFD35 | 0xDD26
+                | 0xFD26 | 0xDD2E | 0xFD2E => {
+                    let operand = read(address + 2);
+                    format!("{:04X} {:02X}        {}", opcode_16, operand, dasm_str)
+                }
+                0xDD36 | 0xFD36 | 0xDD21 | 0xFD21 | 0xED4B | 0xED5B | 0xED6B | 0xED7B | 0xDD2A
+                | 0xFD2A | 0xED43 | 0xED53 | 0xED63 | 0xED73 | 0xDD22 | 0xFD22 | 0xFDCB => {
+                    let operand = read_word(&mut read, address + 2);
+                    format!(
+                        "{:04X} {:02X} {:02X}    {}",
+                        opcode_16,
+                        (operand & 0x00FF) as u8,
+                        (operand >> 8 & 0x00FF) as u8,
+                        dasm_str
+                    )
+                }
+                _ => format!("DD{:02X}          {}", oc, dasm_str),
             }
-            // 8-Bit Load Group
-            // LD r,r'      LD r,(HL)
-            0x40 => String::from("40            LD B,B"), // LD B,B
-            0x41 => String::from("41            LD B,C"), // LD B,C
-            0x42 => String::from("42            LD B,D"), // LD B,D
-            0x43 => String::from("43            LD B,E"), // LD B,E
-            0x44 => String::from("44            LD B,H"), // LD B,H
-            0x45 => String::from("45            LD B,L"), // LD B,L
-            0x46 => String::from("46            LD B,(HL)"), // LD B,(HL)
-            0x47 => String::from("47            LD B,A"), // LD B,A
-
-            0x48 => String::from("48            LD C,B"), // LD C,B
-            0x49 => String::from("49            LD C,C"), // LD C,C
-            0x4A => String::from("4A            LD C,D"), // LD C,D
-            0x4B => String::from("4B            LD C,E"), // LD C,E
-            0x4C => String::from("4C            LD C,H"), // LD C,H
-            0x4D => String::from("4D            LD C,L"), // LD C,L
-            0x4E => String::from("4E            LD C,(HL)"), // LD C,(HL)
-            0x4F => String::from("4F            LD C,A"), // LD C,A
-
-            0x50 => String::from("50            LD D,B"), // LD D,B
-            0x51 => String::from("51            LD D,C"), // LD D,C
-            0x52 => String::from("52            LD D,D"), // LD D,D
-            0x53 => String::from("53            LD D,E"), // LD D,E
-            0x54 => String::from("54            LD D,H"), // LD D,H
-            0x55 => String::from("55            LD D,L"), // LD D,L
-            0x56 => String::from("56            LD D,(HL)"), // LD D,(HL)
-            0x57 => String::from("57            LD D,A"), // LD D,A
-
-            0x58 => String::from("58            LD E,B"), // LD E,B
-            0x59 => String::from("59            LD E,C"), // LD E,C
-            0x5A => String::from("5A            LD E,D"), // LD E,D
-            0x5B => String::from("5B            LD E,E"), // LD E,E
-            0x5C => String::from("5C            LD E,H"), // LD E,H
-            0x5D => String::from("5D            LD E,L"), // LD E,L
-            0x5E => String::from("5E            LD E,(HL)"), // LD E,(HL)
-            0x5F => String::from("5F            LD E,A"), // LD E,A
-
-            0x60 => String::from("60            LD H,B"), // LD H,B
-            0x61 => String::from("61            LD H,C"), // LD H,C
-            0x62 => String::from("62            LD H,D"), // LD H,D
-            0x63 => String::from("63            LD H,E"), // LD H,E
-            0x64 => String::from("64            LD H,H"), // LD H,H
-            0x65 => String::from("65            LD H,L"), // LD H,L
-            0x66 => String::from("66            LD H,(HL)"), // LD H,(HL)
-            0x67 => String::from("67            LD H,A"), // LD H,A
-
-            0x68 => String::from("68            LD L,B"), // LD L,B
-            0x69 => String::from("69            LD L,C"), // LD L,C
-            0x6A => String::from("6A            LD L,D"), // LD L,D
-            0x6B => String::from("6B            LD L,E"), // LD L,E
-            0x6C => String::from("6C            LD L,H"), // LD L,H
-            0x6D => String::from("6D            LD L,L"), // LD L,L
-            0x6E => String::from("6E            LD L,(HL)"), // LD L,(HL)
-            0x6F => String::from("6F            LD L,A"), // LD L,A
-
-            0x78 => String::from("78            LD A,B"), // LD A,B
-            0x79 => String::from("79            LD A,C"), // LD A,C
-            0x7A => String::from("7A            LD A,D"), // LD A,D
-            0x7B => String::from("7B            LD A,E"), // LD A,E
-            0x7C => String::from("7C            LD A,H"), // LD A,H
-            0x7D => String::from("7D            LD A,L"), // LD A,L
-            0x7E => String::from("7E            LD A,(HL)"), // LD A,(HL)
-            0x7F => String::from("7F            LD A,A"), // LD A,A
-
-            // LD (HL),r
-            0x70 => String::from("70            LD (HL), B"), // LD (HL), B
-            0x71 => String::from("71            LD (HL), C"), // LD (HL), C
-            0x72 => String::from("72            LD (HL), D"), // LD (HL), D
-            0x73 => String::from("73            LD (HL), E"), // LD (HL), E
-            0x74 => String::from("74            LD (HL), H"), // LD (HL), H
-            0x75 => String::from("75            LD (HL), L"), // LD (HL), L
-            0x77 => String::from("77            LD (HL), A"), // LD (HL), A
-
-            // LD r,n
-            0x06 => {
-                // LD B,n
-                let data = self.read_byte(address + 1);
-                format!("06 {:02X}         LD B,${:02X}", data, data)
-            }
-            0x0E => {
-                // LD C,n
-                let data = self.read_byte(address + 1);
-                format!("0E {:02X}         LD C,${:02X}", data, data)
-            }
-            0x16 => {
-                // LD D,n
-                let data = self.read_byte(address + 1);
-                format!("16 {:02X}         LD D,${:02X}", data, data)
-            }
-            0x1E => {
-                // LD E,n
-                let data = self.read_byte(address + 1);
-                format!("1E {:02X}         LD E,${:02X}", data, data)
-            }
-            0x26 => {
-                // LD H,n
-                let data = self.read_byte(address + 1);
-                format!("26 {:02X}         LD H,${:02X}", data, data)
-            }
-            0x2E => {
-                // LD L,n
-                let data = self.read_byte(address + 1);
-                format!("2E {:02X}         LD L,${:02X}", data, data)
-            }
-            0x36 => {
-                // LD (HL),n
-                let data = self.read_byte(address + 1);
-                format!("36 {:02X}         LD LD (HL),{:02X}", data, data)
-            }
-            0x3E => {
-                // LD A,n
-                let data = self.read_byte(address + 1);
-                format!("3E {:02X}         LD A,${:02X}", data, data)
-            }
-
-            // LD A,(BC)
-            0x0A => {
-                format!("0A            LD A,(BC)")
-            }
-
-            // LD A,(DE)
-            0x1A => {
-                format!("1A            LD A,(DE)")
-            }
-
-            // LD A,(nn)
-            0x3A => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "3A {:02X} {:02X}      LD A,(${:04X})",
-                    addr_low, addr_high, addr
-                )
-            }
-
-            // LD (BC),A
-            0x02 => {
-                format!("02            LD (BC),A)")
+        }
+        0xFD => {
+            // Reading the byte following the prefix
+            let oc = read(address + 1);
+            opcode_16 = 0xFD00 | (oc as u16);
+            //println!("Debug opcode_16 : {:04X}", opcode_16);
+            // Reading corresponding disassembled string from the table
+            let dasm_str = String::from(DASM_FD[oc as usize]);
+            match opcode_16 {
+                0xFDCB => {
+                    // The sub-opcode follows the displacement
+                    let displacement = read(address + 2);
+                    let oc = read(address + 3);
+                    format!(
+                        "FDCB {:02X} {:02X}    {}",
+                        displacement, oc, DASM_FDCB[oc as usize]
+                    )
+                }
+                0xDD46 | 0xFD46 | 0xDD4E | 0xFD4E | 0xDD56 | 0xFD56 | 0xDD5E | 0xFD5E | 0xDD66
+                | 0xFD66 | 0xDD6E | 0xFD6E | 0xDD7E | 0xFD7E | 0xDD70 | 0xDD71 | 0xDD72
+                | 0xDD73 | 0xDD74 | 0xDD75 | 0xDD77 | 0xFD70 | 0xFD71 | 0xFD72 | 0xFD73
+                | 0xFD74 | 0xFD75 | 0xFD77 | 0xDD86 | 0xFD86 | 0xDD8E | 0xFD8E | 0xDD96
+                | 0xFD96 | 0xDD9E | 0xFD9E | 0xDDA6 | 0xFDA6 | 0xDDB6 | 0xFDB6 | 0xDDAE
+                | 0xFDAE | 0xDDBE | 0xFDBE | 0xDD34 | 0xFD34 | 0xDD35 | 0xFD35 | 0xDD26
+                | 0xFD26 | 0xDD2E | 0xFD2E => {
+                    let operand = read(address + 2);
+                    format!("{:04X} {:02X}        {}", opcode_16, operand, dasm_str)
+                }
+                0xDD36 | 0xFD36 | 0xDD21 | 0xFD21 | 0xED4B | 0xED5B | 0xED6B | 0xED7B | 0xDD2A
+                | 0xFD2A | 0xED43 | 0xED53 | 0xED63 | 0xED73 | 0xDD22 | 0xFD22 | 0xDDCB => {
+                    let operand = read_word(&mut read, address + 2);
+                    format!(
+                        "{:04X} {:02X} {:02X}    {}",
+                        opcode_16,
+                        (operand & 0x00FF) as u8,
+                        (operand >> 8 & 0x00FF) as u8,
+                        dasm_str
+                    )
+                }
+                _ => format!("FD{:02X}          {}", oc, dasm_str),
             }
+        }
+        // 8-Bit Load Group
+        // LD r,r'      LD r,(HL)
+        0x40 => String::from("40            LD B,B"), // LD B,B
+        0x41 => String::from("41            LD B,C"), // LD B,C
+        0x42 => String::from("42            LD B,D"), // LD B,D
+        0x43 => String::from("43            LD B,E"), // LD B,E
+        0x44 => String::from("44            LD B,H"), // LD B,H
+        0x45 => String::from("45            LD B,L"), // LD B,L
+        0x46 => String::from("46            LD B,(HL)"), // LD B,(HL)
+        0x47 => String::from("47            LD B,A"), // LD B,A
+
+        0x48 => String::from("48            LD C,B"), // LD C,B
+        0x49 => String::from("49            LD C,C"), // LD C,C
+        0x4A => String::from("4A            LD C,D"), // LD C,D
+        0x4B => String::from("4B            LD C,E"), // LD C,E
+        0x4C => String::from("4C            LD C,H"), // LD C,H
+        0x4D => String::from("4D            LD C,L"), // LD C,L
+        0x4E => String::from("4E            LD C,(HL)"), // LD C,(HL)
+        0x4F => String::from("4F            LD C,A"), // LD C,A
+
+        0x50 => String::from("50            LD D,B"), // LD D,B
+        0x51 => String::from("51            LD D,C"), // LD D,C
+        0x52 => String::from("52            LD D,D"), // LD D,D
+        0x53 => String::from("53            LD D,E"), // LD D,E
+        0x54 => String::from("54            LD D,H"), // LD D,H
+        0x55 => String::from("55            LD D,L"), // LD D,L
+        0x56 => String::from("56            LD D,(HL)"), // LD D,(HL)
+        0x57 => String::from("57            LD D,A"), // LD D,A
+
+        0x58 => String::from("58            LD E,B"), // LD E,B
+        0x59 => String::from("59            LD E,C"), // LD E,C
+        0x5A => String::from("5A            LD E,D"), // LD E,D
+        0x5B => String::from("5B            LD E,E"), // LD E,E
+        0x5C => String::from("5C            LD E,H"), // LD E,H
+        0x5D => String::from("5D            LD E,L"), // LD E,L
+        0x5E => String::from("5E            LD E,(HL)"), // LD E,(HL)
+        0x5F => String::from("5F            LD E,A"), // LD E,A
+
+        0x60 => String::from("60            LD H,B"), // LD H,B
+        0x61 => String::from("61            LD H,C"), // LD H,C
+        0x62 => String::from("62            LD H,D"), // LD H,D
+        0x63 => String::from("63            LD H,E"), // LD H,E
+        0x64 => String::from("64            LD H,H"), // LD H,H
+        0x65 => String::from("65            LD H,L"), // LD H,L
+        0x66 => String::from("66            LD H,(HL)"), // LD H,(HL)
+        0x67 => String::from("67            LD H,A"), // LD H,A
+
+        0x68 => String::from("68            LD L,B"), // LD L,B
+        0x69 => String::from("69            LD L,C"), // LD L,C
+        0x6A => String::from("6A            LD L,D"), // LD L,D
+        0x6B => String::from("6B            LD L,E"), // LD L,E
+        0x6C => String::from("6C            LD L,H"), // LD L,H
+        0x6D => String::from("6D            LD L,L"), // LD L,L
+        0x6E => String::from("6E            LD L,(HL)"), // LD L,(HL)
+        0x6F => String::from("6F            LD L,A"), // LD L,A
+
+        0x78 => String::from("78            LD A,B"), // LD A,B
+        0x79 => String::from("79            LD A,C"), // LD A,C
+        0x7A => String::from("7A            LD A,D"), // LD A,D
+        0x7B => String::from("7B            LD A,E"), // LD A,E
+        0x7C => String::from("7C            LD A,H"), // LD A,H
+        0x7D => String::from("7D            LD A,L"), // LD A,L
+        0x7E => String::from("7E            LD A,(HL)"), // LD A,(HL)
+        0x7F => String::from("7F            LD A,A"), // LD A,A
+
+        // LD (HL),r
+        0x70 => String::from("70            LD (HL), B"), // LD (HL), B
+        0x71 => String::from("71            LD (HL), C"), // LD (HL), C
+        0x72 => String::from("72            LD (HL), D"), // LD (HL), D
+        0x73 => String::from("73            LD (HL), E"), // LD (HL), E
+        0x74 => String::from("74            LD (HL), H"), // LD (HL), H
+        0x75 => String::from("75            LD (HL), L"), // LD (HL), L
+        0x77 => String::from("77            LD (HL), A"), // LD (HL), A
+
+        // LD r,n
+        0x06 => {
+            // LD B,n
+            let data = read(address + 1);
+            format!("06 {:02X}         LD B,${:02X}", data, data)
+        }
+        0x0E => {
+            // LD C,n
+            let data = read(address + 1);
+            format!("0E {:02X}         LD C,${:02X}", data, data)
+        }
+        0x16 => {
+            // LD D,n
+            let data = read(address + 1);
+            format!("16 {:02X}         LD D,${:02X}", data, data)
+        }
+        0x1E => {
+            // LD E,n
+            let data = read(address + 1);
+            format!("1E {:02X}         LD E,${:02X}", data, data)
+        }
+        0x26 => {
+            // LD H,n
+            let data = read(address + 1);
+            format!("26 {:02X}         LD H,${:02X}", data, data)
+        }
+        0x2E => {
+            // LD L,n
+            let data = read(address + 1);
+            format!("2E {:02X}         LD L,${:02X}", data, data)
+        }
+        0x36 => {
+            // LD (HL),n
+            let data = read(address + 1);
+            format!("36 {:02X}         LD LD (HL),{:02X}", data, data)
+        }
+        0x3E => {
+            // LD A,n
+            let data = read(address + 1);
+            format!("3E {:02X}         LD A,${:02X}", data, data)
+        }
 
-            // LD (DE),A
-            0x12 => {
-                format!("12            LD (DE),A")
-            }
+        // LD A,(BC)
+        0x0A => String::from("0A            LD A,(BC)"),
+
+        // LD A,(DE)
+        0x1A => String::from("1A            LD A,(DE)"),
+
+        // LD A,(nn)
+        0x3A => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "3A {:02X} {:02X}      LD A,(${:04X})",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // LD (nn),A
-            0x32 => {
-                let addr = self.read_word(address + 1);
-                format!("32            LD (${:04X}),A", addr)
-            }
+        // LD (BC),A
+        0x02 => String::from("02            LD (BC),A"),
 
-            // 16-Bit Load Group
-            // LD dd,nn
-            0x01 => {
-                // LD BC,nn
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let d16 = self.read_word(address + 1);
-                format!(
-                    "01 {:02X} {:02X}      LD BC,${:04X}",
-                    addr_low, addr_high, d16
-                )
-            }
-            0x11 => {
-                // LD DE,nn
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let d16 = self.read_word(address + 1);
-                format!(
-                    "11 {:02X} {:02X}      LD DE,${:04X}",
-                    addr_low, addr_high, d16
-                )
-            }
-            0x21 => {
-                // LD HL,nn
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let d16 = self.read_word(address + 1);
-                format!(
-                    "21 {:02X} {:02X}      LD HL,${:04X}",
-                    addr_low, addr_high, d16
-                )
-            }
-            0x31 => {
-                // LD SP,nn
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let d16 = self.read_word(address + 1);
-                format!(
-                    "31 {:02X} {:02X}      LD SP,${:04X}",
-                    addr_low, addr_high, d16
-                )
-            }
+        // LD (DE),A
+        0x12 => String::from("12            LD (DE),A"),
 
-            // LD HL,(nn)
-            0x2A => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "2A {:02X} {:02X}      LD HL,(${:04X})",
-                    addr_low, addr_high, addr
-                )
-            }
+        // LD (nn),A
+        0x32 => {
+            let addr = read_word(&mut read, address + 1);
+            format!("32            LD (${:04X}),A", addr)
+        }
 
-            // LD (nn),HL
-            0x22 => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "22 {:02X} {:02X}      LD (${:04X}),HL",
-                    addr_low, addr_high, addr
-                )
-            }
+        // 16-Bit Load Group
+        // LD dd,nn
+        0x01 => {
+            // LD BC,nn
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let d16 = read_word(&mut read, address + 1);
+            format!(
+                "01 {:02X} {:02X}      LD BC,${:04X}",
+                addr_low, addr_high, d16
+            )
+        }
+        0x11 => {
+            // LD DE,nn
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let d16 = read_word(&mut read, address + 1);
+            format!(
+                "11 {:02X} {:02X}      LD DE,${:04X}",
+                addr_low, addr_high, d16
+            )
+        }
+        0x21 => {
+            // LD HL,nn
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let d16 = read_word(&mut read, address + 1);
+            format!(
+                "21 {:02X} {:02X}      LD HL,${:04X}",
+                addr_low, addr_high, d16
+            )
+        }
+        0x31 => {
+            // LD SP,nn
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let d16 = read_word(&mut read, address + 1);
+            format!(
+                "31 {:02X} {:02X}      LD SP,${:04X}",
+                addr_low, addr_high, d16
+            )
+        }
 
-            // LD SP,HL
-            0xF9 => String::from("F9            LD SP,HL"),
-
-            // PUSH qq
-            0xC5 => String::from("C5            PUSH BC"), // PUSH BC
-            0xD5 => String::from("D5            PUSH DE"), // PUSH DE
-            0xE5 => String::from("E5            PUSH HL"), // PUSH HL
-            0xF5 => String::from("F5            PUSH AF"), // PUSH AF
-
-            // POP qq
-            0xC1 => String::from("C1            POP BC"), // POP BC
-            0xD1 => String::from("D1            POP DE"), // POP DE
-            0xE1 => String::from("E1            POP HL"), // POP HL
-            0xF1 => String::from("F1            POP AF"), // POP AF
-
-            // Exchange, Block Transfer, and Search Group
-            // EX DE,HL
-            0xEB => String::from("EB            EX DE,HL"),
-
-            // EX AF,AF'
-            0x08 => String::from("08            EX AF,AF'"),
-
-            // EXX
-            0xD9 => String::from("D9            EXX"),
-
-            // EX (SP),HL
-            0xE3 => String::from("E3            EX (SP),HL"),
-
-            // 8-Bit Arithmetic Group
-            // ADD A,r
-            0x80 => String::from("80            ADD A,B"), // ADD A,B
-            0x81 => String::from("81            ADD A,C"), // ADD A,C
-            0x82 => String::from("82            ADD A,D"), // ADD A,D
-            0x83 => String::from("83            ADD A,E"), // ADD A,E
-            0x84 => String::from("84            ADD A,H"), // ADD A,H
-            0x85 => String::from("85            ADD A,L"), // ADD A,L
-            0x86 => String::from("86            ADD A,(HL)"), // ADD A,(HL)
-            0x87 => String::from("87            ADD A,A"), // ADD A,A
-
-            // ADD A,n
-            0xC6 => {
-                let n = self.read_byte(address + 1);
-                format!("C6 {:02X}         ADD A,${:02X}", n, n)
-            }
+        // LD HL,(nn)
+        0x2A => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "2A {:02X} {:02X}      LD HL,(${:04X})",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // ADC A,r
-            0x88 => String::from("88            ADC A,B"), // ADC A,B
-            0x89 => String::from("89            ADC A,C"), // ADC A,C
-            0x8A => String::from("8A            ADC A,D"), // ADC A,D
-            0x8B => String::from("8B            ADC A,E"), // ADC A,E
-            0x8C => String::from("8C            ADC A,H"), // ADC A,H
-            0x8D => String::from("8D            ADC A,L"), // ADC A,L
-            0x8E => String::from("8E            ADC A,(HL)"), // ADC A,(HL)
-            0x8F => String::from("8F            ADC A,A"), // ADC A,A
-
-            // ADC a,n
-            0xCE => {
-                // ADC A,(HL)
-                let n = self.read_byte(address + 1);
-                format!("CE {:02X}         ADC A,${:02X}", n, n)
-            }
+        // LD (nn),HL
+        0x22 => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "22 {:02X} {:02X}      LD (${:04X}),HL",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // SUB s
-            0x90 => String::from("90            SUB A,B"), // SUB A,B
-            0x91 => String::from("91            SUB A,C"), // SUB A,C
-            0x92 => String::from("92            SUB A,D"), // SUB A,D
-            0x93 => String::from("93            SUB A,E"), // SUB A,E
-            0x94 => String::from("94            SUB A,H"), // SUB A,H
-            0x95 => String::from("95            SUB A,L"), // SUB A,L
-            0x96 => String::from("96            SUB A,(HL)"), // SUB A,(HL)
-            0x97 => String::from("97            SUB A,A"), // SUB A,A
-
-            0xD6 => {
-                // SUB A,n
-                let n = self.read_byte(address + 1);
-                format!("D6 {:02X}         SUB A,${:02X}", n, n)
-            }
+        // LD SP,HL
+        0xF9 => String::from("F9            LD SP,HL"),
+
+        // PUSH qq
+        0xC5 => String::from("C5            PUSH BC"), // PUSH BC
+        0xD5 => String::from("D5            PUSH DE"), // PUSH DE
+        0xE5 => String::from("E5            PUSH HL"), // PUSH HL
+        0xF5 => String::from("F5            PUSH AF"), // PUSH AF
+
+        // POP qq
+        0xC1 => String::from("C1            POP BC"), // POP BC
+        0xD1 => String::from("D1            POP DE"), // POP DE
+        0xE1 => String::from("E1            POP HL"), // POP HL
+        0xF1 => String::from("F1            POP AF"), // POP AF
+
+        // Exchange, Block Transfer, and Search Group
+        // EX DE,HL
+        0xEB => String::from("EB            EX DE,HL"),
+
+        // EX AF,AF'
+        0x08 => String::from("08            EX AF,AF'"),
+
+        // EXX
+        0xD9 => String::from("D9            EXX"),
+
+        // EX (SP),HL
+        0xE3 => String::from("E3            EX (SP),HL"),
+
+        // 8-Bit Arithmetic Group
+        // ADD A,r
+        0x80 => String::from("80            ADD A,B"), // ADD A,B
+        0x81 => String::from("81            ADD A,C"), // ADD A,C
+        0x82 => String::from("82            ADD A,D"), // ADD A,D
+        0x83 => String::from("83            ADD A,E"), // ADD A,E
+        0x84 => String::from("84            ADD A,H"), // ADD A,H
+        0x85 => String::from("85            ADD A,L"), // ADD A,L
+        0x86 => String::from("86            ADD A,(HL)"), // ADD A,(HL)
+        0x87 => String::from("87            ADD A,A"), // ADD A,A
+
+        // ADD A,n
+        0xC6 => {
+            let n = read(address + 1);
+            format!("C6 {:02X}         ADD A,${:02X}", n, n)
+        }
 
-            // SBC A,s
-            0x98 => String::from("98            SBC A,B"), // SBC A,B
-            0x99 => String::from("99            SBC A,C"), // SBC A,C
-            0x9A => String::from("9A            SBC A,D"), // SBC A,D
-            0x9B => String::from("9B            SBC A,E"), // SBC A,E
-            0x9C => String::from("9C            SBC A,H"), // SBC A,H
-            0x9D => String::from("9D            SBC A,L"), // SBC A,L
-            0x9E => String::from("9E            SBC A,(HL)"), // SBC A,(HL)
-            0x9F => String::from("9F            SBC A,A"), // SBC A,A
-
-            0xDE => {
-                // SBC A,n
-                let n = self.read_byte(address + 1);
-                format!("DE {:02X}         SBC A,${:02X}", n, n)
-            }
+        // ADC A,r
+        0x88 => String::from("88            ADC A,B"), // ADC A,B
+        0x89 => String::from("89            ADC A,C"), // ADC A,C
+        0x8A => String::from("8A            ADC A,D"), // ADC A,D
+        0x8B => String::from("8B            ADC A,E"), // ADC A,E
+        0x8C => String::from("8C            ADC A,H"), // ADC A,H
+        0x8D => String::from("8D            ADC A,L"), // ADC A,L
+        0x8E => String::from("8E            ADC A,(HL)"), // ADC A,(HL)
+        0x8F => String::from("8F            ADC A,A"), // ADC A,A
+
+        // ADC a,n
+        0xCE => {
+            // ADC A,(HL)
+            let n = read(address + 1);
+            format!("CE {:02X}         ADC A,${:02X}", n, n)
+        }
 
-            // AND s
-            0xA0 => String::from("A0            AND B"), // AND B
-            0xA1 => String::from("A1            AND C"), // AND C
-            0xA2 => String::from("A2            AND D"), // AND D
-            0xA3 => String::from("A3            AND E"), // AND E
-            0xA4 => String::from("A4            AND H"), // AND H
-            0xA5 => String::from("A5            AND L"), // AND L
-            0xA6 => String::from("A6            AND (HL)"), // AND (HL)
-            0xA7 => String::from("A7            AND L"), // AND A
-
-            0xE6 => {
-                // AND n
-                let n = self.read_byte(address + 1);
-                format!("E6 {:02X}         AND ${:02X}", n, n)
-            }
+        // SUB s
+        0x90 => String::from("90            SUB A,B"), // SUB A,B
+        0x91 => String::from("91            SUB A,C"), // SUB A,C
+        0x92 => String::from("92            SUB A,D"), // SUB A,D
+        0x93 => String::from("93            SUB A,E"), // SUB A,E
+        0x94 => String::from("94            SUB A,H"), // SUB A,H
+        0x95 => String::from("95            SUB A,L"), // SUB A,L
+        0x96 => String::from("96            SUB A,(HL)"), // SUB A,(HL)
+        0x97 => String::from("97            SUB A,A"), // SUB A,A
+
+        0xD6 => {
+            // SUB A,n
+            let n = read(address + 1);
+            format!("D6 {:02X}         SUB A,${:02X}", n, n)
+        }
 
-            // OR s
-            0xB0 => String::from("B0            OR B"), // OR B
-            0xB1 => String::from("B1            OR C"), // OR C
-            0xB2 => String::from("B2            OR D"), // OR D
-            0xB3 => String::from("B3            OR E"), // OR E
-            0xB4 => String::from("B4            OR H"), // OR H
-            0xB5 => String::from("B5            OR L"), // OR L
-            0xB6 => String::from("B6            OR (HL)"), // OR (HL)
-            0xB7 => String::from("B7            OR A"), // OR A
-
-            0xF6 => {
-                // OR n
-                let n = self.read_byte(address + 1);
-                format!("F6 {:02X}         OR ${:02X}", n, n)
-            }
+        // SBC A,s
+        0x98 => String::from("98            SBC A,B"), // SBC A,B
+        0x99 => String::from("99            SBC A,C"), // SBC A,C
+        0x9A => String::from("9A            SBC A,D"), // SBC A,D
+        0x9B => String::from("9B            SBC A,E"), // SBC A,E
+        0x9C => String::from("9C            SBC A,H"), // SBC A,H
+        0x9D => String::from("9D            SBC A,L"), // SBC A,L
+        0x9E => String::from("9E            SBC A,(HL)"), // SBC A,(HL)
+        0x9F => String::from("9F            SBC A,A"), // SBC A,A
+
+        0xDE => {
+            // SBC A,n
+            let n = read(address + 1);
+            format!("DE {:02X}         SBC A,${:02X}", n, n)
+        }
 
-            // XOR s
-            0xA8 => String::from("A8            XOR B"), // XOR B
-            0xA9 => String::from("A9            XOR C"), // XOR C
-            0xAA => String::from("AA            XOR D"), // XOR D
-            0xAB => String::from("AB            XOR E"), // XOR E
-            0xAC => String::from("AC            XOR H"), // XOR H
-            0xAD => String::from("AD            XOR L"), // XOR L
-            0xAE => String::from("AE            XOR (HL)"), // XOR (HL)
-            0xAF => String::from("AF            XOR A"), // XOR A
-
-            0xEE => {
-                // XOR n
-                let n = self.read_byte(address + 1);
-                format!("EE {:02X}         XOR ${:02X}", n, n)
-            }
+        // AND s
+        0xA0 => String::from("A0            AND B"), // AND B
+        0xA1 => String::from("A1            AND C"), // AND C
+        0xA2 => String::from("A2            AND D"), // AND D
+        0xA3 => String::from("A3            AND E"), // AND E
+        0xA4 => String::from("A4            AND H"), // AND H
+        0xA5 => String::from("A5            AND L"), // AND L
+        0xA6 => String::from("A6            AND (HL)"), // AND (HL)
+        0xA7 => String::from("A7            AND L"), // AND A
+
+        0xE6 => {
+            // AND n
+            let n = read(address + 1);
+            format!("E6 {:02X}         AND ${:02X}", n, n)
+        }
 
-            // CMP s
-            0xB8 => String::from("B8            CP B"), // CP B
-            0xB9 => String::from("B9            CP C"), // CP C
-            0xBA => String::from("BA            CP D"), // CP D
-            0xBB => String::from("BB            CP E"), // CP E
-            0xBC => String::from("BC            CP H"), // CP H
-            0xBD => String::from("BD            CP L"), // CP L
-            0xBE => String::from("BE            CP (HL)"), // CP (HL)
-            0xBF => String::from("BF            CP A"), // CP A
-
-            0xFE => {
-                // CP n
-                let n = self.read_byte(address + 1);
-                format!("FE {:02X}         CP ${:02X}", n, n)
-            }
+        // OR s
+        0xB0 => String::from("B0            OR B"), // OR B
+        0xB1 => String::from("B1            OR C"), // OR C
+        0xB2 => String::from("B2            OR D"), // OR D
+        0xB3 => String::from("B3            OR E"), // OR E
+        0xB4 => String::from("B4            OR H"), // OR H
+        0xB5 => String::from("B5            OR L"), // OR L
+        0xB6 => String::from("B6            OR (HL)"), // OR (HL)
+        0xB7 => String::from("B7            OR A"), // OR A
+
+        0xF6 => {
+            // OR n
+            let n = read(address + 1);
+            format!("F6 {:02X}         OR ${:02X}", n, n)
+        }
 
-            // INC r
-            0x04 => String::from("04            INC B"), // INC B
-            0x0C => String::from("0C            INC C"), // INC C
-            0x14 => String::from("14            INC D"), // INC D
-            0x1C => String::from("1C            INC E"), // INC E
-            0x24 => String::from("24            INC H"), // INC H
-            0x2C => String::from("2C            INC L"), // INC L
-            0x34 => String::from("34            INC (HL)"), // INC (HL)
-            0x3C => String::from("3C            INC A"), // INC A
-
-            // DEC m
-            0x05 => String::from("05            DEC B"), // DEC B
-            0x0D => String::from("0D            DEC C"), // DEC C
-            0x15 => String::from("15            DEC D"), // DEC D
-            0x1D => String::from("1D            DEC E"), // DEC E
-            0x25 => String::from("25            DEC H"), // DEC H
-            0x2D => String::from("2D            DEC L"), // DEC L
-            0x35 => String::from("35            DEC (HL)"), // DEC (HL)
-            0x3D => String::from("3D            DEC A"), // DEC A
-
-            // General-Purpose Arithmetic and CPU Control Groups
-            // DAA
-            0x27 => String::from("27            DAA"),
-
-            // CPL
-            0x2F => String::from("2F            CPL"),
-
-            // CCF
-            0x3F => String::from("3F            CCF"),
-
-            // SCF
-            0x37 => String::from("37            SCF"),
-
-            // NOP
-            0x00 => String::from("00            NOP"),
-
-            // HALT
-            0x76 => String::from("76            HALT"),
-
-            // DI
-            0xF3 => String::from("F3            DI"),
-
-            // EI
-            0xFB => String::from("FB            EI"),
-
-            // 16-Bit Arithmetic Group
-            // ADD HL,ss
-            0x09 => String::from("09            ADD HL,BC"), // ADD HL,BC
-            0x19 => String::from("19            ADD HL,DE"), // ADD HL,DE
-            0x29 => String::from("29            ADD HL,HL"), // ADD HL,HL
-            0x39 => String::from("39            ADD HL,SP"), // ADD HL,SP
-
-            // INC ss
-            0x03 => String::from("03            INC BC"), // INC BC
-            0x13 => String::from("13            INC DE"), // INC DE
-            0x23 => String::from("23            INC HL"), // INC HL
-            0x33 => String::from("33            INC SP"), // INC SP
-
-            // DEC ss
-            0x0B => String::from("0B            DEC BC"), // DEC BC
-            0x1B => String::from("1B            DEC DE"), // DEC DE
-            0x2B => String::from("2B            DEC HL"), // DEC HL
-            0x3B => String::from("3B            DEC SP"), // DEC SP
-
-            // Rotate and Shift Group
-            // RLCA
-            0x07 => String::from("07            RLCA"),
-
-            // RLA
-            0x17 => String::from("17            RLA"),
-
-            // RRCA
-            0x0F => String::from("0F            RRCA"),
-
-            // RRA
-            0x1F => String::from("1F            RRA"),
-
-            // Jump group
-            // JP nn
-            0xC3 => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "C3 {:02X} {:02X}      JP ${:04X}",
-                    addr_low, addr_high, addr
-                )
-            }
+        // XOR s
+        0xA8 => String::from("A8            XOR B"), // XOR B
+        0xA9 => String::from("A9            XOR C"), // XOR C
+        0xAA => String::from("AA            XOR D"), // XOR D
+        0xAB => String::from("AB            XOR E"), // XOR E
+        0xAC => String::from("AC            XOR H"), // XOR H
+        0xAD => String::from("AD            XOR L"), // XOR L
+        0xAE => String::from("AE            XOR (HL)"), // XOR (HL)
+        0xAF => String::from("AF            XOR A"), // XOR A
+
+        0xEE => {
+            // XOR n
+            let n = read(address + 1);
+            format!("EE {:02X}         XOR ${:02X}", n, n)
+        }
 
-            // JP C,nn
-            0xDA => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "DA {:02X} {:02X}      JP C,${:04X}",
-                    addr_low, addr_high, addr
-                )
-            }
+        // CMP s
+        0xB8 => String::from("B8            CP B"), // CP B
+        0xB9 => String::from("B9            CP C"), // CP C
+        0xBA => String::from("BA            CP D"), // CP D
+        0xBB => String::from("BB            CP E"), // CP E
+        0xBC => String::from("BC            CP H"), // CP H
+        0xBD => String::from("BD            CP L"), // CP L
+        0xBE => String::from("BE            CP (HL)"), // CP (HL)
+        0xBF => String::from("BF            CP A"), // CP A
+
+        0xFE => {
+            // CP n
+            let n = read(address + 1);
+            format!("FE {:02X}         CP ${:02X}", n, n)
+        }
 
-            // JP NC,nn
-            0xD2 => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "D2 {:02X} {:02X}      JP NC,${:04X}",
-                    addr_low, addr_high, addr
-                )
-            }
+        // INC r
+        0x04 => String::from("04            INC B"), // INC B
+        0x0C => String::from("0C            INC C"), // INC C
+        0x14 => String::from("14            INC D"), // INC D
+        0x1C => String::from("1C            INC E"), // INC E
+        0x24 => String::from("24            INC H"), // INC H
+        0x2C => String::from("2C            INC L"), // INC L
+        0x34 => String::from("34            INC (HL)"), // INC (HL)
+        0x3C => String::from("3C            INC A"), // INC A
+
+        // DEC m
+        0x05 => String::from("05            DEC B"), // DEC B
+        0x0D => String::from("0D            DEC C"), // DEC C
+        0x15 => String::from("15            DEC D"), // DEC D
+        0x1D => String::from("1D            DEC E"), // DEC E
+        0x25 => String::from("25            DEC H"), // DEC H
+        0x2D => String::from("2D            DEC L"), // DEC L
+        0x35 => String::from("35            DEC (HL)"), // DEC (HL)
+        0x3D => String::from("3D            DEC A"), // DEC A
+
+        // General-Purpose Arithmetic and CPU Control Groups
+        // DAA
+        0x27 => String::from("27            DAA"),
+
+        // CPL
+        0x2F => String::from("2F            CPL"),
+
+        // CCF
+        0x3F => String::from("3F            CCF"),
+
+        // SCF
+        0x37 => String::from("37            SCF"),
+
+        // NOP
+        0x00 => String::from("00            NOP"),
+
+        // HALT
+        0x76 => String::from("76            HALT"),
+
+        // DI
+        0xF3 => String::from("F3            DI"),
+
+        // EI
+        0xFB => String::from("FB            EI"),
+
+        // 16-Bit Arithmetic Group
+        // ADD HL,ss
+        0x09 => String::from("09            ADD HL,BC"), // ADD HL,BC
+        0x19 => String::from("19            ADD HL,DE"), // ADD HL,DE
+        0x29 => String::from("29            ADD HL,HL"), // ADD HL,HL
+        0x39 => String::from("39            ADD HL,SP"), // ADD HL,SP
+
+        // INC ss
+        0x03 => String::from("03            INC BC"), // INC BC
+        0x13 => String::from("13            INC DE"), // INC DE
+        0x23 => String::from("23            INC HL"), // INC HL
+        0x33 => String::from("33            INC SP"), // INC SP
+
+        // DEC ss
+        0x0B => String::from("0B            DEC BC"), // DEC BC
+        0x1B => String::from("1B            DEC DE"), // DEC DE
+        0x2B => String::from("2B            DEC HL"), // DEC HL
+        0x3B => String::from("3B            DEC SP"), // DEC SP
+
+        // Rotate and Shift Group
+        // RLCA
+        0x07 => String::from("07            RLCA"),
+
+        // RLA
+        0x17 => String::from("17            RLA"),
+
+        // RRCA
+        0x0F => String::from("0F            RRCA"),
+
+        // RRA
+        0x1F => String::from("1F            RRA"),
+
+        // Jump group
+        // JP nn
+        0xC3 => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "C3 {:02X} {:02X}      JP ${:04X}",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // JP Z,nn
-            0xCA => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "CA {:02X} {:02X}      JP Z,${:04X}",
-                    addr_low, addr_high, addr
-                )
-            }
+        // JP C,nn
+        0xDA => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "DA {:02X} {:02X}      JP C,${:04X}",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // JP NZ,nn
-            0xC2 => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "C2 {:02X} {:02X}      JP NZ,${:04X}",
-                    addr_low, addr_high, addr
-                )
-            }
+        // JP NC,nn
+        0xD2 => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "D2 {:02X} {:02X}      JP NC,${:04X}",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // JP M,nn
-            0xFA => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "FA {:02X} {:02X}      JP M,${:04X}",
-                    addr_low, addr_high, addr
-                )
-            }
+        // JP Z,nn
+        0xCA => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "CA {:02X} {:02X}      JP Z,${:04X}",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // JP P,nn
-            0xF2 => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "F2 {:02X} {:02X}      JP P,${:04X}",
-                    addr_low, addr_high, addr
-                )
-            }
+        // JP NZ,nn
+        0xC2 => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "C2 {:02X} {:02X}      JP NZ,${:04X}",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // JP PE,nn
-            0xEA => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "EA {:02X} {:02X}      JP PE,${:04X}",
-                    addr_low, addr_high, addr
-                )
-            }
+        // JP M,nn
+        0xFA => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "FA {:02X} {:02X}      JP M,${:04X}",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // JP PO,nn
-            0xE2 => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "E2 {:02X} {:02X}      JP PO,${:04X}",
-                    addr_low, addr_high, addr
-                )
-            }
+        // JP P,nn
+        0xF2 => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "F2 {:02X} {:02X}      JP P,${:04X}",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // JR e
-            0x18 => {
-                let displacement = self.read_byte(address + 1);
-                let addr = match bit::get(displacement, 7) {
-                    true => address + 2 - (signed_to_abs(displacement) as u16),
-                    false => address + 2 + (displacement as u16),
-                };
-                format!("18 {:02X}         JR ${:04X}", displacement, addr)
-            }
+        // JP PE,nn
+        0xEA => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "EA {:02X} {:02X}      JP PE,${:04X}",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // JR C,e
-            0x38 => {
-                let displacement = self.read_byte(address + 1);
-                let addr = match bit::get(displacement, 7) {
-                    true => address + 2 - (signed_to_abs(displacement) as u16),
-                    false => address + 2 + (displacement as u16),
-                };
-                format!("38 {:02X}         JR C,${:04X}", displacement, addr)
-            }
+        // JP PO,nn
+        0xE2 => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "E2 {:02X} {:02X}      JP PO,${:04X}",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // JR NC,e
-            0x30 => {
-                let displacement = self.read_byte(address + 1);
-                let addr = match bit::get(displacement, 7) {
-                    true => address + 2 - (signed_to_abs(displacement) as u16),
-                    false => address + 2 + (displacement as u16),
-                };
-                format!("30 {:02X}         JR NC,${:04X}", displacement, addr)
-            }
+        // JR e
+        0x18 => {
+            let displacement = read(address + 1);
+            let addr = match bit::get(displacement, 7) {
+                true => address + 2 - (signed_to_abs(displacement) as u16),
+                false => address + 2 + (displacement as u16),
+            };
+            format!("18 {:02X}         JR ${:04X}", displacement, addr)
+        }
 
-            // JR Z,e
-            0x28 => {
-                let displacement = self.read_byte(address + 1);
-                let addr = match bit::get(displacement, 7) {
-                    true => address + 2 - (signed_to_abs(displacement) as u16),
-                    false => address + 2 + (displacement as u16),
-                };
-                format!("28 {:02X}         JR Z,${:04X}", displacement, addr)
-            }
+        // JR C,e
+        0x38 => {
+            let displacement = read(address + 1);
+            let addr = match bit::get(displacement, 7) {
+                true => address + 2 - (signed_to_abs(displacement) as u16),
+                false => address + 2 + (displacement as u16),
+            };
+            format!("38 {:02X}         JR C,${:04X}", displacement, addr)
+        }
 
-            // JR NZ,e
-            0x20 => {
-                let displacement = self.read_byte(address + 1);
-                let addr = match bit::get(displacement, 7) {
-                    true => address + 2 - (signed_to_abs(displacement) as u16),
-                    false => address + 2 + (displacement as u16),
-                };
-                format!("20 {:02X}         JR NZ,${:04X}", displacement, addr)
-            }
+        // JR NC,e
+        0x30 => {
+            let displacement = read(address + 1);
+            let addr = match bit::get(displacement, 7) {
+                true => address + 2 - (signed_to_abs(displacement) as u16),
+                false => address + 2 + (displacement as u16),
+            };
+            format!("30 {:02X}         JR NC,${:04X}", displacement, addr)
+        }
 
-            // JP (HL)
-            0xE9 => {
-                format!("E9            JP (HL)")
-            }
+        // JR Z,e
+        0x28 => {
+            let displacement = read(address + 1);
+            let addr = match bit::get(displacement, 7) {
+                true => address + 2 - (signed_to_abs(displacement) as u16),
+                false => address + 2 + (displacement as u16),
+            };
+            format!("28 {:02X}         JR Z,${:04X}", displacement, addr)
+        }
 
-            // DJNZ, e
-            0x10 => {
-                let displacement = self.read_byte(address + 1);
-                let addr = match bit::get(displacement, 7) {
-                    true => address + 2 - (signed_to_abs(displacement) as u16),
-                    false => address + 2 + (displacement as u16),
-                };
-                format!("10 {:02X}         DJNZ ${:04X}", displacement, addr)
-            }
+        // JR NZ,e
+        0x20 => {
+            let displacement = read(address + 1);
+            let addr = match bit::get(displacement, 7) {
+                true => address + 2 - (signed_to_abs(displacement) as u16),
+                false => address + 2 + (displacement as u16),
+            };
+            format!("20 {:02X}         JR NZ,${:04X}", displacement, addr)
+        }
 
-            // Call and Return Group
-            // CALL nn
-            0xCD => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "CD {:02X} {:02X}      CALL ${:04X}",
-                    addr_low, addr_high, addr
-                )
-            }
+        // JP (HL)
+        0xE9 => String::from("E9            JP (HL)"),
+
+        // DJNZ, e
+        0x10 => {
+            let displacement = read(address + 1);
+            let addr = match bit::get(displacement, 7) {
+                true => address + 2 - (signed_to_abs(displacement) as u16),
+                false => address + 2 + (displacement as u16),
+            };
+            format!("10 {:02X}         DJNZ ${:04X}", displacement, addr)
+        }
 
-            // CALL C,nn
-            0xDC => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "DC {:02X} {:02X}      CALL C,${:04X}",
-                    addr_low, addr_high, addr
-                )
-            }
+        // Call and Return Group
+        // CALL nn
+        0xCD => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "CD {:02X} {:02X}      CALL ${:04X}",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // CALL NC,nn
-            0xD4 => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "D4 {:02X} {:02X}      CALL NC,${:04X}",
-                    addr_low, addr_high, addr
-                )
-            }
+        // CALL C,nn
+        0xDC => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "DC {:02X} {:02X}      CALL C,${:04X}",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // CALL Z,nn
-            0xCC => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "CC {:02X} {:02X}      CALL Z,${:04X}",
-                    addr_low, addr_high, addr
-                )
-            }
+        // CALL NC,nn
+        0xD4 => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "D4 {:02X} {:02X}      CALL NC,${:04X}",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // CALL NZ,nn
-            0xC4 => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "C4 {:02X} {:02X}      CALL NZ,${:04X}",
-                    addr_low, addr_high, addr
-                )
-            }
+        // CALL Z,nn
+        0xCC => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "CC {:02X} {:02X}      CALL Z,${:04X}",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // CALL M,nn
-            0xFC => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "FC {:02X} {:02X}      CALL M,${:04X}",
-                    addr_low, addr_high, addr
-                )
-            }
+        // CALL NZ,nn
+        0xC4 => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "C4 {:02X} {:02X}      CALL NZ,${:04X}",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // CALL P,nn
-            0xF4 => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "F4 {:02X} {:02X}      CALL P,${:04X}",
-                    addr_low, addr_high, addr
-                )
-            }
+        // CALL M,nn
+        0xFC => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "FC {:02X} {:02X}      CALL M,${:04X}",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // CALL PE,nn
-            0xEC => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "EC {:02X} {:02X}      CALL PE,${:04X}",
-                    addr_low, addr_high, addr
-                )
-            }
+        // CALL P,nn
+        0xF4 => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "F4 {:02X} {:02X}      CALL P,${:04X}",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // CALL PO,nn
-            0xE4 => {
-                let addr_low = self.read_byte(address + 1);
-                let addr_high = self.read_byte(address + 2);
-                let addr = self.read_word(address + 1);
-                format!(
-                    "E4 {:02X} {:02X}      CALL PO,${:04X}",
-                    addr_low, addr_high, addr
-                )
-            }
+        // CALL PE,nn
+        0xEC => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "EC {:02X} {:02X}      CALL PE,${:04X}",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // RET
-            0xC9 => String::from("C9            RET"),
+        // CALL PO,nn
+        0xE4 => {
+            let addr_low = read(address + 1);
+            let addr_high = read(address + 2);
+            let addr = read_word(&mut read, address + 1);
+            format!(
+                "E4 {:02X} {:02X}      CALL PO,${:04X}",
+                addr_low, addr_high, addr
+            )
+        }
 
-            // RET C
-            0xD8 => String::from("D8            RET C"),
+        // RET
+        0xC9 => String::from("C9            RET"),
 
-            // RET NC
-            0xD0 => String::from("D0            RET NC"),
+        // RET C
+        0xD8 => String::from("D8            RET C"),
 
-            // RET Z
-            0xC8 => String::from("C8            RET Z"),
+        // RET NC
+        0xD0 => String::from("D0            RET NC"),
 
-            // RET NZ
-            0xC0 => String::from("C0            RET NZ"),
+        // RET Z
+        0xC8 => String::from("C8            RET Z"),
 
-            // RET M
-            0xF8 => String::from("F8            RET M"),
+        // RET NZ
+        0xC0 => String::from("C0            RET NZ"),
 
-            // RET P
-            0xF0 => String::from("F0            RET P"),
+        // RET M
+        0xF8 => String::from("F8            RET M"),
 
-            // RET PE
-            0xE8 => String::from("E8            RET PE"),
+        // RET P
+        0xF0 => String::from("F0            RET P"),
 
-            // RET PO
-            0xE0 => String::from("E0            RET PO"),
+        // RET PE
+        0xE8 => String::from("E8            RET PE"),
 
-            // RST 0
-            0xC7 => String::from("C7            RST 0"),
+        // RET PO
+        0xE0 => String::from("E0            RET PO"),
 
-            // RST 08
-            0xCF => String::from("CF            RST 08"),
+        // RST 0
+        0xC7 => String::from("C7            RST 0"),
 
-            // RST 10
-            0xD7 => String::from("D7            RST 10"),
+        // RST 08
+        0xCF => String::from("CF            RST 08"),
 
-            // RST 18
-            0xDF => String::from("DF            RST 18"),
+        // RST 10
+        0xD7 => String::from("D7            RST 10"),
 
-            // RST 20
-            0xE7 => String::from("E7            RST 20"),
+        // RST 18
+        0xDF => String::from("DF            RST 18"),
 
-            // RST 28
-            0xEF => String::from("EF            RST 28"),
+        // RST 20
+        0xE7 => String::from("E7            RST 20"),
 
-            // RST 30
-            0xF7 => String::from("F7            RST 30"),
+        // RST 28
+        0xEF => String::from("EF            RST 28"),
 
-            // RST 38
-            0xFF => String::from("FF            RST 38"),
+        // RST 30
+        0xF7 => String::from("F7            RST 30"),
 
-            // Input and Output Group
-            // IN A,(n)
-            0xDB => {
-                let port = self.read_byte(address + 1);
-                format!("DB {:02X}         IN A,(${:02X})", port, port)
-            }
+        // RST 38
+        0xFF => String::from("FF            RST 38"),
 
-            // OUT (n),A
-            0xD3 => {
-                let port = self.read_byte(address + 1);
-                format!("D3 {:02X}         OUT A,(${:02X})", port, port)
-            }
+        // Input and Output Group
+        // IN A,(n)
+        0xDB => {
+            let port = read(address + 1);
+            format!("DB {:02X}         IN A,(${:02X})", port, port)
+        }
 
-            _ => String::from("Unknown opcode      "),
-        };
-        let instr_size = match opcode {
-            0xC3 | 0xDA | 0xD2 | 0xCA | 0xC2 | 0xFA | 0xF2 | 0xEA | 0xE2 | 0xCD | 0xDC | 0xD4
-            | 0xCC | 0xC4 | 0xFC | 0xF4 | 0xEC | 0xE4 => 3,
-            0x06 | 0x0E | 0x16 | 0x1E | 0x26 | 0x2E | 0x36 | 0x3E | 0xC6 | 0xCE | 0xD6 | 0xDE
-            | 0xE6 | 0xF6 | 0xEE | 0xFE | 0xDB | 0xD3 | 0x10 | 0x18 | 0x38 | 0x30 | 0x28 | 0x20
-            | 0xCB => 2,
-            0x32 | 0x01 | 0x11 | 0x21 | 0x31 | 0x2A | 0x22 | 0x3A => 3,
-            _ => 1,
-        };
-        let instr_size_16: u8 = match opcode_16 {
-            0xDD46 | 0xFD46 | 0xDD4E | 0xFD4E | 0xDD56 | 0xFD56 | 0xDD5E | 0xFD5E | 0xDD66
-            | 0xFD66 | 0xDD6E | 0xFD6E | 0xDD7E | 0xFD7E | 0xDD70 | 0xDD71 | 0xDD72 | 0xDD73
-            | 0xDD74 | 0xDD75 | 0xDD77 | 0xFD70 | 0xFD71 | 0xFD72 | 0xFD73 | 0xFD74 | 0xFD75
-            | 0xFD77 | 0xDD86 | 0xFD86 | 0xDD8E | 0xFD8E | 0xDD96 | 0xFD96 | 0xDD9E | 0xFD9E
-            | 0xDDA6 | 0xFDA6 | 0xDDB6 | 0xFDB6 | 0xDDAE | 0xFDAE | 0xDDBE | 0xFDBE | 0xDD34
-            | 0xFD34 | 0xDD35 | 0xFD35 => 3,
-            0xDD36 | 0xFD36 | 0xDD21 | 0xFD21 | 0xED4B | 0xED5B | 0xED6B | 0xED7B | 0xDD2A
-            | 0xFD2A | 0xED43 | 0xED53 | 0xED63 | 0xED73 | 0xDD22 | 0xFD22 | 0xDDCB | 0xFDCB => 4,
-            _ => 2,
-        };
-        if opcode_16 == 0 {
-            return (instr, instr_size);
-        } else {
-            return (instr, instr_size_16);
+        // OUT (n),A
+        0xD3 => {
+            let port = read(address + 1);
+            format!("D3 {:02X}         OUT (${:02X}),A", port, port)
         }
+    };
+    let instr_size = match opcode {
+        0xC3 | 0xDA | 0xD2 | 0xCA | 0xC2 | 0xFA | 0xF2 | 0xEA | 0xE2 | 0xCD | 0xDC | 0xD4
+        | 0xCC | 0xC4 | 0xFC | 0xF4 | 0xEC | 0xE4 => 3,
+        0x06 | 0x0E | 0x16 | 0x1E | 0x26 | 0x2E | 0x36 | 0x3E | 0xC6 | 0xCE | 0xD6 | 0xDE
+        | 0xE6 | 0xF6 | 0xEE | 0xFE | 0xDB | 0xD3 | 0x10 | 0x18 | 0x38 | 0x30 | 0x28 | 0x20
+        | 0xCB => 2,
+        0x32 | 0x01 | 0x11 | 0x21 | 0x31 | 0x2A | 0x22 | 0x3A => 3,
+        _ => 1,
+    };
+    let instr_size_16: u8 = match opcode_16 {
+        0xDD46 | 0xFD46 | 0xDD4E | 0xFD4E | 0xDD56 | 0xFD56 | 0xDD5E | 0xFD5E | 0xDD66 | 0xFD66
+        | 0xDD6E | 0xFD6E | 0xDD7E | 0xFD7E | 0xDD70 | 0xDD71 | 0xDD72 | 0xDD73 | 0xDD74
+        | 0xDD75 | 0xDD77 | 0xFD70 | 0xFD71 | 0xFD72 | 0xFD73 | 0xFD74 | 0xFD75 | 0xFD77
+        | 0xDD86 | 0xFD86 | 0xDD8E | 0xFD8E | 0xDD96 | 0xFD96 | 0xDD9E | 0xFD9E | 0xDDA6
+        | 0xFDA6 | 0xDDB6 | 0xFDB6 | 0xDDAE | 0xFDAE | 0xDDBE | 0xFDBE | 0xDD34 | 0xFD34
+        | 0xDD35 | 0xFD35 | 0xDD26 | 0xFD26 | 0xDD2E | 0xFD2E => 3,
+        0xDD36 | 0xFD36 | 0xDD21 | 0xFD21 | 0xED4B | 0xED5B | 0xED6B | 0xED7B | 0xDD2A | 0xFD2A
+        | 0xED43 | 0xED53 | 0xED63 | 0xED73 | 0xDD22 | 0xFD22 | 0xDDCB | 0xFDCB => 4,
+        _ => 2,
+    };
+    if opcode_16 == 0 {
+        (instr, instr_size)
+    } else {
+        (instr, instr_size_16)
     }
 }
+
+// Reads a word stored in little endian byte order
+fn read_word<F: FnMut(u16) -> u8>(read: &mut F, address: u16) -> u16 {
+    u16::from(read(address)) | (u16::from(read(address.wrapping_add(1))) << 8)
+}
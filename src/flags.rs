@@ -2,9 +2,9 @@
 pub struct Flags {
     pub s: bool,  // sign                 : bit 7
     pub z: bool,  // zero                 : bit 6
-    pub b5: bool, // unused
+    pub b5: bool, // undocumented Y       : bit 5
     pub h: bool,  // auxiliary carry      : bit 4
-    pub b3: bool, // unused
+    pub b3: bool, // undocumented X       : bit 3
     pub p: bool,  // parity / overflow    : bit 2
     pub n: bool,  // substract            : bit 1
     pub c: bool,  // carry                : bit 0
@@ -0,0 +1,74 @@
+/// A device answering the Z80 port I/O cycles (IN / OUT instructions).
+///
+/// The address passed to the device is the full 16-bit value the Z80 puts on the address bus:
+/// for `IN A,(n)` and `OUT (n),A` the port number n is on the lower half and the A register on the upper half.
+/// ```rust
+/// use zilog_z80::{bus::Bus, io::IoDevice};
+///
+/// struct Uart {
+///     output: Vec<u8>,
+/// }
+///
+/// impl IoDevice for Uart {
+///     fn read(&mut self, _address: u16) -> u8 {
+///         0x00
+///     }
+///
+///     fn write(&mut self, address: u16, data: u8) {
+///         if address & 0x00FF == 0x01 {
+///             self.output.push(data)
+///         }
+///     }
+/// }
+///
+/// let mut b = Bus::new(0xFFFF);
+/// b.set_io_device(Box::new(Uart { output: Vec::new() }));
+/// ```
+pub trait IoDevice: Send {
+    /// Called when the CPU reads a port. Returns the byte put on the data bus by the device.
+    fn read(&mut self, address: u16) -> u8;
+
+    /// Called when the CPU writes a byte to a port.
+    fn write(&mut self, address: u16, data: u8);
+}
+
+/// A device mapped into the memory space (video RAM, keyboard matrix, UART registers...), see `Bus::add_region`.
+///
+/// The address passed to the device is the full 16-bit address. The CPU reads the device with `read`,
+/// which can have side effects, while `Bus::read_byte` (used by debuggers and the disassembler) only peeks at it.
+/// ```rust
+/// use zilog_z80::{bus::{Bus, Region}, io::MemoryDevice};
+///
+/// struct Keyboard {
+///     key: u8,
+/// }
+///
+/// impl MemoryDevice for Keyboard {
+///     fn read(&mut self, _address: u16) -> u8 {
+///         // The key is consumed when read by the CPU
+///         std::mem::take(&mut self.key)
+///     }
+///
+///     fn peek(&self, _address: u16) -> u8 {
+///         self.key
+///     }
+///
+///     fn write(&mut self, _address: u16, _data: u8) {}
+/// }
+///
+/// let mut b = Bus::new(0xFFFF);
+/// b.add_region(0x3800, 0x3BFF, Region::Device(Box::new(Keyboard { key: 0x41 })));
+/// assert_eq!(b.read_byte(0x3800), 0x41);
+/// ```
+pub trait MemoryDevice: Send {
+    /// Called when the CPU reads an address of the device. Returns the byte put on the data bus. Defaults to `peek`.
+    fn read(&mut self, address: u16) -> u8 {
+        self.peek(address)
+    }
+
+    /// Returns the byte at an address of the device, without side effects.
+    fn peek(&self, address: u16) -> u8;
+
+    /// Called when a byte is written to an address of the device.
+    fn write(&mut self, address: u16, data: u8);
+}
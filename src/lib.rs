@@ -2,7 +2,7 @@
 //!
 //! Example for a small loop:
 //! ```rust
-//! use zilog_z80::{cpu::CPU, bus::Bus};
+//! use zilog_z80::{cpu::{CPU, StopReason}, bus::Bus};
 //! let mut b = Bus::new(0xFFFF);
 //! let mut c = CPU::new();
 //! c.reg.pc = 0x0100;                  // sets pc to 0x0100
@@ -14,19 +14,23 @@
 //! b.write_byte(0x0103, 0xc2);     // JP NZ,0x0102
 //! b.write_word(0x0104, 0x0102);
 //! b.write_byte(0x0106, 0xc9);     // RET
-//! loop {
-//!     c.execute(&mut b);
-//!     if c.reg.pc == 0x0000 { break }
-//! }
+//! // Runs until the RET instruction jumps to 0x0000
+//! let reason = c.run_until(&mut b, u64::MAX, |c, _| c.reg.pc == 0x0000);
+//! assert_eq!(reason, StopReason::Breakpoint);
 //! ```
 
 mod bit;
 pub mod bus;
+pub mod clock;
 pub mod cpu;
 mod cycles;
+pub mod daisy_chain;
 pub mod dasm;
 mod flags;
+pub mod io;
+pub mod memory;
 pub mod registers;
+pub mod scheduler;
 
 #[cfg(test)]
 mod test;
@@ -0,0 +1,70 @@
+/// The memory and I/O bus the CPU executes code from. `Bus` is the default implementation.
+///
+/// Implementing this trait lets the CPU run against a custom memory map (banking, mirrored RAM, memory-mapped hardware...).
+/// The CPU is generic over its memory : the accesses are statically dispatched.
+/// ```rust
+/// use zilog_z80::{cpu::CPU, memory::Memory};
+///
+/// // 1 KB of RAM, mirrored over the whole address space
+/// struct Mirrored {
+///     ram: [u8; 0x400],
+/// }
+///
+/// impl Memory for Mirrored {
+///     fn read_byte(&mut self, address: u16) -> u8 {
+///         self.ram[address as usize & 0x3FF]
+///     }
+///
+///     fn write_byte(&mut self, address: u16, data: u8) {
+///         self.ram[address as usize & 0x3FF] = data;
+///     }
+/// }
+///
+/// let mut m = Mirrored { ram: [0; 0x400] };
+/// let mut c = CPU::<Mirrored>::default();
+/// m.write_byte(0x0000, 0x3E);         // LD A,$12
+/// m.write_byte(0x0001, 0x12);
+/// m.write_byte(0x0002, 0x32);         // LD ($8400),A
+/// m.write_word(0x0003, 0x8400);
+/// c.execute(&mut m);
+/// c.execute(&mut m);
+/// assert_eq!(m.read_byte(0x0000), 0x12);
+/// ```
+pub trait Memory {
+    /// Reads a byte from memory
+    fn read_byte(&mut self, address: u16) -> u8;
+
+    /// Writes a byte to memory
+    fn write_byte(&mut self, address: u16, data: u8);
+
+    /// Reads an opcode byte during an opcode fetch (M1 cycle). Defaults to a memory read.
+    fn fetch_opcode(&mut self, address: u16) -> u8 {
+        self.read_byte(address)
+    }
+
+    /// Reads a word stored in memory in little endian byte order
+    fn read_word(&mut self, address: u16) -> u16 {
+        u16::from(self.read_byte(address))
+            | (u16::from(self.read_byte(address.wrapping_add(1))) << 8)
+    }
+
+    /// Writes a word to memory in little endian byte order
+    fn write_word(&mut self, address: u16, data: u16) {
+        self.write_byte(address, (data & 0xFF) as u8);
+        self.write_byte(address.wrapping_add(1), (data >> 8) as u8);
+    }
+
+    /// Reads a byte from an I/O port. Defaults to 0xFF (floating data bus).
+    fn io_read(&mut self, _address: u16) -> u8 {
+        0xFF
+    }
+
+    /// Writes a byte to an I/O port. Ignored by default.
+    fn io_write(&mut self, _address: u16, _data: u8) {}
+
+    /// Returns the wait states inserted by the memory and I/O accesses since the last call, added to the cycles of the instruction.
+    /// Called once per executed instruction. Defaults to 0.
+    fn wait_states(&mut self) -> u32 {
+        0
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{bus::Bus, cpu::CPU};
+
+/// Identifies a scheduled event
+pub type EventId = u64;
+
+/// Function called when a scheduled event is due. It can request interrupts from the CPU and access the bus.
+pub type EventCallback<M = Bus> = Box<dyn FnMut(&mut CPU<M>, &mut M) + Send>;
+
+pub(crate) struct Event<M> {
+    pub id: EventId,
+    // Cycle counter value at which the event is due
+    pub due: u64,
+    pub period: Option<u64>,
+    pub callback: EventCallback<M>,
+}
+
+/// Events waiting for the CPU cycle counter to reach their due time
+pub(crate) struct Scheduler<M> {
+    events: Vec<Event<M>>,
+    next_id: EventId,
+    // Events being fired, and whether their callback cancelled them
+    firing: Vec<(EventId, bool)>,
+}
+
+impl<M> Default for Scheduler<M> {
+    fn default() -> Self {
+        Scheduler {
+            events: Vec::new(),
+            next_id: 0,
+            firing: Vec::new(),
+        }
+    }
+}
+
+impl<M> Scheduler<M> {
+    pub fn add(&mut self, due: u64, period: Option<u64>, callback: EventCallback<M>) -> EventId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.events.push(Event {
+            id,
+            due,
+            period,
+            callback,
+        });
+        id
+    }
+
+    // Returns false if the event does not exist (or was a single event already fired)
+    pub fn cancel(&mut self, id: EventId) -> bool {
+        if let Some((_, cancelled)) = self.firing.iter_mut().find(|(f, _)| *f == id) {
+            *cancelled = true;
+            return true;
+        }
+        let len = self.events.len();
+        self.events.retain(|e| e.id != id);
+        self.events.len() != len
+    }
+
+    pub fn next_due(&self) -> Option<u64> {
+        self.events.iter().map(|e| e.due).min()
+    }
+
+    // Removes and returns the earliest event due at or before the cycle counter value
+    pub fn take_due(&mut self, cycles: u64) -> Option<Event<M>> {
+        let (index, _) = self
+            .events
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.due <= cycles)
+            .min_by_key(|(_, e)| (e.due, e.id))?;
+        let event = self.events.remove(index);
+        self.firing.push((event.id, false));
+        Some(event)
+    }
+
+    // Called once the callback of a taken event has returned : periodic events are scheduled again, one period after their due time
+    pub fn fired(&mut self, mut event: Event<M>) {
+        let cancelled = matches!(self.firing.pop(), Some((_, true)));
+        if let Some(period) = event.period
+            && !cancelled
+        {
+            event.due += period;
+            self.events.push(event);
+        }
+    }
+}
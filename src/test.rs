@@ -1,4 +1,16 @@
-use crate::{bus::Bus, cpu::CPU};
+use crate::{
+    bus::{Bus, Region},
+    clock::VirtualClock,
+    cpu::{
+        CPU, ExecError, Prefix, StopReason, UndefinedRegisters, UnknownOpcode, UnknownOpcodePolicy,
+    },
+    io::{IoDevice, MemoryDevice},
+    memory::Memory,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 // carry flag
 const CF: u8 = 1 << 0;
@@ -13,13 +25,13 @@ const VF: u8 = 1 << 2;
 const PF: u8 = 1 << 2;
 
 // undocumented 'X' flag
-// const XF: u8 = 1 << 3;
+const XF: u8 = 1 << 3;
 
 // half carry flag
 const HF: u8 = 1 << 4;
 
 // undocumented 'Y' flag
-// const YF: u8 = 1 << 5;
+const YF: u8 = 1 << 5;
 
 // zero flag
 const ZF: u8 = 1 << 6;
@@ -366,12 +378,12 @@ fn add_r_asm() {
     assert_eq!(c.flags(), 0); // LD A,0x0F
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x1E, c.reg.a);
-    assert_eq!(c.flags(), HF); // ADD A,A
+    assert_eq!(c.flags(), HF | XF); // ADD A,A
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xE0, c.reg.b); // LD B,0xE0
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0xFE, c.reg.a);
-    assert_eq!(c.flags(), SF); // ADD A,B
+    assert_eq!(c.flags(), SF | YF | XF); // ADD A,B
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0x81, c.reg.a); // LD A,0x81
     assert_eq!(c.execute(&mut b), 7);
@@ -398,10 +410,10 @@ fn add_r_asm() {
     assert_eq!(0x33, c.reg.l); // LD L,0x33
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0xF3, c.reg.a);
-    assert_eq!(c.flags(), SF); // ADD A,L
+    assert_eq!(c.flags(), SF | YF); // ADD A,L
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0x37, c.reg.a);
-    assert_eq!(c.flags(), CF); // ADD A,0x44
+    assert_eq!(c.flags(), YF | CF); // ADD A,0x44
 }
 
 #[test]
@@ -425,10 +437,10 @@ fn add_i_hl_ix_iy_asm() {
     assert_eq!(c.flags(), 0); // ADD A,(HL)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0xA2, c.reg.a);
-    assert_eq!(c.flags(), SF | VF); // ADD A,(IX+1)
+    assert_eq!(c.flags(), SF | YF | VF); // ADD A,(IX+1)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0x23, c.reg.a);
-    assert_eq!(c.flags(), VF | CF); // ADD A,(IY-1)
+    assert_eq!(c.flags(), YF | VF | CF); // ADD A,(IY-1)
 }
 
 #[test]
@@ -441,12 +453,12 @@ fn add_ixh_ixl_asm() {
     assert_eq!(c.flags(), 0); // LD A,0x0F
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x1E, c.reg.a);
-    assert_eq!(c.flags(), HF); // ADD A,A
+    assert_eq!(c.flags(), HF | XF); // ADD A,A
     assert_eq!(c.execute(&mut b), 14);
     assert_eq!(0xE080, c.reg.get_ix(),); // LD  IX,0xE080
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xFE, c.reg.a);
-    assert_eq!(c.flags(), SF); // ADD A,IXH
+    assert_eq!(c.flags(), SF | YF | XF); // ADD A,IXH
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0x81, c.reg.a); // LD  A,0x81
     assert_eq!(c.execute(&mut b), 8);
@@ -464,12 +476,12 @@ fn add_a_iyh_iyl_asm() {
     assert_eq!(c.flags(), 0); // LD A,0x0F
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x1E, c.reg.a);
-    assert_eq!(c.flags(), HF); // ADD A,A
+    assert_eq!(c.flags(), HF | XF); // ADD A,A
     assert_eq!(c.execute(&mut b), 14);
     assert_eq!(0xE080, c.reg.get_iy()); // LD  IY,0xE080
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xFE, c.reg.a);
-    assert_eq!(c.flags(), SF); // ADD A,IYH
+    assert_eq!(c.flags(), SF | YF | XF); // ADD A,IYH
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0x81, c.reg.a); // LD  A,0x81
     assert_eq!(c.execute(&mut b), 8);
@@ -494,7 +506,7 @@ fn adc_a_ixh_ixl_asm() {
     assert_eq!(c.flags(), 0); // ADC A,IXH
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xA2, c.reg.a);
-    assert_eq!(c.flags(), SF | VF); // ADC A,IXL
+    assert_eq!(c.flags(), SF | YF | VF); // ADC A,IXL
 }
 
 #[test]
@@ -514,7 +526,7 @@ fn adc_a_iyh_iyl_asm() {
     assert_eq!(c.flags(), 0); // ADC A,IYH
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xA2, c.reg.a);
-    assert_eq!(c.flags(), SF | VF); // ADC A,IYL
+    assert_eq!(c.flags(), SF | YF | VF); // ADC A,IYL
 }
 
 #[test]
@@ -544,13 +556,13 @@ fn adc_r_asm() {
     assert_eq!(c.flags(), 0); // ADC A,B
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0xA2, c.reg.a);
-    assert_eq!(c.flags(), SF | VF); // ADC A,C
+    assert_eq!(c.flags(), SF | YF | VF); // ADC A,C
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x23, c.reg.a);
-    assert_eq!(c.flags(), VF | CF); // ADC A,D
+    assert_eq!(c.flags(), YF | VF | CF); // ADC A,D
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x65, c.reg.a);
-    assert_eq!(c.flags(), 0); // ADC A,E
+    assert_eq!(c.flags(), YF); // ADC A,E
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0xC6, c.reg.a);
     assert_eq!(c.flags(), SF | VF); // ADC A,H
@@ -559,7 +571,7 @@ fn adc_r_asm() {
     assert_eq!(c.flags(), VF | CF); // ADC A,L
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0x49, c.reg.a);
-    assert_eq!(c.flags(), 0); // ADC A,0x01
+    assert_eq!(c.flags(), XF); // ADC A,0x01
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0x0F, c.reg.a); // LD A,0x0F
     assert_eq!(c.execute(&mut b), 7);
@@ -591,13 +603,13 @@ fn adc_i_hl_ix_iy_asm() {
     assert_eq!(c.flags(), 0); // ADD A,(HL)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0xA2, c.reg.a);
-    assert_eq!(c.flags(), SF | VF); // ADC A,(IX+1)
+    assert_eq!(c.flags(), SF | YF | VF); // ADC A,(IX+1)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0x23, c.reg.a);
-    assert_eq!(c.flags(), VF | CF); // ADC A,(IY-1)
+    assert_eq!(c.flags(), YF | VF | CF); // ADC A,(IY-1)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0x26, c.reg.a);
-    assert_eq!(c.flags(), 0); // ADC A,(IX+3)
+    assert_eq!(c.flags(), YF); // ADC A,(IX+3)
 }
 
 #[test]
@@ -624,25 +636,25 @@ fn sub_r_asm() {
     assert_eq!(c.flags(), ZF | NF); // SUB A,A
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0xFF, c.reg.a);
-    assert_eq!(c.flags(), SF | HF | NF | CF); // SUB A,B
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF | CF); // SUB A,B
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x07, c.reg.a);
     assert_eq!(c.flags(), NF); // SUB A,C
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0xF8, c.reg.a);
-    assert_eq!(c.flags(), SF | HF | NF | CF); // SUB A,D
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF | CF); // SUB A,D
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x7F, c.reg.a);
-    assert_eq!(c.flags(), HF | VF | NF); // SUB A,E
+    assert_eq!(c.flags(), YF | HF | XF | VF | NF); // SUB A,E
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0xBF, c.reg.a);
-    assert_eq!(c.flags(), SF | VF | NF | CF); // SUB A,H
+    assert_eq!(c.flags(), SF | YF | XF | VF | NF | CF); // SUB A,H
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x00, c.reg.a);
     assert_eq!(c.flags(), ZF | NF); // SUB A,L
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xFF, c.reg.a);
-    assert_eq!(c.flags(), SF | HF | NF | CF); // SUB A,0x01
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF | CF); // SUB A,0x01
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0x01, c.reg.a);
     assert_eq!(c.flags(), NF); // SUB A,0xFE
@@ -662,7 +674,7 @@ fn sub_ixh_ixl_asm() {
     assert_eq!(c.flags(), ZF | NF); // SUB A,A
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xFF, c.reg.a);
-    assert_eq!(c.flags(), SF | HF | NF | CF); // SUB A,IXH
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF | CF); // SUB A,IXH
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x07, c.reg.a);
     assert_eq!(c.flags(), NF); // SUB A,IXL
@@ -682,7 +694,7 @@ fn sub_iyh_iyl_asm() {
     assert_eq!(c.flags(), ZF | NF); // SUB A,A
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xFF, c.reg.a);
-    assert_eq!(c.flags(), SF | HF | NF | CF); // SUB A,IXH
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF | CF); // SUB A,IXH
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x07, c.reg.a);
     assert_eq!(c.flags(), NF); // SUB A,IXL
@@ -718,16 +730,16 @@ fn cp_r_asm() {
     assert_eq!(c.flags(), NF); // CP C
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x04, c.reg.a);
-    assert_eq!(c.flags(), HF | NF | CF); // CP D
+    assert_eq!(c.flags(), YF | HF | XF | NF | CF); // CP D
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x04, c.reg.a);
-    assert_eq!(c.flags(), HF | NF | CF); // CP E
+    assert_eq!(c.flags(), YF | HF | XF | NF | CF); // CP E
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x04, c.reg.a);
     assert_eq!(c.flags(), SF | VF | NF | CF); // CP H
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x04, c.reg.a);
-    assert_eq!(c.flags(), SF | HF | NF | CF); // CP L
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF | CF); // CP L
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0x04, c.reg.a);
     assert_eq!(c.flags(), ZF | NF); // CP 0x04
@@ -751,13 +763,13 @@ fn sub_i_hl_ix_iy_asm() {
     assert_eq!(0x00, c.reg.a); // LD A,0x00
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xBF, c.reg.a);
-    assert_eq!(c.flags(), SF | HF | NF | CF); // SUB A,(HL)
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF | CF); // SUB A,(HL)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0x5E, c.reg.a);
-    assert_eq!(c.flags(), VF | NF); // SUB A,(IX+1)
+    assert_eq!(c.flags(), XF | VF | NF); // SUB A,(IX+1)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0xFD, c.reg.a);
-    assert_eq!(c.flags(), SF | NF | CF); // SUB A,(IY-2)
+    assert_eq!(c.flags(), SF | YF | XF | NF | CF); // SUB A,(IY-2)
 }
 
 #[test]
@@ -781,10 +793,10 @@ fn cp_i_hl_ix_iy_asm() {
     assert_eq!(c.flags(), ZF | NF); // CP (HL)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0x41, c.reg.a);
-    assert_eq!(c.flags(), SF | NF | CF); // CP (IX+1)
+    assert_eq!(c.flags(), SF | YF | NF | CF); // CP (IX+1)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0x41, c.reg.a);
-    assert_eq!(c.flags(), HF | NF); // CP (IY-1)
+    assert_eq!(c.flags(), YF | HF | NF); // CP (IY-1)
 }
 
 #[test]
@@ -807,28 +819,28 @@ fn sbc_r_asm() {
     assert_eq!(c.flags(), ZF | NF); // SUB A,A
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0xFF, c.reg.a);
-    assert_eq!(c.flags(), SF | HF | NF | CF); // SBC A,B (0x00 - 0x01)
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF | CF); // SBC A,B (0x00 - 0x01)
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x06, c.reg.a);
     assert_eq!(c.flags(), NF); // SBC A,C (0xFF - 0xF8 - carry)
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0xF7, c.reg.a);
-    assert_eq!(c.flags(), SF | HF | NF | CF); // SBC A,D (0x06 - 0x0F)
+    assert_eq!(c.flags(), SF | YF | HF | NF | CF); // SBC A,D (0x06 - 0x0F)
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x7D, c.reg.a);
-    assert_eq!(c.flags(), HF | VF | NF); // SBC A,E (0xF7 - 0x79)
+    assert_eq!(c.flags(), YF | HF | XF | VF | NF); // SBC A,E (0xF7 - 0x79)
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0xBD, c.reg.a);
-    assert_eq!(c.flags(), SF | VF | NF | CF); // SBC A,H (0x7D - 0xC0)
+    assert_eq!(c.flags(), SF | YF | XF | VF | NF | CF); // SBC A,H (0x7D - 0xC0)
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0xFD, c.reg.a);
-    assert_eq!(c.flags(), SF | HF | NF | CF); // SBC A,L (0xBD - 0xBF - carry ) should set HF
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF | CF); // SBC A,L (0xBD - 0xBF - carry ) should set HF
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xFB, c.reg.a);
-    assert_eq!(c.flags(), SF | NF); // SBC A,0x01
+    assert_eq!(c.flags(), SF | YF | XF | NF); // SBC A,0x01
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xFD, c.reg.a);
-    assert_eq!(c.flags(), SF | HF | NF | CF); // SBC A,0xFE
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF | CF); // SBC A,0xFE
 }
 
 #[test]
@@ -843,7 +855,7 @@ fn sbc_ixyh_ixyl_asm() {
     assert_eq!(c.flags(), ZF | NF); // SUB A,A
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xFF, c.reg.a);
-    assert_eq!(c.flags(), SF | HF | NF | CF); // SBC A,IXH
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF | CF); // SBC A,IXH
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x06, c.reg.a);
     assert_eq!(c.flags(), NF); // SBC A,IXL
@@ -854,7 +866,7 @@ fn sbc_ixyh_ixyl_asm() {
     assert_eq!(c.flags(), ZF | NF); // SUB A,A
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xFF, c.reg.a);
-    assert_eq!(c.flags(), SF | HF | NF | CF); // SBC A,IYH
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF | CF); // SBC A,IYH
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x06, c.reg.a);
     assert_eq!(c.flags(), NF); // SBC A,IYL
@@ -878,13 +890,13 @@ fn sbc_i_hl_ix_iy_asm() {
     assert_eq!(0x00, c.reg.a);
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xBF, c.reg.a);
-    assert_eq!(c.flags(), SF | HF | NF | CF);
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF | CF);
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0x5D, c.reg.a);
-    assert_eq!(c.flags(), VF | NF);
+    assert_eq!(c.flags(), XF | VF | NF);
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0xFC, c.reg.a);
-    assert_eq!(c.flags(), SF | NF | CF);
+    assert_eq!(c.flags(), SF | YF | XF | NF | CF);
 }
 
 #[test]
@@ -909,19 +921,19 @@ fn or_r_asm() {
     assert_eq!(c.flags(), 0); // OR D
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x0F, c.reg.a);
-    assert_eq!(c.flags(), PF); // OR E
+    assert_eq!(c.flags(), XF | PF); // OR E
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x1F, c.reg.a);
-    assert_eq!(c.flags(), 0); // OR H
+    assert_eq!(c.flags(), XF); // OR H
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x3F, c.reg.a);
-    assert_eq!(c.flags(), PF); // OR L
+    assert_eq!(c.flags(), YF | XF | PF); // OR L
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0x7F, c.reg.a);
-    assert_eq!(c.flags(), 0); // OR 0x40
+    assert_eq!(c.flags(), YF | XF); // OR 0x40
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xFF, c.reg.a);
-    assert_eq!(c.flags(), SF | PF); // OR 0x80
+    assert_eq!(c.flags(), SF | YF | XF | PF); // OR 0x80
 }
 
 #[test]
@@ -946,19 +958,19 @@ fn xor_r_asm() {
     assert_eq!(c.flags(), PF); // XOR D
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x0A, c.reg.a);
-    assert_eq!(c.flags(), PF); // XOR E
+    assert_eq!(c.flags(), XF | PF); // XOR E
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x15, c.reg.a);
     assert_eq!(c.flags(), 0); // XOR H
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x2A, c.reg.a);
-    assert_eq!(c.flags(), 0); // XOR L
+    assert_eq!(c.flags(), YF | XF); // XOR L
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0x55, c.reg.a);
     assert_eq!(c.flags(), PF); // XOR 0x7F
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xAA, c.reg.a);
-    assert_eq!(c.flags(), SF | PF); // XOR 0xFF
+    assert_eq!(c.flags(), SF | YF | XF | PF); // XOR 0xFF
 }
 
 #[test]
@@ -977,13 +989,13 @@ fn or_xor_i_hl_ix_iy_asm() {
     assert_eq!(c.flags(), PF); // OR (HL)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0x63, c.reg.a);
-    assert_eq!(c.flags(), PF); // OR (IX+1)
+    assert_eq!(c.flags(), YF | PF); // OR (IX+1)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0xE7, c.reg.a);
-    assert_eq!(c.flags(), SF | PF); // OR (IY-1)
+    assert_eq!(c.flags(), SF | YF | PF); // OR (IY-1)
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xA6, c.reg.a);
-    assert_eq!(c.flags(), SF | PF); // XOR (HL)
+    assert_eq!(c.flags(), SF | YF | PF); // XOR (HL)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0xC4, c.reg.a);
     assert_eq!(c.flags(), SF); // XOR (IX+1)
@@ -1005,46 +1017,46 @@ fn and_r_asm() {
     assert_eq!(c.flags(), HF); // AND B
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xFF, c.reg.a);
-    assert_eq!(c.flags(), SF | PF); // OR 0xFF
+    assert_eq!(c.flags(), SF | YF | XF | PF); // OR 0xFF
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x03, c.reg.a);
     assert_eq!(c.flags(), HF | PF); // AND C
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xFF, c.reg.a);
-    assert_eq!(c.flags(), SF | PF); // OR 0xFF
+    assert_eq!(c.flags(), SF | YF | XF | PF); // OR 0xFF
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x04, c.reg.a);
     assert_eq!(c.flags(), HF); // AND D
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xFF, c.reg.a);
-    assert_eq!(c.flags(), SF | PF); // OR 0xFF
+    assert_eq!(c.flags(), SF | YF | XF | PF); // OR 0xFF
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x08, c.reg.a);
-    assert_eq!(c.flags(), HF); // AND E
+    assert_eq!(c.flags(), HF | XF); // AND E
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xFF, c.reg.a);
-    assert_eq!(c.flags(), SF | PF); // OR 0xFF
+    assert_eq!(c.flags(), SF | YF | XF | PF); // OR 0xFF
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x10, c.reg.a);
     assert_eq!(c.flags(), HF); // AND H
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xFF, c.reg.a);
-    assert_eq!(c.flags(), SF | PF); // OR 0xFF
+    assert_eq!(c.flags(), SF | YF | XF | PF); // OR 0xFF
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x20, c.reg.a);
-    assert_eq!(c.flags(), HF); // AND L
+    assert_eq!(c.flags(), YF | HF); // AND L
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xFF, c.reg.a);
-    assert_eq!(c.flags(), SF | PF); // OR 0xFF
+    assert_eq!(c.flags(), SF | YF | XF | PF); // OR 0xFF
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0x40, c.reg.a);
     assert_eq!(c.flags(), HF); // AND 0x40
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xFF, c.reg.a);
-    assert_eq!(c.flags(), SF | PF); // OR 0xFF
+    assert_eq!(c.flags(), SF | YF | XF | PF); // OR 0xFF
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xAA, c.reg.a);
-    assert_eq!(c.flags(), SF | HF | PF); // AND 0xAA
+    assert_eq!(c.flags(), SF | YF | HF | XF | PF); // AND 0xAA
 }
 
 #[test]
@@ -1060,13 +1072,13 @@ fn and_i_hl_ix_iy_asm() {
     }
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xFE, c.reg.a);
-    assert_eq!(c.flags(), SF | HF); // AND (HL)
+    assert_eq!(c.flags(), SF | YF | HF | XF); // AND (HL)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0xAA, c.reg.a);
-    assert_eq!(c.flags(), SF | HF | PF); // AND (IX+1)
+    assert_eq!(c.flags(), SF | YF | HF | XF | PF); // AND (IX+1)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0x88, c.reg.a);
-    assert_eq!(c.flags(), SF | HF | PF); // AND (IY-1)
+    assert_eq!(c.flags(), SF | HF | XF | PF); // AND (IY-1)
 }
 
 #[test]
@@ -1088,19 +1100,19 @@ fn inc_dec_r_asm() {
     assert_eq!(c.flags(), ZF | HF); // INC B
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0xFF, c.reg.b);
-    assert_eq!(c.flags(), SF | HF | NF); // DEC B
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF); // DEC B
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x10, c.reg.c);
     assert_eq!(c.flags(), HF); // INC C
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x0F, c.reg.c);
-    assert_eq!(c.flags(), HF | NF); // DEC C
+    assert_eq!(c.flags(), HF | XF | NF); // DEC C
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x0F, c.reg.d);
-    assert_eq!(c.flags(), 0); // INC D
+    assert_eq!(c.flags(), XF); // INC D
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x0E, c.reg.d);
-    assert_eq!(c.flags(), NF); // DEC D
+    assert_eq!(c.flags(), XF | NF); // DEC D
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0x00, c.reg.a);
     assert_eq!(c.flags(), SF | HF | NF | CF); // CP 0x01   set carry flag (should be preserved)
@@ -1109,19 +1121,19 @@ fn inc_dec_r_asm() {
     assert_eq!(c.flags(), SF | HF | VF | CF); // INC E
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x7F, c.reg.e);
-    assert_eq!(c.flags(), HF | VF | NF | CF); // DEC E
+    assert_eq!(c.flags(), YF | HF | XF | VF | NF | CF); // DEC E
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x3F, c.reg.h);
-    assert_eq!(c.flags(), CF); // INC H
+    assert_eq!(c.flags(), YF | XF | CF); // INC H
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x3E, c.reg.h);
-    assert_eq!(c.flags(), NF | CF); // DEC H
+    assert_eq!(c.flags(), YF | XF | NF | CF); // DEC H
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x24, c.reg.l);
-    assert_eq!(c.flags(), CF); // INC L
+    assert_eq!(c.flags(), YF | CF); // INC L
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x23, c.reg.l);
-    assert_eq!(c.flags(), NF | CF); // DEC L
+    assert_eq!(c.flags(), YF | NF | CF); // DEC L
 }
 
 #[test]
@@ -1137,7 +1149,7 @@ fn inc_dec_i_hl_ix_iy_asm() {
     }
     assert_eq!(c.execute(&mut b), 11);
     assert_eq!(0xFF, b.read_byte(0x1000));
-    assert_eq!(c.flags(), SF | HF | NF); // DEC (HL)
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF); // DEC (HL)
     assert_eq!(c.execute(&mut b), 11);
     assert_eq!(0x00, b.read_byte(0x1000));
     assert_eq!(c.flags(), ZF | HF); // INC (HL)
@@ -1146,13 +1158,13 @@ fn inc_dec_i_hl_ix_iy_asm() {
     assert_eq!(c.flags(), HF); // INC (IX+1)
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(0x3F, b.read_byte(0x1001));
-    assert_eq!(c.flags(), HF | NF); // DEC (IX+1)
+    assert_eq!(c.flags(), YF | HF | XF | NF); // DEC (IX+1)
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(0x80, b.read_byte(0x1002));
     assert_eq!(c.flags(), SF | HF | VF); // INC (IY-1)
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(0x7F, b.read_byte(0x1002));
-    assert_eq!(c.flags(), HF | PF | NF); // DEC (IY-1)
+    assert_eq!(c.flags(), YF | HF | XF | PF | NF); // DEC (IY-1)
 }
 
 #[test]
@@ -1377,13 +1389,13 @@ fn daa_asm() {
     assert_eq!(0x27, c.reg.b); // LD B,0x27
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x3C, c.reg.a);
-    assert_eq!(c.flags(), 0); // ADD A,B
+    assert_eq!(c.flags(), YF | XF); // ADD A,B
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x42, c.reg.a);
     assert_eq!(c.flags(), HF | PF); // DAA
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x1B, c.reg.a);
-    assert_eq!(c.flags(), HF | NF); // SUB B
+    assert_eq!(c.flags(), HF | XF | NF); // SUB B
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x15, c.reg.a);
     assert_eq!(c.flags(), NF); // DAA
@@ -1395,13 +1407,13 @@ fn daa_asm() {
     assert_eq!(c.flags(), NF); // LD B,0x15
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0xA5, c.reg.a);
-    assert_eq!(c.flags(), SF); // ADD A,B
+    assert_eq!(c.flags(), SF | YF); // ADD A,B
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x05, c.reg.a);
     assert_eq!(c.flags(), PF | CF); // DAA
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0xF0, c.reg.a);
-    assert_eq!(c.flags(), SF | NF | CF); // SUB B
+    assert_eq!(c.flags(), SF | YF | NF | CF); // SUB B
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x90, c.reg.a);
     assert_eq!(c.flags(), SF | PF | NF | CF); // DAA
@@ -1417,19 +1429,19 @@ fn cpl_asm() {
     assert_eq!(c.flags(), ZF | NF); // SUB A
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0xFF, c.reg.a);
-    assert_eq!(c.flags(), ZF | HF | NF); // CPL
+    assert_eq!(c.flags(), ZF | YF | HF | XF | NF); // CPL
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x00, c.reg.a);
     assert_eq!(c.flags(), ZF | HF | NF); // CPL
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xAA, c.reg.a);
-    assert_eq!(c.flags(), SF); // ADD A,0xAA
+    assert_eq!(c.flags(), SF | YF | XF); // ADD A,0xAA
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x55, c.reg.a);
     assert_eq!(c.flags(), SF | HF | NF); // CPL
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0xAA, c.reg.a);
-    assert_eq!(c.flags(), SF | HF | NF); // CPL
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF); // CPL
 }
 
 #[test]
@@ -1448,13 +1460,13 @@ fn ccf_scf_asm() {
     assert_eq!(c.flags(), ZF | HF); // CCF
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0x34, c.reg.a);
-    assert_eq!(c.flags(), HF | NF | CF); // SUB 0xCC
+    assert_eq!(c.flags(), YF | HF | NF | CF); // SUB 0xCC
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x34, c.reg.a);
-    assert_eq!(c.flags(), HF); // CCF
+    assert_eq!(c.flags(), YF | HF); // CCF
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(0x34, c.reg.a);
-    assert_eq!(c.flags(), CF); // SCF
+    assert_eq!(c.flags(), YF | CF); // SCF
 }
 
 #[test]
@@ -1643,7 +1655,7 @@ fn jp_cc_nn_asm() {
     assert_eq!(0x021D, c.reg.pc);
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0xFF, c.reg.a);
-    assert_eq!(c.flags(), SF);
+    assert_eq!(c.flags(), SF | YF | XF);
     assert_eq!(c.execute(&mut b), 10);
     assert_eq!(0x0222, c.reg.pc);
     assert_eq!(c.execute(&mut b), 10);
@@ -1704,13 +1716,13 @@ fn ldi_asm() {
     assert_eq!(0x2002, c.reg.get_de());
     assert_eq!(0x0001, c.reg.get_bc());
     assert_eq!(0x02, b.read_byte(0x2001));
-    assert_eq!(c.flags(), PF);
+    assert_eq!(c.flags(), YF | PF);
     assert_eq!(c.execute(&mut b), 16);
     assert_eq!(0x1003, c.reg.get_hl());
     assert_eq!(0x2003, c.reg.get_de());
     assert_eq!(0x0000, c.reg.get_bc());
     assert_eq!(0x03, b.read_byte(0x2002));
-    assert_eq!(c.flags(), 0);
+    assert_eq!(c.flags(), YF);
 }
 
 #[test]
@@ -1724,12 +1736,15 @@ fn ldir_asm() {
     for _ in 0..3 {
         c.execute(&mut b);
     }
-    c.execute(&mut b);
+    // LDIR : 3 iterations
+    for _ in 0..3 {
+        c.execute(&mut b);
+    }
     assert_eq!(0x1003, c.reg.get_hl());
     assert_eq!(0x2003, c.reg.get_de());
     assert_eq!(0x0000, c.reg.get_bc());
     assert_eq!(0x03, b.read_byte(0x2002));
-    assert_eq!(c.flags(), 0);
+    assert_eq!(c.flags(), YF);
     c.execute(&mut b);
     assert_eq!(0x33, c.reg.a);
 }
@@ -1750,13 +1765,13 @@ fn ldd_asm() {
     assert_eq!(0x2001, c.reg.get_de());
     assert_eq!(0x0002, c.reg.get_bc());
     assert_eq!(0x03, b.read_byte(0x2002));
-    assert_eq!(c.flags(), PF);
+    assert_eq!(c.flags(), YF | PF);
     assert_eq!(c.execute(&mut b), 16);
     assert_eq!(0x1000, c.reg.get_hl());
     assert_eq!(0x2000, c.reg.get_de());
     assert_eq!(0x0001, c.reg.get_bc());
     assert_eq!(0x02, b.read_byte(0x2001));
-    assert_eq!(c.flags(), PF);
+    assert_eq!(c.flags(), YF | PF);
     assert_eq!(c.execute(&mut b), 16);
     assert_eq!(0x0FFF, c.reg.get_hl());
     assert_eq!(0x1FFF, c.reg.get_de());
@@ -1776,7 +1791,10 @@ fn lddr_asm() {
     for _ in 0..3 {
         c.execute(&mut b);
     }
-    c.execute(&mut b);
+    // LDDR : 3 iterations
+    for _ in 0..3 {
+        c.execute(&mut b);
+    }
     assert_eq!(0x0FFF, c.reg.get_hl());
     assert_eq!(0x1FFF, c.reg.get_de());
     assert_eq!(0x0000, c.reg.get_bc());
@@ -1801,7 +1819,7 @@ fn cpi_asm() {
     assert_eq!(c.execute(&mut b), 16);
     assert_eq!(0x1001, c.reg.get_hl());
     assert_eq!(0x0003, c.reg.get_bc());
-    assert_eq!(c.flags(), PF | NF);
+    assert_eq!(c.flags(), YF | PF | NF);
     let f = c.flags() | CF;
     c.reg.flags.set_from_byte(f);
     assert_eq!(c.execute(&mut b), 16);
@@ -1815,7 +1833,7 @@ fn cpi_asm() {
     assert_eq!(c.execute(&mut b), 16);
     assert_eq!(0x1004, c.reg.get_hl());
     assert_eq!(0x0000, c.reg.get_bc());
-    assert_eq!(c.flags(), SF | HF | NF | CF);
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF | CF);
 }
 
 #[test]
@@ -1831,7 +1849,10 @@ fn cpir_asm() {
         c.execute(&mut b);
     }
 
-    c.execute(&mut b);
+    // CPIR : 3 iterations until a match is found
+    for _ in 0..3 {
+        c.execute(&mut b);
+    }
     assert_eq!(0x1003, c.reg.get_hl());
     assert_eq!(0x0001, c.reg.get_bc());
     assert_eq!(c.flags(), ZF | PF | NF);
@@ -1839,7 +1860,7 @@ fn cpir_asm() {
     c.execute(&mut b);
     assert_eq!(0x1004, c.reg.get_hl());
     assert_eq!(0x0000, c.reg.get_bc());
-    assert_eq!(c.flags(), SF | HF | NF);
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF);
 }
 
 #[test]
@@ -1857,7 +1878,7 @@ fn cpd_asm() {
     assert_eq!(c.execute(&mut b), 16);
     assert_eq!(0x1002, c.reg.get_hl());
     assert_eq!(0x0003, c.reg.get_bc());
-    assert_eq!(c.flags(), SF | HF | PF | NF);
+    assert_eq!(c.flags(), SF | YF | HF | XF | PF | NF);
     assert_eq!(c.execute(&mut b), 16);
     assert_eq!(0x1001, c.reg.get_hl());
     assert_eq!(0x0002, c.reg.get_bc());
@@ -1869,7 +1890,7 @@ fn cpd_asm() {
     assert_eq!(c.execute(&mut b), 16);
     assert_eq!(0x0FFF, c.reg.get_hl());
     assert_eq!(0x0000, c.reg.get_bc());
-    assert_eq!(c.flags(), NF);
+    assert_eq!(c.flags(), YF | NF);
 }
 
 #[test]
@@ -1940,7 +1961,7 @@ fn add_adc_sbc_16_asm() {
     assert_eq!(c.flags(), SF | HF | PF);
     assert_eq!(c.execute(&mut b), 15);
     assert_eq!(0x7FFF, c.reg.get_hl());
-    assert_eq!(c.flags(), NF | HF | PF);
+    assert_eq!(c.flags(), YF | HF | XF | PF | NF);
 }
 
 #[test]
@@ -1994,8 +2015,8 @@ fn ld_a_ir_asm() {
     assert_eq!(0x00, c.reg.a);
     assert_eq!(c.flags(), ZF | NF);
     assert_eq!(c.execute(&mut b), 9);
-    assert_eq!(0x34, c.reg.a);
-    assert_eq!(c.flags(), PF);
+    assert_eq!(0x3A, c.reg.a); // R was incremented by the 4 previous instructions
+    assert_eq!(c.flags(), YF | XF | PF);
 }
 
 #[test]
@@ -2027,10 +2048,10 @@ fn rlc_rl_rrc_rr_r_asm() {
     assert_eq!(c.flags(), CF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xFF, c.reg.b);
-    assert_eq!(c.flags(), SF | PF | CF);
+    assert_eq!(c.flags(), SF | YF | XF | PF | CF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xFF, c.reg.b);
-    assert_eq!(c.flags(), SF | PF | CF);
+    assert_eq!(c.flags(), SF | YF | XF | PF | CF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x06, c.reg.c);
     assert_eq!(c.flags(), PF);
@@ -2039,28 +2060,28 @@ fn rlc_rl_rrc_rr_r_asm() {
     assert_eq!(c.flags(), PF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xFD, c.reg.d);
-    assert_eq!(c.flags(), SF | CF);
+    assert_eq!(c.flags(), SF | YF | XF | CF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xFE, c.reg.d);
-    assert_eq!(c.flags(), SF | CF);
+    assert_eq!(c.flags(), SF | YF | XF | CF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x88, c.reg.e);
-    assert_eq!(c.flags(), SF | PF | CF);
+    assert_eq!(c.flags(), SF | XF | PF | CF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x11, c.reg.e);
     assert_eq!(c.flags(), PF | CF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x7E, c.reg.h);
-    assert_eq!(c.flags(), PF);
+    assert_eq!(c.flags(), YF | XF | PF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x3F, c.reg.h);
-    assert_eq!(c.flags(), PF);
+    assert_eq!(c.flags(), YF | XF | PF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xE0, c.reg.l);
-    assert_eq!(c.flags(), SF);
+    assert_eq!(c.flags(), SF | YF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x70, c.reg.l);
-    assert_eq!(c.flags(), 0);
+    assert_eq!(c.flags(), YF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x00, c.reg.a);
     assert_eq!(c.flags(), ZF | PF | CF);
@@ -2069,10 +2090,10 @@ fn rlc_rl_rrc_rr_r_asm() {
     assert_eq!(c.flags(), 0);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x7F, c.reg.b);
-    assert_eq!(c.flags(), CF);
+    assert_eq!(c.flags(), YF | XF | CF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xFF, c.reg.b);
-    assert_eq!(c.flags(), SF | PF);
+    assert_eq!(c.flags(), SF | YF | XF | PF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x06, c.reg.c);
     assert_eq!(c.flags(), PF);
@@ -2081,28 +2102,28 @@ fn rlc_rl_rrc_rr_r_asm() {
     assert_eq!(c.flags(), PF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xFC, c.reg.d);
-    assert_eq!(c.flags(), SF | PF | CF);
+    assert_eq!(c.flags(), SF | YF | XF | PF | CF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xFE, c.reg.d);
-    assert_eq!(c.flags(), SF);
+    assert_eq!(c.flags(), SF | YF | XF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x08, c.reg.e);
-    assert_eq!(c.flags(), CF);
+    assert_eq!(c.flags(), XF | CF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x11, c.reg.e);
     assert_eq!(c.flags(), PF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x7E, c.reg.h);
-    assert_eq!(c.flags(), PF);
+    assert_eq!(c.flags(), YF | XF | PF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x3F, c.reg.h);
-    assert_eq!(c.flags(), PF);
+    assert_eq!(c.flags(), YF | XF | PF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xE0, c.reg.l);
-    assert_eq!(c.flags(), SF);
+    assert_eq!(c.flags(), SF | YF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x70, c.reg.l);
-    assert_eq!(c.flags(), 0);
+    assert_eq!(c.flags(), YF);
 }
 
 #[test]
@@ -2128,17 +2149,17 @@ fn rrc_rlc_rr_rl_i_hl_ix_iy_asm() {
     assert_eq!(0x01, c.reg.a); // LD A,(HL)
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(0xFF, b.read_byte(0x1001));
-    assert_eq!(c.flags(), SF | PF | CF); // RRC (IX+1)
+    assert_eq!(c.flags(), SF | YF | XF | PF | CF); // RRC (IX+1)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0xFF, c.reg.a); // LD A,(IX+1)
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(0xFF, b.read_byte(0x1001));
-    assert_eq!(c.flags(), SF | PF | CF); // RLC (IX+1)
+    assert_eq!(c.flags(), SF | YF | XF | PF | CF); // RLC (IX+1)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0xFF, c.reg.a); // LD A,(IX+1)
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(0x88, b.read_byte(0x1002));
-    assert_eq!(c.flags(), SF | PF | CF); // RRC (IY-1)
+    assert_eq!(c.flags(), SF | XF | PF | CF); // RRC (IY-1)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0x88, c.reg.a); // LD A,(IY-1)
     assert_eq!(c.execute(&mut b), 23);
@@ -2158,17 +2179,17 @@ fn rrc_rlc_rr_rl_i_hl_ix_iy_asm() {
     assert_eq!(0x01, c.reg.a); // LD A,(HL)
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(0xFF, b.read_byte(0x1001));
-    assert_eq!(c.flags(), SF | PF | CF); // RR (IX+1)
+    assert_eq!(c.flags(), SF | YF | XF | PF | CF); // RR (IX+1)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0xFF, c.reg.a); // LD A,(IX+1)
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(0xFF, b.read_byte(0x1001));
-    assert_eq!(c.flags(), SF | PF | CF); // RL (IX+1)
+    assert_eq!(c.flags(), SF | YF | XF | PF | CF); // RL (IX+1)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0xFF, c.reg.a); // LD A,(IX+1)
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(0x23, b.read_byte(0x1002));
-    assert_eq!(c.flags(), 0); // RL (IY-1)
+    assert_eq!(c.flags(), YF); // RL (IY-1)
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(0x23, c.reg.a); // LD A,(IY-1)
     assert_eq!(c.execute(&mut b), 23);
@@ -2197,13 +2218,13 @@ fn sla_r_asm() {
     assert_eq!(c.flags(), CF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xFC, c.reg.d);
-    assert_eq!(c.flags(), SF | PF | CF);
+    assert_eq!(c.flags(), SF | YF | XF | PF | CF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xFE, c.reg.e);
-    assert_eq!(c.flags(), SF);
+    assert_eq!(c.flags(), SF | YF | XF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x22, c.reg.h);
-    assert_eq!(c.flags(), PF);
+    assert_eq!(c.flags(), YF | PF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x00, c.reg.l);
     assert_eq!(c.flags(), ZF | PF);
@@ -2228,13 +2249,13 @@ fn sra_r_asm() {
     assert_eq!(c.flags(), SF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0xFF, c.reg.d);
-    assert_eq!(c.flags(), SF | PF);
+    assert_eq!(c.flags(), SF | YF | XF | PF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x3F, c.reg.e);
-    assert_eq!(c.flags(), PF | CF);
+    assert_eq!(c.flags(), YF | XF | PF | CF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x08, c.reg.h);
-    assert_eq!(c.flags(), CF);
+    assert_eq!(c.flags(), XF | CF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x00, c.reg.l);
     assert_eq!(c.flags(), ZF | PF);
@@ -2259,13 +2280,13 @@ fn srl_r_asm() {
     assert_eq!(c.flags(), PF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x7F, c.reg.d);
-    assert_eq!(c.flags(), 0);
+    assert_eq!(c.flags(), YF | XF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x3F, c.reg.e);
-    assert_eq!(c.flags(), PF | CF);
+    assert_eq!(c.flags(), YF | XF | PF | CF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x08, c.reg.h);
-    assert_eq!(c.flags(), CF);
+    assert_eq!(c.flags(), XF | CF);
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(0x00, c.reg.l);
     assert_eq!(c.flags(), ZF | PF);
@@ -2381,11 +2402,11 @@ fn rld_rrd_asm() {
     assert_eq!(c.execute(&mut b), 18);
     assert_eq!(0xF0, c.reg.a);
     assert_eq!(0x0E, b.read_byte(0x1000));
-    assert_eq!(c.flags(), SF | PF);
+    assert_eq!(c.flags(), SF | YF | PF);
     assert_eq!(c.execute(&mut b), 18);
     assert_eq!(0xFE, c.reg.a);
     assert_eq!(0x00, b.read_byte(0x1000));
-    assert_eq!(c.flags(), SF);
+    assert_eq!(c.flags(), SF | YF | XF);
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(0x00, c.reg.a);
     assert_eq!(c.execute(&mut b), 7);
@@ -2736,6 +2757,42 @@ fn hlt() {
     assert_eq!(c.reg.pc, 0);
 }
 
+#[test]
+fn hlt_int() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xED); // IM 1
+    b.write_byte(0x0001, 0x56);
+    b.write_byte(0x0002, 0xFB); // EI
+    b.write_byte(0x0003, 0x76); // HALT
+    c.reg.sp = 0xFF00;
+    for _ in 0..3 {
+        c.execute(&mut b);
+    }
+    assert_eq!(c.execute(&mut b), 4);
+    assert_eq!(c.execute(&mut b), 4);
+    assert_eq!(c.reg.pc, 0x0003);
+    c.int_request(0xFF);
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0038);
+    assert_eq!(b.read_word(c.reg.sp), 0x0004);
+}
+
+#[test]
+fn hlt_nmi() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0x76); // HALT
+    c.reg.sp = 0xFF00;
+    c.execute(&mut b);
+    assert_eq!(c.execute(&mut b), 4);
+    assert_eq!(c.reg.pc, 0x0000);
+    c.nmi_request();
+    assert_eq!(c.execute(&mut b), 11);
+    assert_eq!(c.reg.pc, 0x0066);
+    assert_eq!(b.read_word(c.reg.sp), 0x0001);
+}
+
 #[test]
 fn ld_b_ix_d() {
     let mut c = CPU::new();
@@ -2838,7 +2895,7 @@ fn ld_a_r() {
     c.reg.r = 0x56;
     assert_eq!(c.execute(&mut b), 9);
     assert_eq!(c.reg.pc, 0x0002);
-    assert_eq!(c.reg.a, 0x56);
+    assert_eq!(c.reg.a, 0x58); // R was incremented twice by the opcode fetch
 }
 
 #[test]
@@ -3337,7 +3394,13 @@ fn ldir() {
     b.write_byte(0x2223, 0x59);
     b.write_byte(0x1113, 0xA5);
     b.write_byte(0x2224, 0xC5);
+    // One iteration per step : PC stays on LDIR while repeating
+    assert_eq!(c.execute(&mut b), 21);
+    assert_eq!(c.reg.pc, 0);
+    assert_eq!(c.reg.get_bc(), 0x02);
     assert_eq!(c.execute(&mut b), 21);
+    assert_eq!(c.reg.pc, 0);
+    assert_eq!(c.execute(&mut b), 16);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(c.reg.get_hl(), 0x1114);
     assert_eq!(b.read_byte(0x1111), 0x88);
@@ -3386,6 +3449,10 @@ fn lddr() {
     b.write_byte(0x1114, 0xA5);
     b.write_byte(0x2225, 0xC5);
     assert_eq!(c.execute(&mut b), 21);
+    assert_eq!(c.reg.pc, 0);
+    assert_eq!(c.execute(&mut b), 21);
+    assert_eq!(c.reg.pc, 0);
+    assert_eq!(c.execute(&mut b), 16);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(c.reg.get_hl(), 0x1111);
     assert_eq!(b.read_byte(0x1112), 0x88);
@@ -3412,8 +3479,8 @@ fn cpi() {
     assert_eq!(c.reg.pc, 2);
     assert_eq!(c.reg.get_hl(), 0x1112);
     assert_eq!(c.reg.get_bc(), 0);
-    assert_eq!(c.reg.flags.z, true);
-    assert_eq!(c.reg.flags.p, false);
+    assert!(c.reg.flags.z);
+    assert!(!c.reg.flags.p);
 }
 
 #[test]
@@ -3429,11 +3496,16 @@ fn cpir() {
     b.write_byte(0x1112, 0x00);
     b.write_byte(0x1113, 0xF3);
     assert_eq!(c.execute(&mut b), 21);
+    assert_eq!(c.reg.pc, 0);
+    assert_eq!(c.execute(&mut b), 21);
+    assert_eq!(c.reg.pc, 0);
+    // A match ends the search
+    assert_eq!(c.execute(&mut b), 16);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(c.reg.get_hl(), 0x1114);
     assert_eq!(c.reg.get_bc(), 4);
-    assert_eq!(c.reg.flags.z, true);
-    assert_eq!(c.reg.flags.p, true);
+    assert!(c.reg.flags.z);
+    assert!(c.reg.flags.p);
 }
 
 #[test]
@@ -3450,8 +3522,8 @@ fn cpd() {
     assert_eq!(c.reg.pc, 2);
     assert_eq!(c.reg.get_hl(), 0x1110);
     assert_eq!(c.reg.get_bc(), 0);
-    assert_eq!(c.reg.flags.z, true);
-    assert_eq!(c.reg.flags.p, false);
+    assert!(c.reg.flags.z);
+    assert!(!c.reg.flags.p);
 }
 
 #[test]
@@ -3467,11 +3539,15 @@ fn cpdr() {
     b.write_byte(0x1117, 0x00);
     b.write_byte(0x1118, 0x52);
     assert_eq!(c.execute(&mut b), 21);
+    assert_eq!(c.reg.pc, 0);
+    assert_eq!(c.execute(&mut b), 21);
+    assert_eq!(c.reg.pc, 0);
+    assert_eq!(c.execute(&mut b), 16);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(c.reg.get_hl(), 0x1115);
     assert_eq!(c.reg.get_bc(), 4);
-    assert_eq!(c.reg.flags.z, true);
-    assert_eq!(c.reg.flags.p, true);
+    assert!(c.reg.flags.z);
+    assert!(c.reg.flags.p);
 }
 
 #[test]
@@ -3623,7 +3699,7 @@ fn sbc_a_r_ovf() {
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(c.reg.pc, 1);
     assert_eq!(c.reg.a, 0x7E);
-    assert_eq!(c.reg.flags.p, true);
+    assert!(c.reg.flags.p);
 }
 
 #[test]
@@ -3806,8 +3882,8 @@ fn cp_r() {
     c.reg.e = 0x05;
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 1);
-    assert_eq!(c.reg.flags.z, false);
-    assert_eq!(c.reg.flags.c, false);
+    assert!(!c.reg.flags.z);
+    assert!(!c.reg.flags.c);
 }
 
 #[test]
@@ -3819,8 +3895,8 @@ fn cp_n() {
     c.reg.a = 0x0A;
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(c.reg.pc, 2);
-    assert_eq!(c.reg.flags.z, false);
-    assert_eq!(c.reg.flags.c, false);
+    assert!(!c.reg.flags.z);
+    assert!(!c.reg.flags.c);
 }
 
 #[test]
@@ -3834,8 +3910,8 @@ fn cp_ix_d() {
     c.reg.a = 0x0A;
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(c.reg.pc, 3);
-    assert_eq!(c.reg.flags.z, false);
-    assert_eq!(c.reg.flags.c, false);
+    assert!(!c.reg.flags.z);
+    assert!(!c.reg.flags.c);
 }
 
 #[test]
@@ -3849,8 +3925,8 @@ fn cp_iy_d() {
     c.reg.a = 0x0A;
     assert_eq!(c.execute(&mut b), 19);
     assert_eq!(c.reg.pc, 3);
-    assert_eq!(c.reg.flags.z, false);
-    assert_eq!(c.reg.flags.c, false);
+    assert!(!c.reg.flags.z);
+    assert!(!c.reg.flags.c);
 }
 
 #[test]
@@ -3862,7 +3938,7 @@ fn inc_b() {
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 0x0001);
     assert_eq!(0, c.reg.b);
-    assert_eq!(true, c.reg.flags.z);
+    assert!(c.reg.flags.z);
 }
 
 #[test]
@@ -3874,7 +3950,7 @@ fn inc_c() {
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 0x0001);
     assert_eq!(0, c.reg.c);
-    assert_eq!(true, c.reg.flags.z);
+    assert!(c.reg.flags.z);
 }
 
 #[test]
@@ -3886,7 +3962,7 @@ fn inc_d() {
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 0x0001);
     assert_eq!(0, c.reg.d);
-    assert_eq!(true, c.reg.flags.z);
+    assert!(c.reg.flags.z);
 }
 
 #[test]
@@ -3898,7 +3974,7 @@ fn inc_e() {
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 0x0001);
     assert_eq!(0, c.reg.e);
-    assert_eq!(true, c.reg.flags.z);
+    assert!(c.reg.flags.z);
 }
 
 #[test]
@@ -3910,7 +3986,7 @@ fn inc_h() {
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 0x0001);
     assert_eq!(0, c.reg.h);
-    assert_eq!(true, c.reg.flags.z);
+    assert!(c.reg.flags.z);
 }
 
 #[test]
@@ -3922,7 +3998,7 @@ fn inc_l() {
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 0x0001);
     assert_eq!(0, c.reg.l);
-    assert_eq!(true, c.reg.flags.z);
+    assert!(c.reg.flags.z);
 }
 
 #[test]
@@ -3936,11 +4012,11 @@ fn inc_c_hl() {
     assert_eq!(c.execute(&mut b), 11);
     assert_eq!(c.reg.pc, 0x0001);
     assert_eq!(0, b.read_byte(0x100));
-    assert_eq!(true, c.reg.flags.z);
+    assert!(c.reg.flags.z);
     c.execute(&mut b);
     assert_eq!(c.reg.pc, 0x0002);
     assert_eq!(1, b.read_byte(0x100));
-    assert_eq!(false, c.reg.flags.z);
+    assert!(!c.reg.flags.z);
 }
 
 #[test]
@@ -3952,8 +4028,8 @@ fn inc_a() {
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 0x0001);
     assert_eq!(0x10, c.reg.a);
-    assert_eq!(false, c.reg.flags.z);
-    assert_eq!(true, c.reg.flags.h);
+    assert!(!c.reg.flags.z);
+    assert!(c.reg.flags.h);
 }
 
 #[test]
@@ -3968,7 +4044,7 @@ fn inc_ix_d() {
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(c.reg.pc, 0x03);
     assert_eq!(0, b.read_byte(0x105));
-    assert_eq!(true, c.reg.flags.z);
+    assert!(c.reg.flags.z);
 }
 
 #[test]
@@ -3983,7 +4059,7 @@ fn inc_iy_d() {
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(c.reg.pc, 0x03);
     assert_eq!(0, b.read_byte(0x105));
-    assert_eq!(true, c.reg.flags.z);
+    assert!(c.reg.flags.z);
 }
 
 #[test]
@@ -3996,11 +4072,11 @@ fn dcr_b() {
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 1);
     assert_eq!(0, c.reg.b);
-    assert_eq!(true, c.reg.flags.z);
+    assert!(c.reg.flags.z);
     c.execute(&mut b);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(0xff, c.reg.b);
-    assert_eq!(false, c.reg.flags.z);
+    assert!(!c.reg.flags.z);
 }
 
 #[test]
@@ -4013,11 +4089,11 @@ fn dcr_c() {
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 1);
     assert_eq!(0, c.reg.c);
-    assert_eq!(true, c.reg.flags.z);
+    assert!(c.reg.flags.z);
     c.execute(&mut b);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(0xff, c.reg.c);
-    assert_eq!(false, c.reg.flags.z);
+    assert!(!c.reg.flags.z);
 }
 
 #[test]
@@ -4030,11 +4106,11 @@ fn dcr_d() {
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 1);
     assert_eq!(0, c.reg.d);
-    assert_eq!(true, c.reg.flags.z);
+    assert!(c.reg.flags.z);
     c.execute(&mut b);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(0xff, c.reg.d);
-    assert_eq!(false, c.reg.flags.z);
+    assert!(!c.reg.flags.z);
 }
 
 #[test]
@@ -4047,11 +4123,11 @@ fn dcr_e() {
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 1);
     assert_eq!(0, c.reg.e);
-    assert_eq!(true, c.reg.flags.z);
+    assert!(c.reg.flags.z);
     c.execute(&mut b);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(0xff, c.reg.e);
-    assert_eq!(false, c.reg.flags.z);
+    assert!(!c.reg.flags.z);
 }
 
 #[test]
@@ -4064,11 +4140,11 @@ fn dcr_h() {
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 1);
     assert_eq!(0, c.reg.h);
-    assert_eq!(true, c.reg.flags.z);
+    assert!(c.reg.flags.z);
     c.execute(&mut b);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(0xff, c.reg.h);
-    assert_eq!(false, c.reg.flags.z);
+    assert!(!c.reg.flags.z);
 }
 
 #[test]
@@ -4081,11 +4157,11 @@ fn dcr_l() {
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 1);
     assert_eq!(0, c.reg.l);
-    assert_eq!(true, c.reg.flags.z);
+    assert!(c.reg.flags.z);
     c.execute(&mut b);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(0xff, c.reg.l);
-    assert_eq!(false, c.reg.flags.z);
+    assert!(!c.reg.flags.z);
 }
 
 #[test]
@@ -4099,11 +4175,11 @@ fn dcr_m() {
     assert_eq!(c.execute(&mut b), 11);
     assert_eq!(c.reg.pc, 1);
     assert_eq!(0x54, b.read_byte(0x0100));
-    assert_eq!(false, c.reg.flags.z);
+    assert!(!c.reg.flags.z);
     c.execute(&mut b);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(0x53, b.read_byte(0x0100));
-    assert_eq!(false, c.reg.flags.z);
+    assert!(!c.reg.flags.z);
 }
 
 #[test]
@@ -4116,11 +4192,11 @@ fn dcr_a() {
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 1);
     assert_eq!(0, c.reg.a);
-    assert_eq!(true, c.reg.flags.z);
+    assert!(c.reg.flags.z);
     c.execute(&mut b);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(0xff, c.reg.a);
-    assert_eq!(false, c.reg.flags.z);
+    assert!(!c.reg.flags.z);
 }
 
 #[test]
@@ -4135,7 +4211,7 @@ fn dec_ix_d() {
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(c.reg.pc, 0x03);
     assert_eq!(0xFE, b.read_byte(0x105));
-    assert_eq!(false, c.reg.flags.z);
+    assert!(!c.reg.flags.z);
 }
 
 #[test]
@@ -4150,7 +4226,7 @@ fn dec_iy_d() {
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(c.reg.pc, 0x03);
     assert_eq!(0xFE, b.read_byte(0x105));
-    assert_eq!(false, c.reg.flags.z);
+    assert!(!c.reg.flags.z);
 }
 
 #[test]
@@ -4164,8 +4240,8 @@ fn daa() {
     c.execute(&mut b);
     assert_eq!(c.reg.pc, 1);
     assert_eq!(c.reg.a, 1);
-    assert_eq!(c.reg.flags.h, true);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.h);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4189,7 +4265,7 @@ fn neg_asm() {
     assert_eq!(c.reg.a, 0x01); // LD A,0x01
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(c.reg.a, 0xFF);
-    assert_eq!(c.flags(), SF | HF | NF | CF); // NEG
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF | CF); // NEG
     assert_eq!(c.execute(&mut b), 7);
     assert_eq!(c.reg.a, 0x00);
     assert_eq!(c.flags(), ZF | HF | CF); // ADD A,0x01
@@ -4217,10 +4293,10 @@ fn ccf() {
     b.write_byte(0x0000, 0x3f);
     b.write_byte(0x0001, 0x3f);
     assert_eq!(c.execute(&mut b), 4);
-    assert_eq!(true, c.reg.flags.c);
+    assert!(c.reg.flags.c);
     assert_eq!(c.reg.pc, 0x0001);
     c.execute(&mut b);
-    assert_eq!(false, c.reg.flags.c);
+    assert!(!c.reg.flags.c);
     assert_eq!(c.reg.pc, 0x0002);
 }
 
@@ -4232,10 +4308,10 @@ fn scf() {
     b.write_byte(0x0001, 0x37);
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 0x0001);
-    assert_eq!(true, c.reg.flags.c);
+    assert!(c.reg.flags.c);
     c.execute(&mut b);
     assert_eq!(c.reg.pc, 0x0002);
-    assert_eq!(true, c.reg.flags.c);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4248,7 +4324,7 @@ fn add_hl_b() {
     assert_eq!(c.execute(&mut b), 11);
     assert_eq!(c.reg.h, 0xD5);
     assert_eq!(c.reg.l, 0x1A);
-    assert_eq!(c.reg.flags.c, false);
+    assert!(!c.reg.flags.c);
     assert_eq!(c.reg.pc, 1);
 }
 
@@ -4262,7 +4338,7 @@ fn add_hl_d() {
     assert_eq!(c.execute(&mut b), 11);
     assert_eq!(c.reg.h, 0xD5);
     assert_eq!(c.reg.l, 0x1A);
-    assert_eq!(c.reg.flags.c, false);
+    assert!(!c.reg.flags.c);
     assert_eq!(c.reg.pc, 1);
 }
 
@@ -4275,7 +4351,7 @@ fn add_hl_h() {
     assert_eq!(c.execute(&mut b), 11);
     assert_eq!(c.reg.h, 0x67);
     assert_eq!(c.reg.l, 0x3e);
-    assert_eq!(c.reg.flags.c, false);
+    assert!(!c.reg.flags.c);
     assert_eq!(c.reg.pc, 1);
 }
 
@@ -4289,7 +4365,7 @@ fn add_hl_sp() {
     assert_eq!(c.execute(&mut b), 11);
     assert_eq!(c.reg.h, 0xD5);
     assert_eq!(c.reg.l, 0x1A);
-    assert_eq!(c.reg.flags.c, false);
+    assert!(!c.reg.flags.c);
     assert_eq!(c.reg.pc, 1);
 }
 
@@ -4321,7 +4397,7 @@ fn adc_hl_d_ovf() {
     assert_eq!(c.reg.h, 0x80);
     assert_eq!(c.reg.l, 0x00);
     assert_eq!(c.reg.pc, 2);
-    assert_eq!(c.reg.flags.p, true);
+    assert!(c.reg.flags.p);
 }
 
 #[test]
@@ -4336,7 +4412,7 @@ fn adc_hl_h_ovf() {
     assert_eq!(c.reg.h, 0x00);
     assert_eq!(c.reg.l, 0x1F);
     assert_eq!(c.reg.pc, 2);
-    assert_eq!(c.reg.flags.p, false);
+    assert!(!c.reg.flags.p);
 }
 
 #[test]
@@ -4352,7 +4428,7 @@ fn adc_hl_sp_ovf() {
     assert_eq!(c.reg.h, 0x80);
     assert_eq!(c.reg.l, 0x00);
     assert_eq!(c.reg.pc, 2);
-    assert_eq!(c.reg.flags.p, true);
+    assert!(c.reg.flags.p);
 }
 
 #[test]
@@ -4475,7 +4551,7 @@ fn rlca() {
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 1);
     assert_eq!(c.reg.a, 0b00010001);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4488,7 +4564,7 @@ fn rla() {
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 1);
     assert_eq!(c.reg.a, 0b11101101);
-    assert_eq!(c.reg.flags.c, false);
+    assert!(!c.reg.flags.c);
 }
 
 #[test]
@@ -4500,7 +4576,7 @@ fn rrca() {
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 1);
     assert_eq!(c.reg.a, 0b10001000);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4513,7 +4589,7 @@ fn rra() {
     assert_eq!(c.execute(&mut b), 4);
     assert_eq!(c.reg.pc, 1);
     assert_eq!(c.reg.a, 0b01110000);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4526,7 +4602,7 @@ fn rlc_a() {
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(c.reg.a, 0b00010001);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4540,7 +4616,7 @@ fn rlc_hl() {
     assert_eq!(c.execute(&mut b), 15);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(b.read_byte(0x2828), 0b00010001);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4556,7 +4632,7 @@ fn rlc_ix_d() {
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(c.reg.pc, 4);
     assert_eq!(b.read_byte(0x1002), 0b00010001);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4572,7 +4648,7 @@ fn rlc_iy_d() {
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(c.reg.pc, 4);
     assert_eq!(b.read_byte(0x1002), 0b00010001);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4586,7 +4662,7 @@ fn rl_d() {
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(c.reg.d, 0b00011110);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4603,7 +4679,7 @@ fn rl_ix_d() {
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(c.reg.pc, 4);
     assert_eq!(b.read_byte(0x1002), 0b00011110);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4620,7 +4696,7 @@ fn rl_iy_d() {
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(c.reg.pc, 4);
     assert_eq!(b.read_byte(0x1002), 0b00011110);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4643,7 +4719,7 @@ fn rrc_a() {
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(c.reg.a, 0b10011000);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4660,7 +4736,7 @@ fn rrc_ix_d() {
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(c.reg.pc, 4);
     assert_eq!(b.read_byte(0x1002), 0b10011000);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4677,7 +4753,7 @@ fn rrc_iy_d() {
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(c.reg.pc, 4);
     assert_eq!(b.read_byte(0x1002), 0b10011000);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4691,7 +4767,7 @@ fn rr_hl() {
     assert_eq!(c.execute(&mut b), 15);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(b.read_byte(0x4343), 0b01101110);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4708,7 +4784,7 @@ fn rr_ix_d() {
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(c.reg.pc, 4);
     assert_eq!(b.read_byte(0x1002), 0b01101110);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4725,7 +4801,7 @@ fn rr_iy_d() {
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(c.reg.pc, 4);
     assert_eq!(b.read_byte(0x1002), 0b01101110);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4738,7 +4814,7 @@ fn sla_l() {
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(c.reg.l, 0b01100010);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4755,7 +4831,7 @@ fn sla_ix_d() {
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(c.reg.pc, 4);
     assert_eq!(b.read_byte(0x1002), 0b01100010);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4772,7 +4848,7 @@ fn sla_iy_d() {
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(c.reg.pc, 4);
     assert_eq!(b.read_byte(0x1002), 0b01100010);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4789,7 +4865,7 @@ fn sra_ix_d() {
     assert_eq!(c.execute(&mut b), 23);
     assert_eq!(c.reg.pc, 4);
     assert_eq!(b.read_byte(0x1002), 0b11011100);
-    assert_eq!(c.reg.flags.c, false);
+    assert!(!c.reg.flags.c);
 }
 
 #[test]
@@ -4802,7 +4878,7 @@ fn srl_b() {
     assert_eq!(c.execute(&mut b), 8);
     assert_eq!(c.reg.pc, 2);
     assert_eq!(c.reg.b, 0b01000111);
-    assert_eq!(c.reg.flags.c, true);
+    assert!(c.reg.flags.c);
 }
 
 #[test]
@@ -4845,7 +4921,7 @@ fn bit_4_hl() {
     c.reg.set_hl(0x4444);
     assert_eq!(c.execute(&mut b), 12);
     assert_eq!(c.reg.pc, 2);
-    assert_eq!(c.reg.flags.z, false);
+    assert!(!c.reg.flags.z);
     assert_eq!(b.read_byte(0x4444), 0x10);
 }
 
@@ -4862,7 +4938,7 @@ fn bit_6_ix_d() {
     assert_eq!(c.execute(&mut b), 20);
     assert_eq!(c.reg.pc, 4);
     assert_eq!(b.read_byte(0x2004), 0x40);
-    assert_eq!(c.reg.flags.z, false);
+    assert!(!c.reg.flags.z);
 }
 
 #[test]
@@ -4878,7 +4954,7 @@ fn bit_6_iy_d() {
     assert_eq!(c.execute(&mut b), 20);
     assert_eq!(c.reg.pc, 4);
     assert_eq!(b.read_byte(0x2004), 0x40);
-    assert_eq!(c.reg.flags.z, false);
+    assert!(!c.reg.flags.z);
 }
 
 #[test]
@@ -5166,11 +5242,12 @@ fn rst() {
 fn debug_unkn() {
     let mut c = CPU::new();
     let mut b = Bus::new(0xFFFF);
-    b.write_byte(0x0000, 0xDD);
+    b.write_byte(0x0000, 0xED);
     b.write_byte(0x0001, 0x00);
+    c.set_unknown_opcode_policy(UnknownOpcodePolicy::Trap);
     c.debug.unknw_instr = true;
     assert_eq!(c.execute(&mut b), 0xFF);
-    assert_eq!(c.debug.string, String::from("0xDD00"));
+    assert_eq!(c.debug.string, String::from("0xED00"));
 }
 
 // if this test loops forever, interrupts are not working
@@ -5237,6 +5314,8 @@ fn nmi() {
     }
     c.nmi_request();
     c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0066);
+    c.execute(&mut b);
     assert_eq!(c.reg.pc, 0x0067);
     assert_eq!(c.reg.b, 0x0F);
     loop {
@@ -5289,11 +5368,1955 @@ fn dasm_cb() {
     b.write_byte(0x0276, 0xCB); // BIT 1,B
     b.write_byte(0x0277, 0x48);
     assert_eq!(
-        Bus::dasm(&mut b, 0x274),
+        Bus::dasm(&b, 0x274),
         (String::from("CB00          RLC B"), 2)
     );
     assert_eq!(
-        Bus::dasm(&mut b, 0x276),
+        Bus::dasm(&b, 0x276),
         (String::from("CB48          BIT 1,B"), 2)
     );
 }
+
+// Port latch whose state stays readable by the test once the device is boxed into the bus
+#[derive(Clone)]
+struct Port {
+    state: Arc<Mutex<(u16, u8)>>,
+}
+
+impl Port {
+    fn new(data: u8) -> Port {
+        Port {
+            state: Arc::new(Mutex::new((0, data))),
+        }
+    }
+
+    // Address of the last port access
+    fn address(&self) -> u16 {
+        self.state.lock().unwrap().0
+    }
+
+    // Last byte written, or byte returned on reads
+    fn data(&self) -> u8 {
+        self.state.lock().unwrap().1
+    }
+}
+
+impl IoDevice for Port {
+    fn read(&mut self, address: u16) -> u8 {
+        let mut state = self.state.lock().unwrap();
+        state.0 = address;
+        state.1
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        *self.state.lock().unwrap() = (address, data);
+    }
+}
+
+#[test]
+fn io_device() {
+    let mut b = Bus::new(0xFFFF);
+    assert_eq!(b.io_read(0x0010), 0xFF);
+    let port = Port::new(0);
+    b.set_io_device(Box::new(port.clone()));
+    b.io_write(0x1234, 0x56);
+    assert_eq!(port.address(), 0x1234);
+    assert_eq!(port.data(), 0x56);
+    assert_eq!(b.io_read(0x0010), 0x56);
+    assert_eq!(port.address(), 0x0010);
+}
+
+#[test]
+fn in_a_n() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    let port = Port::new(0xDE);
+    b.set_io_device(Box::new(port.clone()));
+    b.write_byte(0x0000, 0x3E); // LD A,$12
+    b.write_byte(0x0001, 0x12);
+    b.write_byte(0x0002, 0xDB); // IN A,($34)
+    b.write_byte(0x0003, 0x34);
+    c.execute(&mut b);
+    assert_eq!(c.execute(&mut b), 11);
+    assert_eq!(port.address(), 0x1234);
+    assert_eq!(c.reg.a, 0xDE);
+    assert_eq!(c.reg.pc, 4);
+}
+
+#[test]
+fn out_n_a() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    let port = Port::new(0);
+    b.set_io_device(Box::new(port.clone()));
+    b.write_byte(0x0000, 0xD3); // OUT ($07),A
+    b.write_byte(0x0001, 0x07);
+    b.write_byte(0x0002, 0xDB); // IN A,($00)
+    b.write_byte(0x0003, 0x00);
+    c.reg.a = 0xBB;
+    assert_eq!(c.execute(&mut b), 11);
+    assert_eq!(port.address(), 0xBB07);
+    assert_eq!(c.reg.pc, 2);
+    c.reg.a = 0x00;
+    c.execute(&mut b);
+    assert_eq!(port.address(), 0x0000);
+    assert_eq!(c.reg.a, 0xBB);
+}
+
+#[test]
+fn debug_io() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.set_io_device(Box::new(Port::new(0x85)));
+    b.write_byte(0x0000, 0xED); // IN D,(C)
+    b.write_byte(0x0001, 0x50);
+    b.write_byte(0x0002, 0xED); // OUT (C),E
+    b.write_byte(0x0003, 0x59);
+    c.reg.set_bc(0x1234);
+    c.reg.e = 0x5A;
+    c.debug.instr_in = true;
+    c.debug.io = true;
+    c.execute(&mut b);
+    assert_eq!(c.debug.string, String::from("IN : 0x1234 -> 0x85"));
+    c.execute(&mut b);
+    assert_eq!(c.debug.string, String::from("OUT : 0x5A -> 0x1234"));
+}
+
+#[test]
+fn in_r_c() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    let port = Port::new(0x85);
+    b.set_io_device(Box::new(port.clone()));
+    b.write_byte(0x0000, 0xED); // IN D,(C)
+    b.write_byte(0x0001, 0x50);
+    b.write_byte(0x0002, 0xED); // IN (C)
+    b.write_byte(0x0003, 0x70);
+    c.reg.set_bc(0x1234);
+    c.reg.flags.c = true;
+    assert_eq!(c.execute(&mut b), 12);
+    assert_eq!(c.reg.d, 0x85);
+    assert_eq!(port.address(), 0x1234);
+    assert_eq!(c.flags(), SF | CF);
+    c.reg.d = 0;
+    assert_eq!(c.execute(&mut b), 12);
+    assert_eq!(c.reg.d, 0);
+    assert_eq!(c.reg.pc, 4);
+}
+
+#[test]
+fn out_c_r() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    let port = Port::new(0);
+    b.set_io_device(Box::new(port.clone()));
+    b.write_byte(0x0000, 0xED); // OUT (C),E
+    b.write_byte(0x0001, 0x59);
+    c.reg.set_bc(0x1234);
+    c.reg.e = 0x5A;
+    assert_eq!(c.execute(&mut b), 12);
+    assert_eq!(port.address(), 0x1234);
+    assert_eq!(port.data(), 0x5A);
+    assert_eq!(c.reg.pc, 2);
+}
+
+#[test]
+fn ini() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    let port = Port::new(0x80);
+    b.set_io_device(Box::new(port.clone()));
+    b.write_byte(0x0000, 0xED); // INI
+    b.write_byte(0x0001, 0xA2);
+    c.reg.set_bc(0x0210);
+    c.reg.set_hl(0x1000);
+    assert_eq!(c.execute(&mut b), 16);
+    // The port is read before B is decremented
+    assert_eq!(port.address(), 0x0210);
+    assert_eq!(b.read_byte(0x1000), 0x80);
+    assert_eq!(c.reg.get_hl(), 0x1001);
+    assert_eq!(c.reg.b, 0x01);
+    assert_eq!(c.flags(), PF | NF);
+}
+
+#[test]
+fn inir() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    let port = Port::new(0x3C);
+    b.set_io_device(Box::new(port.clone()));
+    b.write_byte(0x0000, 0xED); // INIR
+    b.write_byte(0x0001, 0xB2);
+    c.reg.set_bc(0x0310);
+    c.reg.set_hl(0x1000);
+    assert_eq!(c.execute(&mut b), 21);
+    assert_eq!(c.reg.pc, 0);
+    assert_eq!(port.address(), 0x0310);
+    assert_eq!(c.execute(&mut b), 21);
+    assert_eq!(c.execute(&mut b), 16);
+    assert_eq!(port.address(), 0x0110);
+    assert_eq!(b.read_byte(0x1000), 0x3C);
+    assert_eq!(b.read_byte(0x1002), 0x3C);
+    assert_eq!(c.reg.get_hl(), 0x1003);
+    assert_eq!(c.reg.b, 0x00);
+    assert!(c.reg.flags.z);
+    assert_eq!(c.reg.pc, 2);
+}
+
+#[test]
+fn outi() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    let port = Port::new(0);
+    b.set_io_device(Box::new(port.clone()));
+    b.write_byte(0x0000, 0xED); // OUTI
+    b.write_byte(0x0001, 0xA3);
+    b.write_byte(0x1000, 0xF0);
+    c.reg.set_bc(0x0107);
+    c.reg.set_hl(0x1000);
+    assert_eq!(c.execute(&mut b), 16);
+    // B is decremented before the port is written
+    assert_eq!(port.address(), 0x0007);
+    assert_eq!(port.data(), 0xF0);
+    assert_eq!(c.reg.get_hl(), 0x1001);
+    assert_eq!(c.reg.b, 0x00);
+    assert_eq!(c.flags(), ZF | NF);
+}
+
+#[test]
+fn otdr() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    let port = Port::new(0);
+    b.set_io_device(Box::new(port.clone()));
+    b.write_byte(0x0000, 0xED); // OTDR
+    b.write_byte(0x0001, 0xBB);
+    b.write_byte(0x1000, 0x11);
+    b.write_byte(0x1001, 0x22);
+    c.reg.set_bc(0x0207);
+    c.reg.set_hl(0x1001);
+    assert_eq!(c.execute(&mut b), 21);
+    assert_eq!(c.reg.pc, 0);
+    assert_eq!(port.address(), 0x0107);
+    assert_eq!(port.data(), 0x22);
+    assert_eq!(c.execute(&mut b), 16);
+    assert_eq!(port.address(), 0x0007);
+    assert_eq!(port.data(), 0x11);
+    assert_eq!(c.reg.get_hl(), 0x0FFF);
+    assert_eq!(c.reg.b, 0x00);
+    assert_eq!(c.reg.pc, 2);
+}
+
+#[test]
+fn dasm_ed() {
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xED); // OTIR
+    b.write_byte(0x0001, 0xB3);
+    b.write_byte(0x0002, 0xED); // IN A,(C)
+    b.write_byte(0x0003, 0x78);
+    b.write_byte(0x0004, 0xED); // LD BC,($1234)
+    b.write_byte(0x0005, 0x4B);
+    b.write_word(0x0006, 0x1234);
+    assert_eq!(
+        Bus::dasm(&b, 0x0000),
+        (String::from("EDB3          OTIR"), 2)
+    );
+    assert_eq!(
+        Bus::dasm(&b, 0x0002),
+        (String::from("ED78          IN A,(C)"), 2)
+    );
+    assert_eq!(
+        Bus::dasm(&b, 0x0004),
+        (String::from("ED4B 34 12    LD BC,(nn)"), 4)
+    );
+}
+
+#[test]
+fn r_increment() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0x00); // NOP
+    b.write_byte(0x0001, 0xDD); // LD IX,$2000
+    b.write_byte(0x0002, 0x21);
+    b.write_word(0x0003, 0x2000);
+    b.write_byte(0x0005, 0xFD); // INC IY
+    b.write_byte(0x0006, 0x23);
+    b.write_byte(0x0007, 0xED); // NEG
+    b.write_byte(0x0008, 0x44);
+    b.write_byte(0x0009, 0xCB); // RLC B
+    b.write_byte(0x000A, 0x00);
+    b.write_byte(0x000B, 0xDD); // RLC (IX+1)
+    b.write_byte(0x000C, 0xCB);
+    b.write_byte(0x000D, 0x01);
+    b.write_byte(0x000E, 0x06);
+    b.write_byte(0x000F, 0xED); // LD A,R
+    b.write_byte(0x0010, 0x5F);
+    c.reg.r = 0xFE;
+    c.execute(&mut b);
+    assert_eq!(c.reg.r, 0xFF);
+    c.execute(&mut b);
+    assert_eq!(c.reg.r, 0x81);
+    c.execute(&mut b);
+    assert_eq!(c.reg.r, 0x83);
+    c.execute(&mut b);
+    assert_eq!(c.reg.r, 0x85);
+    c.execute(&mut b);
+    assert_eq!(c.reg.r, 0x87);
+    c.execute(&mut b);
+    assert_eq!(c.reg.r, 0x89);
+    c.execute(&mut b);
+    assert_eq!(c.reg.a, 0x8B);
+    assert_eq!(c.reg.pc, 0x0011);
+}
+
+#[test]
+fn r_increment_halt_int() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0x76); // HALT
+    c.reg.sp = 0xFF00;
+    c.execute(&mut b);
+    c.execute(&mut b);
+    c.execute(&mut b);
+    assert_eq!(c.reg.r, 0x03);
+    c.nmi_request();
+    c.execute(&mut b);
+    assert_eq!(c.reg.r, 0x04);
+    c.execute(&mut b);
+    assert_eq!(c.reg.r, 0x05);
+}
+
+#[test]
+fn xy_flags_scf_ccf() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0x3E); // LD A,0x28
+    b.write_byte(0x0001, 0x28);
+    b.write_byte(0x0002, 0x37); // SCF
+    b.write_byte(0x0003, 0x3E); // LD A,0x00
+    b.write_byte(0x0004, 0x00);
+    b.write_byte(0x0005, 0x37); // SCF
+    b.write_byte(0x0006, 0x3F); // CCF
+    c.execute(&mut b);
+    c.execute(&mut b);
+    assert_eq!(c.flags(), YF | XF | CF);
+    c.execute(&mut b);
+    // The previous instruction did not modify the flags : X and Y are ORed with the A register bits
+    c.execute(&mut b);
+    assert_eq!(c.flags(), YF | XF | CF);
+    // The previous instruction modified the flags : X and Y are copied from the A register
+    c.execute(&mut b);
+    assert_eq!(c.flags(), HF);
+}
+
+#[test]
+fn xy_flags_ldi_cpi() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xED); // LDI
+    b.write_byte(0x0001, 0xA0);
+    b.write_byte(0x0002, 0xED); // CPI
+    b.write_byte(0x0003, 0xA1);
+    b.write_byte(0x1000, 0x0A);
+    b.write_byte(0x1001, 0x01);
+    c.reg.set_hl(0x1000);
+    c.reg.set_de(0x2000);
+    c.reg.set_bc(0x0002);
+    c.execute(&mut b);
+    assert_eq!(c.flags(), YF | XF | PF);
+    c.execute(&mut b);
+    assert_eq!(c.flags(), SF | YF | HF | XF | NF);
+}
+
+#[test]
+fn xy_flags_bit() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xCB); // BIT 7,A
+    b.write_byte(0x0001, 0x7F);
+    b.write_byte(0x0002, 0xDD); // BIT 0,(IX+1)
+    b.write_byte(0x0003, 0xCB);
+    b.write_byte(0x0004, 0x01);
+    b.write_byte(0x0005, 0x46);
+    c.reg.a = 0xA8;
+    c.reg.set_ix(0x27FF);
+    c.execute(&mut b);
+    assert_eq!(c.flags(), SF | YF | HF | XF);
+    // X and Y are copied from the high byte of IX+d
+    c.execute(&mut b);
+    assert_eq!(c.flags(), ZF | YF | HF | XF | PF);
+}
+
+#[test]
+fn adc_sbc_16_overflow() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0x37); // SCF
+    b.write_byte(0x0001, 0xED); // ADC HL,BC
+    b.write_byte(0x0002, 0x4A);
+    b.write_byte(0x0003, 0x37); // SCF
+    b.write_byte(0x0004, 0xED); // SBC HL,DE
+    b.write_byte(0x0005, 0x52);
+    c.reg.set_hl(0x7FFF);
+    c.reg.set_bc(0x0000);
+    c.reg.set_de(0xFFFF);
+    c.execute(&mut b);
+    c.execute(&mut b);
+    assert_eq!(c.reg.get_hl(), 0x8000);
+    assert_eq!(c.flags(), SF | HF | VF);
+    c.reg.set_hl(0x0000);
+    c.execute(&mut b);
+    c.execute(&mut b);
+    assert_eq!(c.reg.get_hl(), 0x0000);
+    assert_eq!(c.flags(), ZF | HF | NF | CF);
+}
+
+#[test]
+fn memptr() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0x3A); // LD A,($1234)
+    b.write_word(0x0001, 0x1234);
+    b.write_byte(0x0003, 0x32); // LD ($10FF),A
+    b.write_word(0x0004, 0x10FF);
+    b.write_byte(0x0006, 0xCA); // JP Z,$0100
+    b.write_word(0x0007, 0x0100);
+    b.write_byte(0x0009, 0x09); // ADD HL,BC
+    b.write_byte(0x000A, 0xDD); // LD A,(IX-2)
+    b.write_byte(0x000B, 0x7E);
+    b.write_byte(0x000C, 0xFE);
+    b.write_byte(0x000D, 0xED); // IN A,(C)
+    b.write_byte(0x000E, 0x78);
+    b.write_byte(0x1234, 0x55);
+    c.reg.set_hl(0x4000);
+    c.reg.set_ix(0x2000);
+    c.reg.set_bc(0x00FE);
+    c.execute(&mut b);
+    assert_eq!(c.memptr(), 0x1235);
+    c.execute(&mut b);
+    assert_eq!(c.memptr(), 0x5500);
+    // JP cc,nn loads MEMPTR even if the jump is not taken
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0009);
+    assert_eq!(c.memptr(), 0x0100);
+    c.execute(&mut b);
+    assert_eq!(c.memptr(), 0x4001);
+    c.execute(&mut b);
+    assert_eq!(c.memptr(), 0x1FFE);
+    c.execute(&mut b);
+    assert_eq!(c.memptr(), 0x00FF);
+}
+
+#[test]
+fn memptr_bit_hl() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0x3A); // LD A,($27FF)
+    b.write_word(0x0001, 0x27FF);
+    b.write_byte(0x0003, 0xCB); // BIT 0,(HL)
+    b.write_byte(0x0004, 0x46);
+    c.reg.set_hl(0x1000);
+    c.execute(&mut b);
+    c.execute(&mut b);
+    // X and Y are copied from the high byte of MEMPTR ($2800)
+    assert_eq!(c.flags(), ZF | YF | HF | XF | PF);
+}
+
+#[test]
+fn ei_int_delay() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xED); // IM 1
+    b.write_byte(0x0001, 0x56);
+    b.write_byte(0x0002, 0xFB); // EI
+    b.write_byte(0x0003, 0x00); // NOP
+    b.write_byte(0x0004, 0x00); // NOP
+    c.reg.sp = 0xFF00;
+    c.execute(&mut b);
+    c.execute(&mut b);
+    // The interrupt is not accepted before the instruction following EI has been executed
+    c.int_request(0xFF);
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0004);
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0038);
+    assert_eq!(b.read_word(0xFEFE), 0x0004);
+}
+
+#[test]
+fn prefix_int_delay() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xED); // IM 1
+    b.write_byte(0x0001, 0x56);
+    b.write_byte(0x0002, 0xFB); // EI
+    b.write_byte(0x0003, 0x00); // NOP
+    b.write_byte(0x0004, 0xDD); // DD prefix, overridden by the FD prefix
+    b.write_byte(0x0005, 0xFD); // LD IY,$1234
+    b.write_byte(0x0006, 0x21);
+    b.write_byte(0x0007, 0x34);
+    b.write_byte(0x0008, 0x12);
+    c.reg.sp = 0xFF00;
+    c.execute(&mut b);
+    c.execute(&mut b);
+    c.execute(&mut b);
+    assert_eq!(c.execute(&mut b), 4);
+    assert_eq!(c.reg.pc, 0x0005);
+    // Neither INT nor NMI is accepted within the prefix chain
+    c.int_request(0xFF);
+    c.nmi_request();
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0009);
+    assert_eq!(c.reg.get_iy(), 0x1234);
+    assert_eq!(c.reg.get_ix(), 0x0000);
+    // The NMI is accepted once the instruction has been executed
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0066);
+    assert_eq!(b.read_word(0xFEFE), 0x0009);
+}
+
+#[test]
+fn im0_multi_byte() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0100, 0xFB); // EI
+    b.write_byte(0x0101, 0x00); // NOP
+    b.write_byte(0x0102, 0x00); // NOP
+    b.write_byte(0x1000, 0xFB); // EI
+    b.write_byte(0x1001, 0xC9); // RET
+    c.reg.pc = 0x0100;
+    c.reg.sp = 0xFF00;
+    c.execute(&mut b);
+    c.execute(&mut b);
+    // CALL $1000 on the data bus : 17 T-states + 2 wait states
+    c.int_request_bytes(&[0xCD, 0x00, 0x10]);
+    assert_eq!(c.execute(&mut b), 19);
+    assert_eq!(c.reg.pc, 0x1000);
+    assert_eq!(c.reg.sp, 0xFEFE);
+    assert_eq!(b.read_word(0xFEFE), 0x0102);
+    c.execute(&mut b);
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0102);
+    // LD A,$55 on the data bus : the interrupted program continues at the same address
+    c.int_request_bytes(&[0x3E, 0x55]);
+    assert_eq!(c.execute(&mut b), 9);
+    assert_eq!(c.reg.a, 0x55);
+    assert_eq!(c.reg.pc, 0x0102);
+}
+
+#[test]
+fn im0_data_bus_pc_wrap() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0xFFFF, 0xFB); // EI
+    b.write_byte(0x0000, 0x00); // NOP
+    // LD A,$42 ; LD IX,$1234 ; SET 0,(IX+$00)
+    for bytes in [
+        &[0x3E, 0x42][..],
+        &[0xDD, 0x21, 0x34, 0x12],
+        &[0xDD, 0xCB, 0x00, 0xC6],
+    ] {
+        c.reg.pc = 0xFFFF;
+        c.execute(&mut b);
+        c.execute(&mut b);
+        // Accepted at $0001 : the instruction is executed as if it was read at $FFFF
+        c.int_request_bytes(bytes);
+        c.execute(&mut b);
+        assert_eq!(c.reg.pc, 0x0001);
+    }
+    assert_eq!(c.reg.a, 0x42);
+    assert_eq!(c.reg.get_ix(), 0x1234);
+    assert_eq!(b.read_byte(0x1234), 0x01);
+}
+
+#[test]
+fn im0_rst() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0100, 0xFB); // EI
+    b.write_byte(0x0101, 0x00); // NOP
+    c.reg.pc = 0x0100;
+    c.reg.sp = 0xFF00;
+    c.execute(&mut b);
+    c.execute(&mut b);
+    c.int_request(0xD7); // RST 10
+    assert_eq!(c.execute(&mut b), 13);
+    assert_eq!(c.reg.pc, 0x0010);
+    assert_eq!(b.read_word(0xFEFE), 0x0102);
+}
+
+#[test]
+fn ddcb_fdcb_undocumented() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xDD); // RLC (IX+1),B
+    b.write_byte(0x0001, 0xCB);
+    b.write_byte(0x0002, 0x01);
+    b.write_byte(0x0003, 0x00);
+    b.write_byte(0x0004, 0xFD); // SET 3,(IY-1),A
+    b.write_byte(0x0005, 0xCB);
+    b.write_byte(0x0006, 0xFF);
+    b.write_byte(0x0007, 0xDF);
+    b.write_byte(0x0008, 0xDD); // BIT 0,(IX+1) alias
+    b.write_byte(0x0009, 0xCB);
+    b.write_byte(0x000A, 0x01);
+    b.write_byte(0x000B, 0x40);
+    b.write_byte(0x1001, 0x81);
+    b.write_byte(0x1FFF, 0x10);
+    c.reg.set_ix(0x1000);
+    c.reg.set_iy(0x2000);
+    assert_eq!(c.execute(&mut b), 23);
+    assert_eq!(b.read_byte(0x1001), 0x03);
+    assert_eq!(c.reg.b, 0x03);
+    assert_eq!(c.flags(), PF | CF);
+    assert_eq!(c.execute(&mut b), 23);
+    assert_eq!(b.read_byte(0x1FFF), 0x18);
+    assert_eq!(c.reg.a, 0x18);
+    assert_eq!(c.execute(&mut b), 20);
+    assert_eq!(c.flags(), HF | CF);
+    assert_eq!(c.reg.pc, 0x000C);
+}
+
+#[test]
+fn dasm_ddcb_fdcb() {
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xDD); // RLC (IX+1),B
+    b.write_byte(0x0001, 0xCB);
+    b.write_byte(0x0002, 0x01);
+    b.write_byte(0x0003, 0x00);
+    b.write_byte(0x0004, 0xFD); // SET 3,(IY+d),A
+    b.write_byte(0x0005, 0xCB);
+    b.write_byte(0x0006, 0xFF);
+    b.write_byte(0x0007, 0xDF);
+    b.write_byte(0x0008, 0xDD); // BIT 0,(IX+1)
+    b.write_byte(0x0009, 0xCB);
+    b.write_byte(0x000A, 0x01);
+    b.write_byte(0x000B, 0x46);
+    assert_eq!(
+        Bus::dasm(&b, 0x0000),
+        (String::from("DDCB 01 00    RLC (IX+d),B"), 4)
+    );
+    assert_eq!(
+        Bus::dasm(&b, 0x0004),
+        (String::from("FDCB FF DF    SET 3,(IY+d),A"), 4)
+    );
+    assert_eq!(
+        Bus::dasm(&b, 0x0008),
+        (String::from("DDCB 01 46    BIT 0,(IX+d)"), 4)
+    );
+}
+
+#[test]
+fn ignored_prefix() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xDD); // DD prefix, ignored
+    b.write_byte(0x0001, 0x3C); // INC A
+    b.write_byte(0x0002, 0xDD); // DD prefix, ignored
+    b.write_byte(0x0003, 0xFD); // LD IY,$1234
+    b.write_byte(0x0004, 0x21);
+    b.write_byte(0x0005, 0x34);
+    b.write_byte(0x0006, 0x12);
+    b.write_byte(0x0007, 0xFD); // LD IYH,$56
+    b.write_byte(0x0008, 0x26);
+    b.write_byte(0x0009, 0x56);
+    b.write_byte(0x000A, 0xFD); // INC IYL
+    b.write_byte(0x000B, 0x2C);
+    assert_eq!(c.execute(&mut b), 4);
+    assert_eq!(c.reg.pc, 0x0001);
+    assert_eq!(c.reg.a, 0x00);
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0002);
+    assert_eq!(c.reg.a, 0x01);
+    // The last prefix of a chain wins
+    assert_eq!(c.execute(&mut b), 4);
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0007);
+    assert_eq!(c.reg.get_iy(), 0x1234);
+    assert_eq!(c.reg.get_ix(), 0x0000);
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x000A);
+    assert_eq!(c.reg.get_iy(), 0x5634);
+    c.execute(&mut b);
+    assert_eq!(c.reg.get_iy(), 0x5635);
+}
+
+#[test]
+fn ignored_prefix_int() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xED); // IM 1
+    b.write_byte(0x0001, 0x56);
+    b.write_byte(0x0002, 0xFB); // EI
+    b.write_byte(0x0003, 0x00); // NOP
+    b.write_byte(0x0004, 0xDD); // DD prefix, ignored
+    b.write_byte(0x0005, 0x00); // NOP
+    c.reg.sp = 0xFF00;
+    c.execute(&mut b);
+    c.execute(&mut b);
+    c.execute(&mut b);
+    c.execute(&mut b);
+    // Neither INT nor NMI is accepted between the ignored prefix and the following instruction
+    c.int_request(0xFF);
+    c.nmi_request();
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0006);
+    // The NMI is accepted once the NOP has been executed
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0066);
+    assert_eq!(b.read_word(0xFEFE), 0x0006);
+}
+
+#[test]
+fn dasm_ignored_prefix() {
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xDD); // DD prefix, ignored
+    b.write_byte(0x0001, 0xFD); // LD IY,$1234
+    b.write_byte(0x0002, 0x21);
+    b.write_byte(0x0003, 0x34);
+    b.write_byte(0x0004, 0x12);
+    b.write_byte(0x0005, 0xDD); // LD IXL,$56
+    b.write_byte(0x0006, 0x2E);
+    b.write_byte(0x0007, 0x56);
+    assert_eq!(
+        Bus::dasm(&b, 0x0000),
+        (String::from("DD            NOP (ignored prefix)"), 1)
+    );
+    assert_eq!(
+        Bus::dasm(&b, 0x0001),
+        (String::from("FD21 34 12    LD IY,nn"), 4)
+    );
+    assert_eq!(
+        Bus::dasm(&b, 0x0005),
+        (String::from("DD2E 56        LD IXL,n"), 3)
+    );
+}
+
+#[test]
+fn block_repeat_int() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xED); // IM 1
+    b.write_byte(0x0001, 0x56);
+    b.write_byte(0x0002, 0xFB); // EI
+    b.write_byte(0x0003, 0xED); // LDIR
+    b.write_byte(0x0004, 0xB0);
+    b.write_byte(0x0038, 0xC9); // RET
+    c.reg.sp = 0xFF00;
+    c.reg.set_hl(0x1000);
+    c.reg.set_de(0x2000);
+    c.reg.set_bc(0x0003);
+    c.execute(&mut b);
+    c.execute(&mut b);
+    c.execute(&mut b);
+    assert_eq!(c.reg.get_bc(), 0x0002);
+    // The interrupt is accepted between two iterations, the address of LDIR is stacked
+    c.int_request(0xFF);
+    c.execute(&mut b);
+    assert_eq!(b.read_word(0xFEFE), 0x0003);
+    assert_eq!(c.reg.pc, 0x0038);
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0003);
+    c.execute(&mut b);
+    c.execute(&mut b);
+    assert_eq!(c.reg.get_bc(), 0x0000);
+    assert_eq!(c.reg.pc, 0x0005);
+}
+
+#[test]
+fn ldir_bc_zero() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xED); // LDIR
+    b.write_byte(0x0001, 0xB0);
+    c.reg.set_hl(0x8000);
+    c.reg.set_de(0x8000);
+    c.reg.set_bc(0x0000);
+    // BC = 0 at entry : 64 KB are copied (onto themselves, to keep the program intact)
+    assert_eq!(c.execute(&mut b), 21);
+    assert_eq!(c.reg.get_bc(), 0xFFFF);
+    assert_eq!(c.reg.pc, 0);
+    let mut steps = 1;
+    while c.reg.pc == 0 {
+        c.execute(&mut b);
+        steps += 1;
+    }
+    assert_eq!(steps, 0x10000);
+    assert_eq!(c.reg.get_bc(), 0x0000);
+}
+
+// Reference T-states of every opcode, when the branch is not taken (0 : prefix)
+const REF_CYCLES: [u32; 256] = [
+    4, 10, 7, 6, 4, 4, 7, 4, 4, 11, 7, 6, 4, 4, 7, 4, 8, 10, 7, 6, 4, 4, 7, 4, 12, 11, 7, 6, 4, 4,
+    7, 4, 7, 10, 16, 6, 4, 4, 7, 4, 7, 11, 16, 6, 4, 4, 7, 4, 7, 10, 13, 6, 11, 11, 10, 4, 7, 11,
+    13, 6, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4,
+    4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 7, 7, 7, 7, 7, 7, 4, 7, 4,
+    4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4,
+    4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4,
+    4, 4, 4, 4, 4, 7, 4, 5, 10, 10, 10, 10, 11, 7, 11, 5, 10, 10, 0, 10, 17, 7, 11, 5, 10, 10, 11,
+    10, 11, 7, 11, 5, 4, 10, 11, 10, 0, 7, 11, 5, 10, 10, 19, 10, 11, 7, 11, 5, 4, 10, 4, 10, 0, 7,
+    11, 5, 10, 10, 4, 10, 11, 7, 11, 5, 6, 10, 4, 10, 0, 7, 11,
+];
+
+// Reference T-states of the CB prefixed opcodes
+const REF_CYCLES_CB: [u32; 256] = [
+    8, 8, 8, 8, 8, 8, 15, 8, 8, 8, 8, 8, 8, 8, 15, 8, 8, 8, 8, 8, 8, 8, 15, 8, 8, 8, 8, 8, 8, 8,
+    15, 8, 8, 8, 8, 8, 8, 8, 15, 8, 8, 8, 8, 8, 8, 8, 15, 8, 8, 8, 8, 8, 8, 8, 15, 8, 8, 8, 8, 8,
+    8, 8, 15, 8, 8, 8, 8, 8, 8, 8, 12, 8, 8, 8, 8, 8, 8, 8, 12, 8, 8, 8, 8, 8, 8, 8, 12, 8, 8, 8,
+    8, 8, 8, 8, 12, 8, 8, 8, 8, 8, 8, 8, 12, 8, 8, 8, 8, 8, 8, 8, 12, 8, 8, 8, 8, 8, 8, 8, 12, 8,
+    8, 8, 8, 8, 8, 8, 12, 8, 8, 8, 8, 8, 8, 8, 15, 8, 8, 8, 8, 8, 8, 8, 15, 8, 8, 8, 8, 8, 8, 8,
+    15, 8, 8, 8, 8, 8, 8, 8, 15, 8, 8, 8, 8, 8, 8, 8, 15, 8, 8, 8, 8, 8, 8, 8, 15, 8, 8, 8, 8, 8,
+    8, 8, 15, 8, 8, 8, 8, 8, 8, 8, 15, 8, 8, 8, 8, 8, 8, 8, 15, 8, 8, 8, 8, 8, 8, 8, 15, 8, 8, 8,
+    8, 8, 8, 8, 15, 8, 8, 8, 8, 8, 8, 8, 15, 8, 8, 8, 8, 8, 8, 8, 15, 8, 8, 8, 8, 8, 8, 8, 15, 8,
+    8, 8, 8, 8, 8, 8, 15, 8, 8, 8, 8, 8, 8, 8, 15, 8,
+];
+
+// Reference T-states of the ED prefixed opcodes, when the block instructions do not repeat (0 : invalid or undocumented duplicate opcode)
+const REF_CYCLES_ED: [u32; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    12, 12, 15, 20, 8, 14, 8, 9, 12, 12, 15, 20, 0, 14, 0, 9, 12, 12, 15, 20, 0, 0, 8, 9, 12, 12,
+    15, 20, 0, 0, 8, 9, 12, 12, 15, 20, 0, 0, 0, 18, 12, 12, 15, 20, 0, 0, 0, 18, 12, 12, 15, 20,
+    0, 0, 0, 0, 12, 12, 15, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 16, 16, 16, 16, 0, 0, 0, 0, 16, 16, 16, 16, 0, 0, 0,
+    0, 16, 16, 16, 16, 0, 0, 0, 0, 16, 16, 16, 16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+// Reference T-states of the DD / FD prefixed opcodes (0 : the prefix does not apply)
+const REF_CYCLES_DD_FD: [u32; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 15, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 15, 0, 0, 0, 0, 0,
+    0, 0, 14, 20, 10, 8, 8, 11, 0, 0, 15, 20, 10, 8, 8, 11, 0, 0, 0, 0, 0, 23, 23, 19, 0, 0, 15, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 8, 8, 19, 0, 0, 0, 0, 0, 8, 8, 19, 0, 0, 0, 0, 0, 8, 8, 19, 0, 0, 0,
+    0, 0, 8, 8, 19, 0, 8, 8, 8, 8, 8, 8, 19, 8, 8, 8, 8, 8, 8, 8, 19, 8, 19, 19, 19, 19, 19, 19, 0,
+    19, 0, 0, 0, 0, 8, 8, 19, 0, 0, 0, 0, 0, 8, 8, 19, 0, 0, 0, 0, 0, 8, 8, 19, 0, 0, 0, 0, 0, 8,
+    8, 19, 0, 0, 0, 0, 0, 8, 8, 19, 0, 0, 0, 0, 0, 8, 8, 19, 0, 0, 0, 0, 0, 8, 8, 19, 0, 0, 0, 0,
+    0, 8, 8, 19, 0, 0, 0, 0, 0, 8, 8, 19, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 14, 0, 23, 0, 15, 0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 10, 0, 0, 0, 0, 0, 0,
+];
+
+// Extra T-states when the branch is taken, or when the block instruction repeats
+fn taken_extra_cycles(opcode: u16) -> u32 {
+    match opcode {
+        0x10 | 0x20 | 0x28 | 0x30 | 0x38 => 5,
+        0xC4 | 0xCC | 0xD4 | 0xDC | 0xE4 | 0xEC | 0xF4 | 0xFC => 7,
+        0xC0 | 0xC8 | 0xD0 | 0xD8 | 0xE0 | 0xE8 | 0xF0 | 0xF8 => 6,
+        0xEDB0..=0xEDB3 | 0xEDB8..=0xEDBB => 5,
+        _ => 0,
+    }
+}
+
+// Executes a single instruction, with the flags and registers set so that its branch is taken or not. Returns the T-states.
+fn cycles_of(instr: &[u8], taken: bool) -> u32 {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    for (i, byte) in instr.iter().enumerate() {
+        b.write_byte(i as u16, *byte);
+    }
+    let opcode = match instr {
+        [0xED, op, ..] => 0xED00 | *op as u16,
+        [op, ..] => *op as u16,
+        [] => 0,
+    };
+    // Conditions NZ, NC, PO and P are true when the flag is reset, Z, C, PE and M when it is set
+    let flag_set = (opcode >> 3) & 1 == 1;
+    c.reg
+        .flags
+        .set_from_byte(if flag_set == taken { 0xFF } else { 0x00 });
+    // DJNZ and the block instructions : B or BC reaches 0 when not taken. A is not found by CPIR / CPDR.
+    c.reg.set_bc(match (opcode, taken) {
+        (_, true) => 0x0202,
+        (0xEDB2 | 0xEDB3 | 0xEDBA | 0xEDBB, false) => 0x0100,
+        (0x10, false) => 0x0100,
+        (_, false) => 0x0001,
+    });
+    c.reg.a = 0xFF;
+    c.reg.sp = 0xFF00;
+    c.execute(&mut b)
+}
+
+#[test]
+fn cycles_all_opcodes() {
+    for taken in [false, true] {
+        for op in 0..=0xFF_u8 {
+            if REF_CYCLES[op as usize] != 0 {
+                let expected = REF_CYCLES[op as usize]
+                    + if taken {
+                        taken_extra_cycles(op as u16)
+                    } else {
+                        0
+                    };
+                assert_eq!(
+                    cycles_of(&[op, 0, 0], taken),
+                    expected,
+                    "{:02X} taken: {}",
+                    op,
+                    taken
+                );
+            }
+            assert_eq!(
+                cycles_of(&[0xCB, op], taken),
+                REF_CYCLES_CB[op as usize],
+                "CB{:02X}",
+                op
+            );
+            if REF_CYCLES_ED[op as usize] != 0 {
+                let expected = REF_CYCLES_ED[op as usize]
+                    + if taken {
+                        taken_extra_cycles(0xED00 | op as u16)
+                    } else {
+                        0
+                    };
+                assert_eq!(
+                    cycles_of(&[0xED, op, 0, 0], taken),
+                    expected,
+                    "ED{:02X} taken: {}",
+                    op,
+                    taken
+                );
+            }
+            for prefix in [0xDD, 0xFD] {
+                if REF_CYCLES_DD_FD[op as usize] != 0 {
+                    assert_eq!(
+                        cycles_of(&[prefix, op, 0, 0], taken),
+                        REF_CYCLES_DD_FD[op as usize],
+                        "{:02X}{:02X}",
+                        prefix,
+                        op
+                    );
+                }
+                // BIT n,(IX+d) : 20 T-states, other DDCB / FDCB instructions : 23 T-states
+                let expected = if (0x40..0x80).contains(&op) { 20 } else { 23 };
+                assert_eq!(
+                    cycles_of(&[prefix, 0xCB, 0, op], taken),
+                    expected,
+                    "{:02X}CB00{:02X}",
+                    prefix,
+                    op
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn int_ack_cycles() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xED); // IM 1
+    b.write_byte(0x0001, 0x56);
+    b.write_byte(0x0002, 0xFB); // EI
+    b.write_byte(0x0003, 0x00); // NOP
+    b.write_byte(0x0004, 0x00); // NOP
+    b.write_byte(0x0038, 0x3C); // INC A
+    b.write_byte(0x0066, 0x04); // INC B
+    b.write_byte(0x0106, 0x0C); // INC C
+    b.write_word(0x0210, 0x0106);
+    c.reg.sp = 0xFF00;
+    c.reg.i = 0x02;
+    for _ in 0..3 {
+        c.execute(&mut b);
+    }
+    // IM 1 : 13 T-states, further interrupts are disabled, the handler is executed by the next step
+    c.int_request(0xFF);
+    assert_eq!(c.execute(&mut b), 13);
+    assert_eq!(c.reg.pc, 0x0038);
+    assert_eq!(c.reg.a, 0x00);
+    c.int_request(0xFF);
+    assert_eq!(c.execute(&mut b), 4);
+    assert_eq!(c.reg.a, 0x01);
+    // NMI : 11 T-states
+    c.nmi_request();
+    assert_eq!(c.execute(&mut b), 11);
+    assert_eq!(c.reg.pc, 0x0066);
+    assert_eq!(c.reg.b, 0x00);
+    assert_eq!(c.execute(&mut b), 4);
+    assert_eq!(c.reg.b, 0x01);
+    // IM 2 : 19 T-states
+    b.write_byte(0x0067, 0xED); // IM 2
+    b.write_byte(0x0068, 0x5E);
+    b.write_byte(0x0069, 0xFB); // EI
+    b.write_byte(0x006A, 0x00); // NOP
+    for _ in 0..3 {
+        c.execute(&mut b);
+    }
+    c.int_request(0x10);
+    assert_eq!(c.execute(&mut b), 19);
+    assert_eq!(c.reg.pc, 0x0106);
+    assert_eq!(b.read_word(c.reg.sp), 0x006B);
+    assert_eq!(c.execute(&mut b), 4);
+    assert_eq!(c.reg.c, 0x01);
+}
+
+#[test]
+fn cycle_counter() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0x01); // LD BC,$1234
+    b.write_word(0x0001, 0x1234);
+    b.write_byte(0x0003, 0x3C); // INC A
+    assert_eq!(c.execute(&mut b), 10);
+    assert_eq!(c.execute(&mut b), 4);
+    assert_eq!(c.cycles(), 14);
+    // NOPs : the budget is exceeded by 2 cycles
+    assert_eq!(c.run_for_cycles(&mut b, 6), Ok(2));
+    assert_eq!(c.cycles(), 22);
+    assert_eq!(c.reg.pc, 0x0006);
+    assert_eq!(c.run_for_cycles(&mut b, 4), Ok(0));
+    assert_eq!(c.cycles(), 26);
+}
+
+#[test]
+fn run_until() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0x3C); // INC A
+    b.write_byte(0x0001, 0xC3); // JP $0000
+    b.write_word(0x0002, 0x0000);
+    assert_eq!(
+        c.run_until(&mut b, u64::MAX, |c, _| c.reg.a == 0x03),
+        StopReason::Breakpoint
+    );
+    assert_eq!(c.reg.pc, 0x0001);
+    assert_eq!(c.cycles(), 32);
+    assert_eq!(
+        c.run_until(&mut b, 100, |_, _| false),
+        StopReason::CycleLimit
+    );
+    assert_eq!(c.cycles(), 140);
+    b.write_byte(0x0000, 0x76); // HALT
+    assert_eq!(c.run_until(&mut b, 100, |_, _| false), StopReason::Halt);
+    assert_eq!(c.reg.pc, 0x0000);
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xED); // Unknown opcode
+    b.write_byte(0x0001, 0x00);
+    c.set_unknown_opcode_policy(UnknownOpcodePolicy::Trap);
+    assert_eq!(
+        c.run_until(&mut b, 100, |_, _| false),
+        StopReason::UnknownOpcode
+    );
+    // Breakpoint on memory contents
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0x32); // LD ($2000),A
+    b.write_word(0x0001, 0x2000);
+    b.write_byte(0x0003, 0x3C); // INC A
+    b.write_byte(0x0004, 0xC3); // JP $0000
+    b.write_word(0x0005, 0x0000);
+    assert_eq!(
+        c.run_until(&mut b, u64::MAX, |_, b| b.read_byte(0x2000) == 0x05),
+        StopReason::Breakpoint
+    );
+    assert_eq!(c.reg.pc, 0x0003);
+}
+
+// Executes NOPs until the end of a slice. Returns the number of steps and the sleep time.
+fn run_slice(c: &mut CPU, b: &mut Bus) -> (u32, Duration) {
+    let mut steps = 1;
+    loop {
+        if let Some(sleep_time) = c.execute_timed(b) {
+            return (steps, sleep_time);
+        }
+        steps += 1;
+    }
+}
+
+#[test]
+fn execute_timed_virtual_clock() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    let clock = VirtualClock::new();
+    c.set_clock(Box::new(clock.clone()));
+    // 1.7 MHz, 16 ms slices : 27200 cycles per slice (6800 NOPs)
+    c.set_freq_hz(1_700_000);
+    assert_eq!(run_slice(&mut c, &mut b), (6800, Duration::from_millis(16)));
+    // The host was slower than expected : the next sleep time is shortened accordingly
+    clock.advance(Duration::from_millis(20));
+    assert_eq!(run_slice(&mut c, &mut b), (6800, Duration::from_millis(12)));
+    clock.advance(Duration::from_millis(12));
+    assert_eq!(run_slice(&mut c, &mut b), (6800, Duration::from_millis(16)));
+    // More than a slice late : no sleep, the timing restarts with the next slice instead of catching up
+    clock.advance(Duration::from_millis(100));
+    assert_eq!(run_slice(&mut c, &mut b), (6800, Duration::ZERO));
+    clock.advance(Duration::from_millis(4));
+    assert_eq!(run_slice(&mut c, &mut b), (6800, Duration::from_millis(16)));
+    clock.advance(Duration::from_millis(20));
+    assert_eq!(run_slice(&mut c, &mut b), (6800, Duration::from_millis(12)));
+    // 20 ms slices
+    c.set_slice_duration(20);
+    assert_eq!(run_slice(&mut c, &mut b), (8500, Duration::from_millis(20)));
+}
+
+#[test]
+fn scheduler_events() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    let fired = Arc::new(Mutex::new(Vec::new()));
+    let f = fired.clone();
+    c.schedule(10, Box::new(move |c, _| f.lock().unwrap().push(c.cycles())));
+    let f = fired.clone();
+    let periodic = c.schedule_periodic(
+        6,
+        Box::new(move |c, _| f.lock().unwrap().push(100 + c.cycles())),
+    );
+    assert_eq!(c.next_event(), Some(6));
+    // NOPs : events are fired after the instruction reaching their due time
+    assert_eq!(c.run_to_next_event(&mut b), Ok(Some(6)));
+    assert_eq!(c.cycles(), 8);
+    assert_eq!(*fired.lock().unwrap(), vec![108]);
+    // Both events due at 10 and 12 are fired after the instruction ending at 12
+    assert_eq!(c.run_to_next_event(&mut b), Ok(Some(10)));
+    assert_eq!(c.cycles(), 12);
+    assert_eq!(*fired.lock().unwrap(), vec![108, 12, 112]);
+    // The period is counted from the due time : no drift
+    assert_eq!(c.next_event(), Some(18));
+    assert!(c.cancel_event(periodic));
+    assert!(!c.cancel_event(periodic));
+    assert_eq!(c.next_event(), None);
+    assert_eq!(c.run_to_next_event(&mut b), Ok(None));
+}
+
+#[test]
+fn scheduler_interrupts() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xED); // IM 1
+    b.write_byte(0x0001, 0x56);
+    b.write_byte(0x0002, 0xFB); // EI
+    b.write_byte(0x0003, 0x18); // JR $0003
+    b.write_byte(0x0004, 0xFE);
+    b.write_byte(0x0038, 0x3C); // INC A
+    b.write_byte(0x0039, 0xFB); // EI
+    b.write_byte(0x003A, 0xC9); // RET
+    b.write_byte(0x0066, 0xED); // RETN
+    b.write_byte(0x0067, 0x45);
+    c.reg.sp = 0xFF00;
+    // Frame interrupt every 1000 cycles, stopped by its own callback after 3 interrupts
+    let count = Arc::new(Mutex::new(0));
+    let n = count.clone();
+    let id = Arc::new(Mutex::new(None));
+    let i = id.clone();
+    *id.lock().unwrap() = Some(c.schedule_periodic(
+        1000,
+        Box::new(move |c, _| {
+            c.int_request(0xFF);
+            let mut n = n.lock().unwrap();
+            *n += 1;
+            if *n == 3 {
+                c.cancel_event(i.lock().unwrap().unwrap());
+            }
+        }),
+    ));
+    // One-shot NMI
+    c.schedule(2500, Box::new(|c, _| c.nmi_request()));
+    c.run_for_cycles(&mut b, 5000).unwrap();
+    assert_eq!(*count.lock().unwrap(), 3);
+    assert_eq!(c.reg.a, 3);
+    assert_eq!(c.next_event(), None);
+    assert_eq!(b.read_word(0xFEFE), 0x0003);
+}
+
+#[test]
+fn int_line_sources() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xED); // IM 2
+    b.write_byte(0x0001, 0x5E);
+    b.write_byte(0x0002, 0x00); // NOP
+    b.write_byte(0x0003, 0xFB); // EI
+    b.write_byte(0x0004, 0x00); // NOP
+    b.write_byte(0x1000, 0xFB); // EI
+    b.write_byte(0x1001, 0xED); // RETI
+    b.write_byte(0x1002, 0x4D);
+    b.write_byte(0x2000, 0xFB); // EI
+    b.write_byte(0x2001, 0xED); // RETI
+    b.write_byte(0x2002, 0x4D);
+    b.write_word(0x0010, 0x1000);
+    b.write_word(0x0020, 0x2000);
+    b.write_word(0x0030, 0x3000);
+    c.reg.sp = 0xFF00;
+    // Requests made while interrupts are disabled are not lost
+    c.int_assert(3, &[0x30]);
+    c.int_assert(2, &[0x20]);
+    c.int_assert(1, &[0x10]);
+    c.execute(&mut b);
+    c.execute(&mut b);
+    // Source 3 releases the line before being acknowledged
+    c.int_release(3);
+    c.execute(&mut b);
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0005);
+    // Highest priority first : source 1, then source 2 once the handler has returned
+    assert_eq!(c.execute(&mut b), 19);
+    assert_eq!(c.reg.pc, 0x1000);
+    c.execute(&mut b);
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0005);
+    assert_eq!(c.execute(&mut b), 19);
+    assert_eq!(c.reg.pc, 0x2000);
+    c.execute(&mut b);
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0005);
+    // No more pending request
+    assert_eq!(c.execute(&mut b), 4);
+    assert_eq!(c.reg.pc, 0x0006);
+}
+
+#[test]
+fn int_assert_no_data() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xFB); // EI
+    b.write_byte(0x0001, 0x00); // NOP
+    c.reg.sp = 0xFF00;
+    c.execute(&mut b);
+    // Nothing on the data bus in IM 0 : the floating bus is read as RST 38h
+    c.int_assert(0, &[]);
+    c.execute(&mut b);
+    assert_eq!(c.execute(&mut b), 13);
+    assert_eq!(c.reg.pc, 0x0038);
+    assert_eq!(b.read_word(0xFEFE), 0x0002);
+}
+
+#[test]
+fn reti_iff() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xED); // RETI
+    b.write_byte(0x0001, 0x4D);
+    b.write_word(0xFF00, 0x1234);
+    c.reg.sp = 0xFF00;
+    c.set_iff1(false);
+    c.set_iff2(true);
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x1234);
+    assert!(c.iff1() && c.iff2());
+}
+
+#[test]
+fn daisy_chain() {
+    use crate::daisy_chain::DaisyChainDevice;
+
+    #[derive(Default)]
+    struct State {
+        pending: bool,
+        in_service: bool,
+    }
+
+    struct Device {
+        state: Arc<Mutex<State>>,
+        vector: u8,
+    }
+
+    impl DaisyChainDevice for Device {
+        fn int_pending(&self) -> bool {
+            self.state.lock().unwrap().pending
+        }
+
+        fn in_service(&self) -> bool {
+            self.state.lock().unwrap().in_service
+        }
+
+        fn ack(&mut self) -> u8 {
+            let mut state = self.state.lock().unwrap();
+            state.pending = false;
+            state.in_service = true;
+            self.vector
+        }
+
+        fn reti(&mut self) {
+            self.state.lock().unwrap().in_service = false;
+        }
+    }
+
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xED); // IM 2
+    b.write_byte(0x0001, 0x5E);
+    b.write_byte(0x0002, 0xFB); // EI
+    b.write_byte(0x0003, 0x00); // NOP
+    b.write_byte(0x0004, 0x00); // NOP
+    b.write_byte(0x1000, 0xFB); // EI
+    b.write_byte(0x1001, 0x00); // NOP
+    b.write_byte(0x1002, 0xED); // RETI
+    b.write_byte(0x1003, 0x4D);
+    b.write_byte(0x2000, 0xFB); // EI
+    b.write_byte(0x2001, 0x00); // NOP
+    b.write_byte(0x2002, 0xED); // RETI
+    b.write_byte(0x2003, 0x4D);
+    b.write_word(0x0010, 0x1000);
+    b.write_word(0x0020, 0x2000);
+    c.reg.sp = 0xFF00;
+    let high = Arc::new(Mutex::new(State::default()));
+    let low = Arc::new(Mutex::new(State::default()));
+    c.daisy_chain_add(Box::new(Device {
+        state: high.clone(),
+        vector: 0x10,
+    }));
+    c.daisy_chain_add(Box::new(Device {
+        state: low.clone(),
+        vector: 0x20,
+    }));
+    high.lock().unwrap().pending = true;
+    c.execute(&mut b);
+    c.execute(&mut b);
+    c.execute(&mut b);
+    // The high priority device is acknowledged and provides its vector
+    assert_eq!(c.execute(&mut b), 19);
+    assert_eq!(c.reg.pc, 0x1000);
+    assert!(high.lock().unwrap().in_service);
+    // The low priority device is blocked while the high priority one is in service, even with interrupts enabled
+    low.lock().unwrap().pending = true;
+    c.execute(&mut b);
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x1002);
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0004);
+    assert!(!high.lock().unwrap().in_service);
+    // RETI has unblocked the low priority device
+    assert_eq!(c.execute(&mut b), 19);
+    assert_eq!(c.reg.pc, 0x2000);
+    // The high priority device can interrupt the service of the low priority one
+    high.lock().unwrap().pending = true;
+    c.execute(&mut b);
+    c.execute(&mut b);
+    assert_eq!(c.execute(&mut b), 19);
+    assert_eq!(c.reg.pc, 0x1000);
+    c.execute(&mut b);
+    c.execute(&mut b);
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x2002);
+    // RETI only ends the service of the highest priority device in service
+    assert!(!high.lock().unwrap().in_service);
+    assert!(low.lock().unwrap().in_service);
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0004);
+    assert!(!low.lock().unwrap().in_service);
+}
+
+#[test]
+fn interrupt_state() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xED); // IM 2
+    b.write_byte(0x0001, 0x5E);
+    b.write_byte(0x0002, 0xFB); // EI
+    b.write_byte(0x0003, 0x76); // HALT
+    b.write_byte(0x0004, 0x00); // NOP
+    assert_eq!(c.im(), 0);
+    assert!(!c.iff1() && !c.iff2());
+    c.execute(&mut b);
+    c.execute(&mut b);
+    assert_eq!(c.im(), 2);
+    assert!(c.iff1() && c.iff2());
+    c.execute(&mut b);
+    assert!(c.is_halted());
+    assert_eq!(c.reg.pc, 0x0003);
+    // Pending requests can be inspected and cancelled
+    c.int_request(0x10);
+    c.nmi_request();
+    assert!(c.int_pending() && c.nmi_pending());
+    c.int_cancel();
+    c.nmi_cancel();
+    assert!(!c.int_pending() && !c.nmi_pending());
+    assert_eq!(c.execute(&mut b), 4);
+    assert!(c.is_halted());
+    // Releasing HALT resumes the execution after the HALT instruction
+    c.release_halt();
+    assert!(!c.is_halted());
+    assert_eq!(c.reg.pc, 0x0004);
+    c.release_halt();
+    assert_eq!(c.reg.pc, 0x0004);
+    // State restored from a snapshot
+    c.set_im(1);
+    c.set_iff1(false);
+    c.set_iff2(true);
+    c.reg.pc = 0x0003;
+    c.set_halted(true);
+    assert_eq!(c.im(), 1);
+    assert!(!c.iff1() && c.iff2());
+    assert_eq!(c.execute(&mut b), 4);
+    assert_eq!(c.reg.pc, 0x0003);
+}
+
+#[test]
+#[should_panic]
+fn set_im_invalid() {
+    let mut c = CPU::new();
+    c.set_im(3);
+}
+
+#[test]
+fn power_on_state() {
+    let c: CPU = CPU::power_on();
+    assert_eq!(c.reg.get_af(), 0xFFFF);
+    assert_eq!(c.reg.sp, 0xFFFF);
+    assert_eq!(c.reg.pc, 0x0000);
+    assert_eq!(c.reg.i, 0x00);
+    assert_eq!(c.reg.r, 0x00);
+    assert_eq!(c.reg.get_hl(), 0x0000);
+    assert_eq!(c.im(), 0);
+    assert!(!c.iff1() && !c.iff2());
+    let c: CPU = CPU::power_on_with(UndefinedRegisters::Fill(0x5A5A));
+    assert_eq!(c.reg.get_af(), 0xFFFF);
+    assert_eq!(c.reg.get_bc(), 0x5A5A);
+    assert_eq!(c.reg.get_iy(), 0x5A5A);
+    assert_eq!(c.alt.get_af(), 0x5A5A);
+    assert_eq!(c.alt.get_hl(), 0x5A5A);
+    // Random contents are reproducible, and differ with the seed
+    let c = CPU::power_on_with(UndefinedRegisters::Random(1));
+    let d = CPU::power_on_with(UndefinedRegisters::Random(1));
+    let e = CPU::power_on_with(UndefinedRegisters::Random(2));
+    let regs = |c: &CPU| {
+        [
+            c.reg.get_bc(),
+            c.reg.get_de(),
+            c.reg.get_hl(),
+            c.reg.get_ix(),
+            c.reg.get_iy(),
+            c.alt.get_af(),
+            c.alt.get_bc(),
+            c.alt.get_de(),
+            c.alt.get_hl(),
+        ]
+    };
+    assert_eq!(regs(&c), regs(&d));
+    assert_ne!(regs(&c), regs(&e));
+    assert_eq!(c.reg.get_af(), 0xFFFF);
+    assert_eq!(c.reg.sp, 0xFFFF);
+}
+
+#[test]
+fn power_on_custom_memory() {
+    struct Ram(Vec<u8>);
+
+    impl Memory for Ram {
+        fn read_byte(&mut self, address: u16) -> u8 {
+            self.0[address as usize]
+        }
+
+        fn write_byte(&mut self, address: u16, data: u8) {
+            self.0[address as usize] = data;
+        }
+    }
+
+    let mut m = Ram(vec![0; 0x10000]);
+    m.write_byte(0x0000, 0xF5); // PUSH AF
+    let mut c = CPU::<Ram>::power_on_with(UndefinedRegisters::Fill(0x5A5A));
+    assert_eq!(c.reg.get_af(), 0xFFFF);
+    assert_eq!(c.reg.sp, 0xFFFF);
+    assert_eq!(c.reg.get_hl(), 0x5A5A);
+    c.execute(&mut m);
+    assert_eq!(m.read_word(0xFFFD), 0xFFFF);
+    let c = CPU::<Ram>::power_on();
+    assert_eq!(c.reg.get_af(), 0xFFFF);
+    assert_eq!(c.reg.get_bc(), 0x0000);
+}
+
+#[test]
+fn reset() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xED); // IM 2
+    b.write_byte(0x0001, 0x5E);
+    b.write_byte(0x0002, 0x3E); // LD A,$12
+    b.write_byte(0x0003, 0x12);
+    b.write_byte(0x0004, 0xED); // LD I,A
+    b.write_byte(0x0005, 0x47);
+    b.write_byte(0x0006, 0x31); // LD SP,$FF00
+    b.write_byte(0x0007, 0x00);
+    b.write_byte(0x0008, 0xFF);
+    b.write_byte(0x0009, 0xFB); // EI
+    b.write_byte(0x000A, 0x76); // HALT
+    for _ in 0..6 {
+        c.execute(&mut b);
+    }
+    assert!(c.is_halted());
+    c.nmi_request();
+    c.reset();
+    assert_eq!(c.reg.pc, 0x0000);
+    assert_eq!(c.reg.i, 0x00);
+    assert_eq!(c.reg.r, 0x00);
+    assert_eq!(c.im(), 0);
+    assert!(!c.iff1() && !c.iff2());
+    assert!(!c.is_halted());
+    assert!(!c.nmi_pending());
+    // The other registers are preserved
+    assert_eq!(c.reg.a, 0x12);
+    assert_eq!(c.reg.sp, 0xFF00);
+    // Execution restarts at 0000h
+    c.execute(&mut b);
+    assert_eq!(c.reg.pc, 0x0002);
+}
+
+#[test]
+fn unknown_opcode_policy() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0x00); // NOP
+    b.write_byte(0x0001, 0xED); // Unknown instruction
+    b.write_byte(0x0002, 0x54);
+    b.write_byte(0x0003, 0x00); // NOP
+    c.set_unknown_opcode_policy(UnknownOpcodePolicy::Trap);
+    c.execute(&mut b);
+    // Trapped : PC is left on the instruction and no cycle is counted
+    let err = c.try_execute(&mut b).unwrap_err();
+    assert_eq!(
+        err,
+        ExecError::UnknownOpcode(UnknownOpcode {
+            address: 0x0001,
+            prefix: Prefix::Ed,
+            bytes: vec![0xED, 0x54],
+        })
+    );
+    assert_eq!(err.to_string(), "unknown opcode ED 54 at 0x0001");
+    assert_eq!(c.reg.pc, 0x0001);
+    assert_eq!(c.cycles(), 4);
+    assert_eq!(c.execute(&mut b), 0xFF);
+    assert_eq!(c.reg.pc, 0x0001);
+    // Executed as a NOP
+    c.set_unknown_opcode_policy(UnknownOpcodePolicy::Nop);
+    assert_eq!(c.try_execute(&mut b), Ok(8));
+    assert_eq!(c.reg.pc, 0x0003);
+    assert_eq!(c.cycles(), 12);
+    // Emulated by a callback (ED54 is a mirror of NEG)
+    c.reg.pc = 0x0001;
+    c.reg.a = 0x01;
+    c.set_unknown_opcode_policy(UnknownOpcodePolicy::Callback(Box::new(
+        |c, _, unknown| match unknown.bytes[..] {
+            [0xED, 0x54] => {
+                c.reg.a = c.reg.a.wrapping_neg();
+                c.reg.pc = unknown.address.wrapping_add(2);
+                Ok(8)
+            }
+            _ => Err(ExecError::UnknownOpcode(unknown.clone())),
+        },
+    )));
+    assert_eq!(c.try_execute(&mut b), Ok(8));
+    assert_eq!(c.reg.a, 0xFF);
+    assert_eq!(c.reg.pc, 0x0003);
+    // The callback can trap the instruction
+    b.write_byte(0x0003, 0xED);
+    b.write_byte(0x0004, 0x00);
+    assert!(c.try_execute(&mut b).is_err());
+    assert_eq!(c.reg.pc, 0x0003);
+}
+
+#[test]
+fn run_unknown_opcode() {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0xED); // Unknown instruction
+    b.write_byte(0x0001, 0x00);
+    // Executed as a NOP by default
+    assert_eq!(c.run_for_cycles(&mut b, 10), Ok(2));
+    assert_eq!(c.reg.pc, 0x0003);
+    // Trapped : the execution stops on the instruction
+    c.reg.pc = 0x0000;
+    c.set_unknown_opcode_policy(UnknownOpcodePolicy::Trap);
+    assert!(c.run_for_cycles(&mut b, 10).is_err());
+    assert_eq!(c.reg.pc, 0x0000);
+    assert_eq!(c.cycles(), 12);
+    c.schedule(4, Box::new(|_, _| {}));
+    assert!(c.run_to_next_event(&mut b).is_err());
+    assert_eq!(c.reg.pc, 0x0000);
+    assert_eq!(c.next_event(), Some(16));
+}
+
+#[test]
+fn custom_memory() {
+    // 64 KB of RAM with one wait state on every opcode fetch, recording the addresses of the M1 cycles
+    struct WaitRam {
+        ram: Vec<u8>,
+        m1: Vec<u16>,
+        wait_states: u32,
+    }
+
+    impl Memory for WaitRam {
+        fn read_byte(&mut self, address: u16) -> u8 {
+            self.ram[address as usize]
+        }
+
+        fn write_byte(&mut self, address: u16, data: u8) {
+            self.ram[address as usize] = data;
+        }
+
+        fn fetch_opcode(&mut self, address: u16) -> u8 {
+            self.m1.push(address);
+            self.wait_states += 1;
+            self.read_byte(address)
+        }
+
+        fn wait_states(&mut self) -> u32 {
+            std::mem::take(&mut self.wait_states)
+        }
+    }
+
+    let mut m = WaitRam {
+        ram: vec![0; 0x10000],
+        m1: Vec::new(),
+        wait_states: 0,
+    };
+    let mut c = CPU::<WaitRam>::default();
+    m.write_byte(0x0000, 0x3E); // LD A,$12
+    m.write_byte(0x0001, 0x12);
+    m.write_byte(0x0002, 0xDD); // LD (IX+$01),A
+    m.write_byte(0x0003, 0x77);
+    m.write_byte(0x0004, 0x01);
+    m.write_byte(0x0005, 0xDB); // IN A,($10)
+    m.write_byte(0x0006, 0x10);
+    c.reg.set_ix(0x2000);
+    assert_eq!(c.execute(&mut m), 8);
+    assert_eq!(c.execute(&mut m), 21);
+    assert_eq!(m.read_byte(0x2001), 0x12);
+    // The default I/O ports read a floating data bus
+    assert_eq!(c.execute(&mut m), 12);
+    assert_eq!(c.reg.a, 0xFF);
+    assert_eq!(m.m1, vec![0x0000, 0x0002, 0x0003, 0x0005]);
+    assert_eq!(c.cycles(), 41);
+    // Events and run_until get the memory type
+    c.schedule(
+        10,
+        Box::new(|_, m: &mut WaitRam| m.write_byte(0x3000, 0x01)),
+    );
+    assert_eq!(
+        c.run_until(&mut m, 100, |_, m| m.ram[0x3000] == 0x01),
+        StopReason::Breakpoint
+    );
+}
+
+#[test]
+fn instruction_bytes_read_once() {
+    // 64 KB of RAM recording the address of every read
+    struct TraceRam {
+        ram: Vec<u8>,
+        reads: Vec<u16>,
+    }
+
+    impl Memory for TraceRam {
+        fn read_byte(&mut self, address: u16) -> u8 {
+            self.reads.push(address);
+            self.ram[address as usize]
+        }
+
+        fn write_byte(&mut self, address: u16, data: u8) {
+            self.ram[address as usize] = data;
+        }
+    }
+
+    // Executes the instruction at 0100h (and the one following an ignored prefix), returning the addresses read
+    fn trace(bytes: &[u8]) -> Vec<u16> {
+        let mut m = TraceRam {
+            ram: vec![0; 0x10000],
+            reads: Vec::new(),
+        };
+        let mut c = CPU::<TraceRam>::default();
+        for (i, byte) in bytes.iter().enumerate() {
+            m.ram[0x0100 + i] = *byte;
+        }
+        // The data accesses are kept away from the instruction
+        c.reg.pc = 0x0100;
+        c.reg.sp = 0x8000;
+        c.reg.set_bc(0x8000);
+        c.reg.set_de(0x8000);
+        c.reg.set_hl(0x8000);
+        c.reg.set_ix(0x8000);
+        c.reg.set_iy(0x8000);
+        c.execute(&mut m);
+        if c.reg.pc == 0x0101 && matches!(bytes[0], 0xDD | 0xFD) {
+            c.execute(&mut m);
+        }
+        m.reads
+    }
+
+    assert_eq!(trace(&[0xDD, 0x77, 0x01]), vec![0x0100, 0x0101, 0x0102]);
+    assert_eq!(
+        trace(&[0xDD, 0xCB, 0x01, 0x06]),
+        vec![0x0100, 0x0101, 0x0102, 0x0103, 0x8001]
+    );
+    assert_eq!(trace(&[0xED, 0xB0]), vec![0x0100, 0x0101, 0x8000]);
+    // Ignored prefix : the byte following it is not read again by the next instruction
+    assert_eq!(trace(&[0xDD, 0x00]), vec![0x0100, 0x0101]);
+
+    for prefix in [
+        &[][..],
+        &[0xCB],
+        &[0xED],
+        &[0xDD],
+        &[0xFD],
+        &[0xDD, 0xCB, 0x40],
+        &[0xFD, 0xCB, 0x40],
+    ] {
+        for opcode in 0..=0xFF {
+            let mut bytes = prefix.to_vec();
+            bytes.extend([opcode, 0x40, 0x40, 0x40]);
+            let reads = trace(&bytes);
+            for address in 0x0100..0x0108 {
+                assert!(
+                    reads.iter().filter(|&&a| a == address).count() <= 1,
+                    "{bytes:02X?} : {address:04X} read more than once ({reads:04X?})"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn paging() {
+    let mut b = Bus::new_paged(0x20000, 0x4000);
+    assert_eq!(b.pages().slots(), 4);
+    assert_eq!(b.pages().pages(), 8);
+    for page in 0..8 {
+        b.pages_mut().map(3, page);
+        b.write_byte(0xC000, page as u8);
+    }
+    // The same page can be mapped into several slots
+    b.pages_mut().map(1, 5);
+    assert_eq!(b.read_byte(0x4000), 0x05);
+    b.write_byte(0x4001, 0xAA);
+    b.pages_mut().map(3, 5);
+    assert_eq!(b.read_byte(0xC001), 0xAA);
+    // Words crossing a slot boundary
+    b.pages_mut().map(0, 6);
+    b.write_word(0x3FFF, 0x1234);
+    assert_eq!(b.read_byte(0x3FFF), 0x34);
+    assert_eq!(b.read_byte(0x4000), 0x12);
+    // ROM pages are protected wherever they are mapped, but can be loaded
+    b.pages_mut().set_rom(2, true);
+    b.pages_mut().map(0, 2);
+    b.write_byte(0x0000, 0xFF);
+    assert_eq!(b.read_byte(0x0000), 0x02);
+    b.clear_mem_slice(0x0000, 0x0000);
+    assert_eq!(b.read_byte(0x0000), 0x00);
+    b.pages_mut().map(2, 2);
+    b.write_byte(0x8000, 0xFF);
+    assert_eq!(b.read_byte(0x8000), 0x00);
+}
+
+#[test]
+#[should_panic]
+fn map_invalid_page() {
+    let mut b = Bus::new_paged(0x10000, 0x4000);
+    b.pages_mut().map(0, 4);
+}
+
+#[test]
+fn bank_switching() {
+    let mut c = CPU::new();
+    let mut b = Bus::new_paged(0x20000, 0x4000);
+    // Pages 4 to 7 switched at C000h by port 7Fh
+    b.set_page_mapper(Box::new(|address, data, pages| {
+        if address & 0xFF == 0x7F {
+            pages.map(3, 4 + (data & 0x03) as usize)
+        }
+    }));
+    b.write_byte(0x0000, 0x3E); // LD A,$01
+    b.write_byte(0x0001, 0x01);
+    b.write_byte(0x0002, 0xD3); // OUT ($7F),A
+    b.write_byte(0x0003, 0x7F);
+    b.write_byte(0x0004, 0x32); // LD ($C000),A
+    b.write_word(0x0005, 0xC000);
+    for _ in 0..3 {
+        c.execute(&mut b);
+    }
+    assert_eq!(b.pages().mapped(3), 5);
+    assert_eq!(b.read_byte(0xC000), 0x01);
+    b.pages_mut().map(3, 3);
+    assert_eq!(b.read_byte(0xC000), 0x00);
+}
+
+#[test]
+fn memory_mapped_device() {
+    // UART : received byte at 3000h, cleared when read by the CPU. Transmitted bytes written to 3001h.
+    struct Uart {
+        rx: u8,
+        tx: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl MemoryDevice for Uart {
+        fn read(&mut self, address: u16) -> u8 {
+            match address {
+                0x3000 => std::mem::take(&mut self.rx),
+                _ => 0xFF,
+            }
+        }
+
+        fn peek(&self, address: u16) -> u8 {
+            match address {
+                0x3000 => self.rx,
+                _ => 0xFF,
+            }
+        }
+
+        fn write(&mut self, address: u16, data: u8) {
+            if address == 0x3001 {
+                self.tx.lock().unwrap().push(data)
+            }
+        }
+    }
+
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    let tx = Arc::new(Mutex::new(Vec::new()));
+    b.add_region(
+        0x3000,
+        0x3001,
+        Region::Device(Box::new(Uart {
+            rx: 0x41,
+            tx: tx.clone(),
+        })),
+    );
+    b.write_byte(0x0000, 0x3A); // LD A,($3000)
+    b.write_word(0x0001, 0x3000);
+    b.write_byte(0x0003, 0x32); // LD ($3001),A
+    b.write_word(0x0004, 0x3001);
+    b.write_byte(0x0006, 0x3A); // LD A,($3000)
+    b.write_word(0x0007, 0x3000);
+    // Peeking has no side effect
+    assert_eq!(b.read_byte(0x3000), 0x41);
+    assert_eq!(b.read_byte(0x3000), 0x41);
+    c.execute(&mut b);
+    assert_eq!(c.reg.a, 0x41);
+    c.execute(&mut b);
+    assert_eq!(*tx.lock().unwrap(), vec![0x41]);
+    // The received byte has been consumed by the CPU
+    c.execute(&mut b);
+    assert_eq!(c.reg.a, 0x00);
+    assert_eq!(b.read_byte(0x3001), 0xFF);
+}
+
+#[test]
+fn bus_is_send() {
+    fn assert_send<T: Send>() {}
+    assert_send::<Bus>();
+}
+
+#[test]
+fn regions() {
+    let mut b = Bus::new(0xFFFF);
+    b.write_byte(0x0000, 0x01);
+    b.write_byte(0x2000, 0x02);
+    let rom = b.add_region(0x0000, 0x3FFF, Region::Rom);
+    b.add_region(0x1000, 0x1FFF, Region::Unmapped);
+    let rom_over_ram = b.add_region(0x8000, 0x8001, Region::RomOverRam(vec![0xAA]));
+    b.set_romspace(0xF000, 0xFFFF);
+    // Several ROM regions, the last region added taking precedence
+    b.write_byte(0x0000, 0xFF);
+    b.write_byte(0xF000, 0xFF);
+    assert_eq!(b.read_byte(0x0000), 0x01);
+    assert_eq!(b.read_byte(0xF000), 0x00);
+    b.write_byte(0x1000, 0x00);
+    assert_eq!(b.read_byte(0x1000), 0xFF);
+    assert_eq!(b.read_byte(0x2000), 0x02);
+    // ROM over RAM : the open bus value is read after the end of the image
+    b.write_word(0x8000, 0x1234);
+    assert_eq!(b.read_byte(0x8000), 0xAA);
+    assert_eq!(b.read_byte(0x8001), 0xFF);
+    b.set_region(rom_over_ram, Region::Ram);
+    assert_eq!(b.read_word(0x8000), 0x1234);
+    // The ROM is switched off
+    b.set_region(rom, Region::Ram);
+    b.write_byte(0x0000, 0xFF);
+    assert_eq!(b.read_byte(0x0000), 0xFF);
+    assert_eq!(b.read_byte(0x1000), 0xFF);
+}
+
+#[test]
+#[should_panic(expected = "is above its end")]
+fn add_region_reversed() {
+    let mut b = Bus::new(0xFFFF);
+    b.add_region(0x2000, 0x1000, Region::Rom);
+}
+
+#[test]
+fn romspace_moved() {
+    let mut b = Bus::new(0xFFFF);
+    // The ROM window moved by a bank switch handler : a single region is updated
+    for start in 0..300 {
+        b.set_romspace(start, start + 0x0FFF);
+    }
+    b.write_byte(0x012A, 0x01);
+    b.write_byte(0x012B, 0x01);
+    b.write_byte(0x112A, 0x01);
+    b.write_byte(0x112B, 0x01);
+    assert_eq!(b.read_byte(0x012A), 0x01);
+    assert_eq!(b.read_byte(0x012B), 0x00);
+    assert_eq!(b.read_byte(0x112A), 0x00);
+    assert_eq!(b.read_byte(0x112B), 0x01);
+    // The regions added after the ROM space still take precedence
+    b.add_region(0x2000, 0x20FF, Region::Unmapped);
+    b.set_romspace(0x0000, 0x3FFF);
+    assert_eq!(b.read_byte(0x2000), 0xFF);
+    b.write_byte(0x3000, 0x01);
+    assert_eq!(b.read_byte(0x3000), 0x00);
+    assert_eq!(b.read_byte(0x012A), 0x01);
+}
+
+#[test]
+#[should_panic(expected = "end of physical memory")]
+fn load_bin_physical_memory() {
+    // 256 bytes of memory : the 118 bytes file does not fit at 00C0h
+    let mut b = Bus::new(0x00FF);
+    b.load_bin("bin/nmi.bin", 0x0080).unwrap();
+    b.load_bin("bin/nmi.bin", 0x00C0).unwrap();
+}
+
+// Runs a CP/M instruction exerciser (zexdoc.com / zexall.com, not distributed with the crate) and returns its console output
+fn run_exerciser(path: &str) -> String {
+    let mut c = CPU::new();
+    let mut b = Bus::new(0xFFFF);
+    b.load_bin(path, 0x0100)
+        .unwrap_or_else(|e| panic!("{path} : {e}"));
+    // RET at 0x0005 : BDOS calls are mocked below
+    b.write_byte(0x0005, 0xC9);
+    b.write_word(0x0006, 0xFF00);
+    c.reg.pc = 0x0100;
+    c.reg.sp = 0xFF00;
+    c.set_unknown_opcode_policy(UnknownOpcodePolicy::Trap);
+    let mut output = String::new();
+    loop {
+        let reason = c.run_until(&mut b, u64::MAX, |c, _| {
+            c.reg.pc == 0x0005 || c.reg.pc == 0x0000
+        });
+        assert_eq!(reason, StopReason::Breakpoint, "{output}");
+        if c.reg.pc == 0x0000 {
+            return output;
+        }
+        match c.reg.c {
+            // C_WRITE
+            0x02 => output.push(c.reg.e as char),
+            // C_WRITESTR
+            0x09 => {
+                let mut a = c.reg.get_de();
+                while b.read_byte(a) != b'$' {
+                    output.push(b.read_byte(a) as char);
+                    a = a.wrapping_add(1);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// cargo test --release -- --ignored zexdoc
+#[test]
+#[ignore = "needs bin/zexdoc.com, runs for minutes"]
+fn zexdoc() {
+    let output = run_exerciser("bin/zexdoc.com");
+    println!("{output}");
+    assert!(output.contains("Tests complete"));
+    assert!(!output.contains("ERROR"));
+}
+
+// cargo test --release -- --ignored zexall : also checks the undocumented X and Y flags
+#[test]
+#[ignore = "needs bin/zexall.com, runs for minutes"]
+fn zexall() {
+    let output = run_exerciser("bin/zexall.com");
+    println!("{output}");
+    assert!(output.contains("Tests complete"));
+    assert!(!output.contains("ERROR"));
+}